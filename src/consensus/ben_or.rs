@@ -0,0 +1,283 @@
+use crate::consensus::Node;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// Source of random bits for a node that sees no majority in a round.
+pub trait Coin {
+    fn flip(&mut self, round: usize) -> bool;
+}
+
+// Flips independently of every other node
+pub struct LocalCoin(StdRng);
+
+impl LocalCoin {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Coin for LocalCoin {
+    fn flip(&mut self, _round: usize) -> bool {
+        self.0.gen()
+    }
+}
+
+// Flips the same bit in a round on every node with the same seed, as if dealt by a trusted dealer
+pub struct CommonCoin(u64);
+
+impl CommonCoin {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl Coin for CommonCoin {
+    fn flip(&mut self, round: usize) -> bool {
+        StdRng::seed_from_u64(self.0 ^ ((round as u64) << 32)).gen()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    // Sender's value at the start of the round
+    Report(usize, bool),
+    // Value reported by a majority, if any
+    Propose(usize, Option<bool>),
+}
+
+/// Randomized binary consensus by Ben-Or, despite `f` crashes of `n > 2f` nodes on an asynchronous network.
+///
+/// No deterministic protocol decides on an asynchronous network with a single crash, but a coin can break ties. Each
+/// round, a node reports its value and waits for `n - f` reports, then proposes a value reported by a majority, if any.
+/// As two majorities overlap, every proposal in a round is of the same value or none. A node then waits for `n - f`
+/// proposals, and decides a value proposed `f + 1` times, adopts a value proposed at least once, or else flips a coin.
+/// A node deciding means every node saw its value proposed, so every value agrees in the next round, where every node
+/// decides. If every value agrees at the start, every node decides it in the first round. Otherwise nodes decide once
+/// enough coins agree, which happens eventually with probability 1, in `O(2^n)` expected rounds for local coins or
+/// `O(1)` for a common coin. A node halts after proposing in the round after deciding, which is all others wait on.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::ben_or::{BenOr, LocalCoin};
+/// use rads::consensus::network::Network;
+///
+/// let nodes = BenOr::cluster(1, &[true, false, true], |i| Box::new(LocalCoin::new(i as u64)) as _);
+/// let mut net = Network::new(nodes);
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// let decision = net.node(0).decision();
+/// assert!(decision.is_some() && net.nodes().iter().all(|p| p.decision() == decision));
+/// ```
+pub struct BenOr {
+    i: usize,
+    n: usize,
+    f: usize,
+    x: bool,
+    round: usize,
+    // Whether the node has proposed this round, and so waits on proposals
+    proposed: bool,
+    reports: HashMap<usize, Vec<bool>>,
+    proposals: HashMap<usize, Vec<Option<bool>>>,
+    // Decision and the round it was made in
+    decision: Option<(bool, usize)>,
+    halted: bool,
+    coin: Box<dyn Coin>,
+}
+
+impl BenOr {
+    pub fn new(i: usize, n: usize, f: usize, x: bool, coin: Box<dyn Coin>) -> Self {
+        assert!(n > 2 * f, "{n} nodes with {f} crashes");
+        Self {
+            i,
+            n,
+            f,
+            x,
+            round: 1,
+            proposed: false,
+            reports: HashMap::new(),
+            proposals: HashMap::new(),
+            decision: None,
+            halted: false,
+            coin,
+        }
+    }
+    // Node i starts with inputs[i] and flips coin(i)
+    pub fn cluster(f: usize, inputs: &[bool], coin: impl Fn(usize) -> Box<dyn Coin>) -> Vec<Self> {
+        (inputs.iter().enumerate())
+            .map(|(i, &x)| Self::new(i, inputs.len(), f, x, coin(i)))
+            .collect()
+    }
+    pub fn round(&self) -> usize {
+        self.round
+    }
+    pub fn decision(&self) -> Option<bool> {
+        self.decision.map(|(v, _)| v)
+    }
+    // Round in which the node decided
+    pub fn decided_round(&self) -> Option<usize> {
+        self.decision.map(|(_, r)| r)
+    }
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    fn broadcast(&self, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        (0..self.n)
+            .filter(|&j| j != self.i)
+            .for_each(|j| out.push((j, msg)));
+    }
+    fn report(&mut self, out: &mut Vec<(usize, Msg)>) {
+        self.reports.entry(self.round).or_default().push(self.x);
+        self.broadcast(Msg::Report(self.round, self.x), out);
+    }
+    // Moves through every phase whose messages have arrived
+    fn progress(&mut self, out: &mut Vec<(usize, Msg)>) {
+        let quorum = self.n - self.f;
+        while !self.halted {
+            if !self.proposed {
+                let Some(reports) = self
+                    .reports
+                    .get(&self.round)
+                    .filter(|rs| rs.len() >= quorum)
+                else {
+                    return;
+                };
+                let n_true = reports.iter().filter(|&&v| v).count();
+                let v = match (2 * n_true > self.n, 2 * (reports.len() - n_true) > self.n) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                };
+                self.proposed = true;
+                self.proposals.entry(self.round).or_default().push(v);
+                self.broadcast(Msg::Propose(self.round, v), out);
+                self.halted = self.decided_round().is_some_and(|r| r < self.round);
+            } else {
+                let Some(proposals) = self
+                    .proposals
+                    .get(&self.round)
+                    .filter(|ps| ps.len() >= quorum)
+                else {
+                    return;
+                };
+                let count = |v| proposals.iter().filter(|&&p| p == Some(v)).count();
+                let (n_true, n_false) = (count(true), count(false));
+                let v = match (n_true, n_false) {
+                    (0, 0) => None,
+                    (_, 0) => Some(true),
+                    _ => Some(false),
+                };
+                if let Some(v) = v.filter(|_| n_true.max(n_false) > self.f) {
+                    self.decision.get_or_insert((v, self.round));
+                }
+                self.x = v.unwrap_or_else(|| self.coin.flip(self.round));
+                self.round += 1;
+                self.proposed = false;
+                self.report(out);
+            }
+        }
+    }
+}
+
+impl Node for BenOr {
+    type Msg = Msg;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg)>) {
+        self.report(out);
+        self.progress(out);
+    }
+    fn recv(&mut self, _from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        if self.halted {
+            return;
+        }
+        match msg {
+            Msg::Report(r, v) => self.reports.entry(r).or_default().push(v),
+            Msg::Propose(r, v) => self.proposals.entry(r).or_default().push(v),
+        }
+        // Forget rounds that have passed
+        self.reports.retain(|&r, _| r >= self.round);
+        self.proposals.retain(|&r, _| r >= self.round);
+        self.progress(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::ben_or::{BenOr, Coin, CommonCoin, LocalCoin};
+    use crate::consensus::network::Network;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn agrees_despite_crashes() {
+        for seed in 0..500 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let f = (n - 1) / 2;
+            let inputs: Vec<_> = (0..n).map(|_| rng.gen()).collect();
+            let common = rng.gen_bool(0.5);
+            let mut net = Network::new(BenOr::cluster(f, &inputs, |i| coin(common, seed, i)));
+            let mut crashes: Vec<_> = (0..n).filter(|_| rng.gen_bool(0.5)).take(f).collect();
+            net.start_all();
+            while net.step(&mut rng) {
+                if rng.gen_bool(0.02) {
+                    if let Some(i) = crashes.pop() {
+                        net.crash(i);
+                    }
+                }
+            }
+
+            let live: Vec<_> = (0..n).filter(|&i| !net.is_crashed(i)).collect();
+            let decision = net.node(live[0]).decision();
+            assert!(decision.is_some(), "seed {seed}");
+            assert!(live.iter().all(|&i| net.node(i).decision() == decision));
+            if inputs.iter().all(|&x| x == inputs[0]) {
+                assert_eq!(decision, Some(inputs[0]));
+                assert!(live.iter().all(|&i| net.node(i).decided_round() == Some(1)));
+            }
+            // Nodes decide within a round of each other
+            let rounds = live.iter().map(|&i| net.node(i).decided_round().unwrap());
+            assert!(rounds.clone().max().unwrap() <= rounds.min().unwrap() + 1);
+        }
+    }
+
+    #[test]
+    fn terminates_with_probability_1() {
+        let n_trials = 300;
+        for (n, f) in [(3, 1), (5, 2), (7, 3)] {
+            let mut means = Vec::new();
+            for common in [false, true] {
+                // Rounds until every node decides, by frequency
+                let mut rounds = BTreeMap::new();
+                for seed in 0..n_trials {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let inputs: Vec<_> = (0..n).map(|i| 2 * i < n).collect();
+                    let mut net =
+                        Network::new(BenOr::cluster(f, &inputs, |i| coin(common, seed, i)));
+                    net.start_all();
+                    net.run(&mut rng);
+                    let r = net.nodes().iter().map(|p| p.decided_round().unwrap()).max();
+                    *rounds.entry(r.unwrap()).or_insert(0) += 1;
+                }
+                let mean =
+                    rounds.iter().map(|(r, k)| r * k).sum::<usize>() as f64 / n_trials as f64;
+                println!("n={n} f={f} common={common}: mean {mean:.2} rounds, {rounds:?}");
+                // A common coin agrees with every value adopted with probability at least 1/2 each round
+                if common {
+                    assert!(mean <= 4.0, "{mean}");
+                }
+                means.push(mean);
+            }
+            assert!(means[1] < means[0]);
+        }
+    }
+
+    fn coin(common: bool, seed: u64, i: usize) -> Box<dyn Coin> {
+        match common {
+            true => Box::new(CommonCoin::new(seed)),
+            false => Box::new(LocalCoin::new(seed << 8 | i as u64)),
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use crate::consensus::Node;
+use anyhow::ensure;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+// Origin of a broadcast and its sequence number there
+pub type Id = (usize, usize);
+
+/// Process that broadcasts values to every node, and delivers values broadcast by any node.
+pub trait Broadcast: Node {
+    type Value;
+
+    fn broadcast(&mut self, v: Self::Value, out: &mut Vec<(usize, Self::Msg)>);
+    // Values delivered in order, by broadcast
+    fn delivered(&self) -> &[(Id, Self::Value)];
+}
+
+/// Checks reliable broadcast over what each node broadcast in order, or `None` if Byzantine, and delivered.
+///
+/// - Integrity: a node delivers each broadcast at most once, and only what an honest origin broadcast.
+/// - Validity: a correct node delivers what it broadcast.
+/// - Agreement: every correct node delivers what a correct node delivers, or what any honest node delivers if uniform.
+pub fn check<V: PartialEq + Debug>(
+    broadcasts: &[Option<Vec<V>>],
+    delivered: &[&[(Id, V)]],
+    correct: &[bool],
+    uniform: bool,
+) -> anyhow::Result<()> {
+    let honest = |i: usize| broadcasts[i].is_some();
+    for (i, ds) in delivered.iter().enumerate().filter(|&(i, _)| honest(i)) {
+        for (k, ((origin, seq), v)) in ds.iter().enumerate() {
+            ensure!(
+                ds[..k].iter().all(|(id, _)| *id != (*origin, *seq)),
+                "Node {i} delivered ({origin}, {seq}) twice"
+            );
+            if let Some(bs) = &broadcasts[*origin] {
+                ensure!(
+                    bs.get(*seq) == Some(v),
+                    "Node {i} delivered {v:?} for ({origin}, {seq}), which broadcast {:?}",
+                    bs.get(*seq)
+                );
+            }
+            if correct[i] || uniform {
+                for j in (0..delivered.len()).filter(|&j| correct[j]) {
+                    ensure!(
+                        delivered[j]
+                            .iter()
+                            .any(|(id, w)| *id == (*origin, *seq) && w == v),
+                        "Node {i} delivered {v:?} for ({origin}, {seq}), but node {j} did not"
+                    );
+                }
+            }
+        }
+        if correct[i] {
+            for (seq, v) in broadcasts[i].iter().flatten().enumerate() {
+                ensure!(
+                    ds.iter().any(|(id, w)| *id == (i, seq) && w == v),
+                    "Node {i} did not deliver its broadcast of {v:?}"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Eager reliable broadcast, which relays each broadcast to every node on first receiving it, despite any crashes.
+///
+/// A node delivers a broadcast as it relays it, so if any correct node delivers it, every correct node receives it. But
+/// a node may deliver then crash before its relays are received, so agreement is not uniform. It takes `O(n^2)`
+/// messages per broadcast.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::broadcast::{Broadcast, Eager};
+/// use rads::consensus::network::Network;
+///
+/// let mut net = Network::new(Eager::cluster(3));
+/// // Crash after only sending to node 1
+/// net.act(0, |p, out| {
+///     p.broadcast("a", out);
+///     out.retain(|&(j, _)| j == 1);
+/// });
+/// net.crash(0);
+/// net.run(&mut rand::thread_rng());
+/// assert_eq!(net.node(2).delivered(), [((0, 0), "a")]);
+/// ```
+pub struct Eager<V> {
+    i: usize,
+    n: usize,
+    seq: usize,
+    delivered: Vec<(Id, V)>,
+    seen: HashSet<Id>,
+}
+
+impl<V: Clone> Eager<V> {
+    pub fn new(i: usize, n: usize) -> Self {
+        Self {
+            i,
+            n,
+            seq: 0,
+            delivered: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+    pub fn cluster(n: usize) -> Vec<Self> {
+        (0..n).map(|i| Self::new(i, n)).collect()
+    }
+    fn relay(&mut self, id: Id, v: V, out: &mut Vec<(usize, (Id, V))>) {
+        if self.seen.insert(id) {
+            (0..self.n)
+                .filter(|&j| j != self.i)
+                .for_each(|j| out.push((j, (id, v.clone()))));
+            self.delivered.push((id, v));
+        }
+    }
+}
+
+impl<V: Clone> Node for Eager<V> {
+    type Msg = (Id, V);
+
+    fn start(&mut self, _out: &mut Vec<(usize, (Id, V))>) {}
+    fn recv(&mut self, _from: usize, (id, v): (Id, V), out: &mut Vec<(usize, (Id, V))>) {
+        self.relay(id, v, out);
+    }
+}
+
+impl<V: Clone> Broadcast for Eager<V> {
+    type Value = V;
+
+    fn broadcast(&mut self, v: V, out: &mut Vec<(usize, (Id, V))>) {
+        self.seq += 1;
+        self.relay((self.i, self.seq - 1), v, out);
+    }
+    fn delivered(&self) -> &[(Id, V)] {
+        &self.delivered
+    }
+}
+
+/// Uniform reliable broadcast, which delivers a broadcast once a majority has relayed it, despite `f` crashes of
+/// `n > 2f` nodes.
+///
+/// A node relays each broadcast on first receiving it, as in eager broadcast, but only delivers it once more than half
+/// of the nodes have relayed it. Then some correct node has relayed it, so every correct node receives it, relays it,
+/// and delivers it once the majority of correct nodes has, even if the node that delivered it first has crashed.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::broadcast::{Broadcast, Uniform};
+/// use rads::consensus::network::Network;
+///
+/// let mut net = Network::new(Uniform::cluster(3));
+/// net.act(0, |p, out| p.broadcast("a", out));
+/// net.run(&mut rand::thread_rng());
+/// assert!(net.nodes().iter().all(|p| p.delivered() == [((0, 0), "a")]));
+/// ```
+pub struct Uniform<V> {
+    i: usize,
+    n: usize,
+    seq: usize,
+    delivered: Vec<(Id, V)>,
+    // Nodes that have relayed each broadcast
+    relayed: HashMap<Id, HashSet<usize>>,
+}
+
+impl<V: Clone> Uniform<V> {
+    pub fn new(i: usize, n: usize) -> Self {
+        Self {
+            i,
+            n,
+            seq: 0,
+            delivered: Vec::new(),
+            relayed: HashMap::new(),
+        }
+    }
+    pub fn cluster(n: usize) -> Vec<Self> {
+        (0..n).map(|i| Self::new(i, n)).collect()
+    }
+    fn recv_from(&mut self, from: usize, id: Id, v: V, out: &mut Vec<(usize, (Id, V))>) {
+        let relayed = self.relayed.entry(id).or_default();
+        if relayed.insert(self.i) {
+            (0..self.n)
+                .filter(|&j| j != self.i)
+                .for_each(|j| out.push((j, (id, v.clone()))));
+        }
+        relayed.insert(from);
+        let is_delivered = self.delivered.iter().any(|(d, _)| *d == id);
+        if 2 * relayed.len() > self.n && !is_delivered {
+            self.delivered.push((id, v));
+        }
+    }
+}
+
+impl<V: Clone> Node for Uniform<V> {
+    type Msg = (Id, V);
+
+    fn start(&mut self, _out: &mut Vec<(usize, (Id, V))>) {}
+    fn recv(&mut self, from: usize, (id, v): (Id, V), out: &mut Vec<(usize, (Id, V))>) {
+        self.recv_from(from, id, v, out);
+    }
+}
+
+impl<V: Clone> Broadcast for Uniform<V> {
+    type Value = V;
+
+    fn broadcast(&mut self, v: V, out: &mut Vec<(usize, (Id, V))>) {
+        self.seq += 1;
+        self.recv_from(self.i, (self.i, self.seq - 1), v, out);
+    }
+    fn delivered(&self) -> &[(Id, V)] {
+        &self.delivered
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Msg<V> {
+    // Value of the sender's broadcast
+    Initial(usize, V),
+    Echo(Id, V),
+    Ready(Id, V),
+}
+
+// Votes of each node for a broadcast, which counts only the first echo and ready of each node
+struct Votes<V> {
+    echoes: HashMap<usize, V>,
+    readies: HashMap<usize, V>,
+    echoed: bool,
+    ready: bool,
+    delivered: bool,
+}
+
+impl<V> Default for Votes<V> {
+    fn default() -> Self {
+        Self {
+            echoes: HashMap::new(),
+            readies: HashMap::new(),
+            echoed: false,
+            ready: false,
+            delivered: false,
+        }
+    }
+}
+
+/// Byzantine reliable broadcast by Bracha, despite `f` Byzantine nodes of `n > 3f`, including the origin.
+///
+/// The origin sends its value, and each node echoes the first value it receives from the origin. A node is ready for
+/// a value once more than `(n + f) / 2` nodes echo it, as any two such sets share a correct node, which echoes one
+/// value, so correct nodes are only ready for one value. A node is also ready once `f + 1` nodes are ready, one of
+/// which is correct, and delivers once `2f + 1` are ready. Then `f + 1` correct nodes are ready, so every correct node
+/// becomes ready, and delivers the same value. An equivocating origin may be delivered by every correct node or none.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::broadcast::{Bracha, Broadcast};
+/// use rads::consensus::network::Network;
+///
+/// let mut net = Network::new(Bracha::cluster(4, 1));
+/// net.act(0, |p, out| p.broadcast("a", out));
+/// net.crash(3);
+/// net.run(&mut rand::thread_rng());
+/// assert!((0..3).all(|i| net.node(i).delivered() == [((0, 0), "a")]));
+/// ```
+pub struct Bracha<V> {
+    i: usize,
+    n: usize,
+    f: usize,
+    seq: usize,
+    delivered: Vec<(Id, V)>,
+    votes: HashMap<Id, Votes<V>>,
+}
+
+impl<V: Clone + Eq> Bracha<V> {
+    pub fn new(i: usize, n: usize, f: usize) -> Self {
+        assert!(n > 3 * f, "{n} nodes with {f} Byzantine");
+        Self {
+            i,
+            n,
+            f,
+            seq: 0,
+            delivered: Vec::new(),
+            votes: HashMap::new(),
+        }
+    }
+    pub fn cluster(n: usize, f: usize) -> Vec<Self> {
+        (0..n).map(|i| Self::new(i, n, f)).collect()
+    }
+    // Sends to every node, including itself
+    fn send_all(&mut self, msg: Msg<V>, out: &mut Vec<(usize, Msg<V>)>) {
+        (0..self.n)
+            .filter(|&j| j != self.i)
+            .for_each(|j| out.push((j, msg.clone())));
+        self.recv(self.i, msg, out);
+    }
+    fn count(votes: &HashMap<usize, V>, v: &V) -> usize {
+        votes.values().filter(|&w| w == v).count()
+    }
+}
+
+impl<V: Clone + Eq> Node for Bracha<V> {
+    type Msg = Msg<V>;
+
+    fn start(&mut self, _out: &mut Vec<(usize, Msg<V>)>) {}
+    fn recv(&mut self, from: usize, msg: Msg<V>, out: &mut Vec<(usize, Msg<V>)>) {
+        let (id, v) = match msg {
+            Msg::Initial(seq, v) => {
+                let votes = self.votes.entry((from, seq)).or_default();
+                if !std::mem::replace(&mut votes.echoed, true) {
+                    self.send_all(Msg::Echo((from, seq), v), out);
+                }
+                return;
+            }
+            Msg::Echo(id, v) => {
+                let votes = self.votes.entry(id).or_default();
+                votes.echoes.entry(from).or_insert(v.clone());
+                (id, v)
+            }
+            Msg::Ready(id, v) => {
+                let votes = self.votes.entry(id).or_default();
+                votes.readies.entry(from).or_insert(v.clone());
+                (id, v)
+            }
+        };
+        let (n, f) = (self.n, self.f);
+        let votes = &self.votes[&id];
+        let (echoes, readies) = (
+            Self::count(&votes.echoes, &v),
+            Self::count(&votes.readies, &v),
+        );
+        if !votes.ready && (2 * echoes > n + f || readies > f) {
+            self.votes.get_mut(&id).unwrap().ready = true;
+            self.send_all(Msg::Ready(id, v.clone()), out);
+        }
+        let votes = self.votes.get_mut(&id).unwrap();
+        if !votes.delivered && Self::count(&votes.readies, &v) > 2 * f {
+            votes.delivered = true;
+            self.delivered.push((id, v));
+        }
+    }
+}
+
+impl<V: Clone + Eq> Broadcast for Bracha<V> {
+    type Value = V;
+
+    fn broadcast(&mut self, v: V, out: &mut Vec<(usize, Msg<V>)>) {
+        self.seq += 1;
+        self.send_all(Msg::Initial(self.seq - 1, v), out);
+    }
+    fn delivered(&self) -> &[(Id, V)] {
+        &self.delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::broadcast::{check, Bracha, Broadcast, Eager, Id, Msg, Uniform};
+    use crate::consensus::network::Network;
+    use crate::consensus::Node;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashSet;
+
+    // Broadcasts from random nodes, some of which crash, possibly mid-broadcast, and returns what each broadcast
+    fn crash_randomly<N: Broadcast<Value = usize>>(
+        net: &mut Network<N>,
+        f: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<Option<Vec<usize>>> {
+        let n = net.len();
+        let mut broadcasts = vec![Some(Vec::new()); n];
+        let mut crashes: Vec<_> = (0..n).filter(|_| rng.gen_bool(0.5)).take(f).collect();
+        for v in 0..20 {
+            let i = rng.gen_range(0..n);
+            if net.is_crashed(i) {
+                continue;
+            }
+            broadcasts[i].as_mut().unwrap().push(v);
+            if crashes.last() == Some(&i) && rng.gen_bool(0.5) {
+                let k = rng.gen_range(0..n);
+                net.act(i, |p, out| {
+                    p.broadcast(v, out);
+                    out.truncate(k);
+                });
+                net.crash(crashes.pop().unwrap());
+            } else {
+                net.act(i, |p, out| p.broadcast(v, out));
+            }
+            for _ in 0..rng.gen_range(0..10) {
+                net.step(rng);
+            }
+        }
+        for i in crashes {
+            net.crash(i);
+            while rng.gen_bool(0.9) && net.step(rng) {}
+        }
+        net.run(rng);
+        broadcasts
+    }
+
+    fn deliveries<N: Broadcast>(net: &Network<N>) -> Vec<&[(Id, N::Value)]> {
+        net.nodes().iter().map(|p| p.delivered()).collect()
+    }
+
+    fn correct<N: Node>(net: &Network<N>) -> Vec<bool> {
+        (0..net.len()).map(|i| !net.is_crashed(i)).collect()
+    }
+
+    #[test]
+    fn eager_is_reliable_despite_crashes() {
+        for seed in 0..300 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let mut net = Network::new(Eager::cluster(n));
+            let broadcasts = crash_randomly(&mut net, n - 1, &mut rng);
+            check(&broadcasts, &deliveries(&net), &correct(&net), false).unwrap();
+        }
+    }
+
+    #[test]
+    fn uniform_is_uniform_despite_crashes() {
+        for seed in 0..300 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let mut net = Network::new(Uniform::cluster(n));
+            let broadcasts = crash_randomly(&mut net, (n - 1) / 2, &mut rng);
+            check(&broadcasts, &deliveries(&net), &correct(&net), true).unwrap();
+        }
+    }
+
+    #[test]
+    fn only_uniform_survives_a_delivering_crash() {
+        // Node 0 only reaches node 1, which delivers and crashes before its relays get out
+        fn run<N: Broadcast<Value = usize>>(nodes: Vec<N>) -> anyhow::Result<()> {
+            let mut net = Network::new(nodes);
+            net.partition(&[0, 1]);
+            net.act(0, |p, out| p.broadcast(0, out));
+            net.run(&mut StdRng::seed_from_u64(0));
+            net.crash(0);
+            net.crash(1);
+            net.heal();
+            let broadcasts = [Some(vec![0]), Some(vec![]), Some(vec![]), Some(vec![])];
+            check(&broadcasts, &deliveries(&net), &correct(&net), true)
+        }
+        assert!(run(Eager::cluster(4)).is_err());
+        run(Uniform::cluster(4)).unwrap();
+    }
+
+    // Node of Bracha's broadcast, or a Byzantine node that equivocates and votes at random
+    enum Process {
+        Honest(Bracha<usize>),
+        Byzantine(usize, usize, Box<StdRng>, HashSet<(Id, bool)>),
+    }
+
+    impl Node for Process {
+        type Msg = Msg<usize>;
+
+        fn start(&mut self, out: &mut Vec<(usize, Msg<usize>)>) {
+            if let Process::Byzantine(i, n, rng, _) = self {
+                for j in (0..*n).filter(|j| j != i) {
+                    out.push((j, Msg::Initial(0, rng.gen_range(0..2))));
+                }
+            }
+        }
+        fn recv(&mut self, from: usize, msg: Msg<usize>, out: &mut Vec<(usize, Msg<usize>)>) {
+            let (i, n, rng, voted) = match self {
+                Process::Honest(p) => return p.recv(from, msg, out),
+                Process::Byzantine(i, n, rng, voted) => (*i, *n, rng, voted),
+            };
+            let id = match msg {
+                Msg::Initial(seq, _) => (from, seq),
+                Msg::Echo(id, _) | Msg::Ready(id, _) => id,
+            };
+            // Votes at most once per broadcast of each kind, so that Byzantine nodes do not flood each other forever
+            for ready in [false, true] {
+                if voted.insert((id, ready)) {
+                    for j in (0..n).filter(|&j| j != i) {
+                        // Nothing, 0 or 1
+                        let v = rng.gen_range(0..3);
+                        match (v, ready) {
+                            (2, _) => {}
+                            (v, false) => out.push((j, Msg::Echo(id, v))),
+                            (v, true) => out.push((j, Msg::Ready(id, v))),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bracha_is_reliable_despite_byzantine_nodes() {
+        for seed in 0..300 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=10);
+            let f = (n - 1) / 3;
+            let byzantine: HashSet<_> = (0..n).filter(|_| rng.gen_bool(0.5)).take(f).collect();
+            let nodes = (0..n)
+                .map(|i| match byzantine.contains(&i) {
+                    true => Process::Byzantine(
+                        i,
+                        n,
+                        Box::new(StdRng::seed_from_u64(seed << 8 | i as u64)),
+                        HashSet::new(),
+                    ),
+                    false => Process::Honest(Bracha::new(i, n, f)),
+                })
+                .collect();
+            let mut net = Network::new(nodes);
+            net.start_all();
+            let mut broadcasts: Vec<_> = (0..n)
+                .map(|i| Some(Vec::new()).filter(|_| !byzantine.contains(&i)))
+                .collect();
+            for _ in 0..10 {
+                let i = rng.gen_range(0..n);
+                if let (Some(bs), Process::Honest(_)) = (&mut broadcasts[i], net.node(i)) {
+                    let v = rng.gen_range(0..2);
+                    bs.push(v);
+                    net.act(i, |p, out| match p {
+                        Process::Honest(p) => p.broadcast(v, out),
+                        Process::Byzantine(..) => unreachable!(),
+                    });
+                }
+                for _ in 0..rng.gen_range(0..20) {
+                    net.step(&mut rng);
+                }
+            }
+            net.run(&mut rng);
+
+            let delivered: Vec<&[(Id, usize)]> = (net.nodes().iter())
+                .map(|p| match p {
+                    Process::Honest(p) => p.delivered(),
+                    Process::Byzantine(..) => &[],
+                })
+                .collect();
+            let correct: Vec<_> = (0..n).map(|i| !byzantine.contains(&i)).collect();
+            check(&broadcasts, &delivered, &correct, false).unwrap();
+        }
+    }
+}
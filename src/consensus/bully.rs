@@ -0,0 +1,201 @@
+use crate::consensus::Node;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    Election,
+    // A higher node is alive and takes over the election
+    Answer,
+    Coordinator,
+}
+
+/// Leader election by Garcia-Molina's Bully algorithm on a fully connected network with crash failures, where node `i`
+/// has priority `i`.
+///
+/// A node that suspects the leader has crashed starts an election by messaging every higher node. A higher node answers
+/// and starts its own election, so the node waits for a coordinator instead, restarting the election if none arrives
+/// within a longer timeout. A node without an answer before its timeout is the highest node alive, and announces itself
+/// as coordinator to every other node. This takes `O(n^2)` messages if the lowest node starts, but `n - 1` if the
+/// highest does.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::bully::Bully;
+/// use rads::consensus::network::Network;
+///
+/// let mut net = Network::new(Bully::cluster(4));
+/// net.crash(3);
+/// net.start(0); // 0 suspects 3
+/// net.run(&mut rand::thread_rng());
+/// assert!((0..3).all(|i| net.node(i).leader() == Some(2)));
+/// ```
+pub struct Bully {
+    i: usize,
+    n: usize,
+    leader: Option<usize>,
+    electing: bool,
+    answered: bool,
+    // Timed out once waiting for a coordinator
+    waited: bool,
+}
+
+impl Bully {
+    // Initially, every node believes the highest node is the leader
+    pub fn new(i: usize, n: usize) -> Self {
+        Self {
+            i,
+            n,
+            leader: n.checked_sub(1),
+            electing: false,
+            answered: false,
+            waited: false,
+        }
+    }
+    pub fn cluster(n: usize) -> Vec<Self> {
+        (0..n).map(|i| Self::new(i, n)).collect()
+    }
+    pub fn leader(&self) -> Option<usize> {
+        self.leader
+    }
+    pub fn is_electing(&self) -> bool {
+        self.electing
+    }
+    fn coordinate(&mut self, out: &mut Vec<(usize, Msg)>) {
+        self.leader = Some(self.i);
+        self.electing = false;
+        (0..self.n)
+            .filter(|&j| j != self.i)
+            .for_each(|j| out.push((j, Msg::Coordinator)));
+    }
+}
+
+impl Node for Bully {
+    type Msg = Msg;
+
+    // Starts an election, e.g. as a request to the leader timed out
+    fn start(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if self.electing {
+            return;
+        }
+        self.electing = true;
+        self.answered = false;
+        self.waited = false;
+        if self.i + 1 == self.n {
+            return self.coordinate(out);
+        }
+        (self.i + 1..self.n).for_each(|j| out.push((j, Msg::Election)));
+    }
+    fn recv(&mut self, from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        match msg {
+            Msg::Election => {
+                out.push((from, Msg::Answer));
+                self.start(out);
+            }
+            Msg::Answer => self.answered = true,
+            Msg::Coordinator => {
+                // A lower coordinator means this node has recovered, so bully it
+                if from < self.i {
+                    self.start(out);
+                } else {
+                    self.leader = Some(from);
+                    self.electing = false;
+                }
+            }
+        }
+    }
+    fn timeout(&mut self, out: &mut Vec<(usize, Msg)>) {
+        match (self.electing, self.answered, self.waited) {
+            // Every higher node has crashed
+            (true, false, _) => self.coordinate(out),
+            (true, true, false) => self.waited = true,
+            // The higher node that answered crashed before announcing itself
+            (true, true, true) => {
+                self.electing = false;
+                self.start(out);
+            }
+            _ => {}
+        }
+    }
+    fn has_timer(&self) -> bool {
+        self.electing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::bully::Bully;
+    use crate::consensus::network::Network;
+    use rand::Rng;
+
+    #[test]
+    fn reelects_after_leader_crashes() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let n = rng.gen_range(2..=10);
+            let mut net = Network::new(Bully::cluster(n));
+            // Crash leaders until only one node is left
+            for leader in (1..n).rev() {
+                assert!((0..=leader).all(|i| net.node(i).leader() == Some(leader)));
+                net.crash(leader);
+                let alive: Vec<_> = (0..leader).collect();
+                let suspects: Vec<_> = alive.iter().filter(|_| rng.gen_bool(0.5)).collect();
+                net.start(alive[rng.gen_range(0..alive.len())]);
+                suspects.into_iter().for_each(|&i| net.start(i));
+                net.run(&mut rng);
+                assert!(alive.iter().all(|&i| !net.node(i).is_electing()));
+            }
+            assert_eq!(net.node(0).leader(), Some(0));
+        }
+    }
+
+    #[test]
+    fn crashes_during_election() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n = rng.gen_range(2..=10);
+            let mut net = Network::new(Bully::cluster(n));
+            net.crash(n - 1);
+            net.start(rng.gen_range(0..n - 1));
+            // Any node but 0 may crash at any step, including before announcing itself
+            let crashed: Vec<_> = (1..n - 1).filter(|_| rng.gen_bool(0.3)).collect();
+            for &i in &crashed {
+                (0..rng.gen_range(0..10)).for_each(|_| {
+                    net.step(&mut rng);
+                });
+                net.crash(i);
+            }
+            net.run(&mut rng);
+            let alive: Vec<_> = (0..n).filter(|&i| !net.is_crashed(i)).collect();
+            // A coordinator may crash after announcing itself, so its followers eventually suspect it
+            while let Some(&i) =
+                (alive.iter()).find(|&&i| net.node(i).leader().is_none_or(|j| net.is_crashed(j)))
+            {
+                net.start(i);
+                net.run(&mut rng);
+            }
+            let highest = alive.iter().max().copied();
+            assert!(alive.iter().all(|&i| net.node(i).leader() == highest));
+        }
+    }
+
+    #[test]
+    fn messages() {
+        for n in 2..=20 {
+            // The lowest node starts, so each node i < n - 1 sends n - 1 - i elections, which all but n - 1 answer
+            let mut net = Network::new(Bully::cluster(n));
+            net.crash(n - 1);
+            net.start(0);
+            net.run(&mut rand::thread_rng());
+            assert_eq!(
+                net.n_sent(),
+                (n - 1) * n / 2 + (n - 2) * (n - 1) / 2 + n - 1
+            );
+
+            let mut net = Network::new(Bully::cluster(n));
+            net.crash(n - 1);
+            net.start(n - 2);
+            net.run(&mut rand::thread_rng());
+            // Election to n - 1, then coordinator to the others
+            assert_eq!(net.n_sent(), 1 + n - 1);
+        }
+    }
+}
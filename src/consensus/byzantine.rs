@@ -0,0 +1,181 @@
+use crate::consensus::SyncNode;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+/// Behaviour of a Byzantine node in a synchronous protocol of binary messages, which may send anything to anyone.
+pub trait Strategy {
+    // Messages that Byzantine node i of n sends in the round, which may differ by receiver
+    fn send(&mut self, i: usize, n: usize, round: usize, out: &mut Vec<(usize, bool)>);
+}
+
+// Sends nothing, as if crashed
+pub struct Silent;
+
+impl Strategy for Silent {
+    fn send(&mut self, _i: usize, _n: usize, _round: usize, _out: &mut Vec<(usize, bool)>) {}
+}
+
+// Tells the lower half of nodes true, and the upper half false
+pub struct Equivocating;
+
+impl Strategy for Equivocating {
+    fn send(&mut self, i: usize, n: usize, _round: usize, out: &mut Vec<(usize, bool)>) {
+        (0..n)
+            .filter(|&j| j != i)
+            .for_each(|j| out.push((j, 2 * j < n)));
+    }
+}
+
+// Equivocates with n copies of each message, to stuff ballots
+pub struct Flooding;
+
+impl Strategy for Flooding {
+    fn send(&mut self, i: usize, n: usize, round: usize, out: &mut Vec<(usize, bool)>) {
+        let mut msgs = Vec::new();
+        Equivocating.send(i, n, round, &mut msgs);
+        (0..n).for_each(|_| out.extend(&msgs));
+    }
+}
+
+// Sends a random value, or nothing, to each node
+pub struct Random(StdRng);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Strategy for Random {
+    fn send(&mut self, i: usize, n: usize, _round: usize, out: &mut Vec<(usize, bool)>) {
+        for j in (0..n).filter(|&j| j != i) {
+            if self.0.gen_bool(2.0 / 3.0) {
+                out.push((j, self.0.gen()));
+            }
+        }
+    }
+}
+
+// Values of the first message of each sender, as a Byzantine node may send many to stuff ballots
+pub fn first_by_sender(msgs: &[(usize, bool)]) -> BTreeMap<usize, bool> {
+    let mut first = BTreeMap::new();
+    for &(j, v) in msgs {
+        first.entry(j).or_insert(v);
+    }
+    first
+}
+
+/// Node of a synchronous network, which is either honest or Byzantine by some strategy.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::byzantine::{Equivocating, Process};
+/// use rads::consensus::phase_king::PhaseKing;
+/// use rads::consensus::sync_network::SyncNetwork;
+///
+/// let nodes = PhaseKing::cluster(1, &[true, false, true, false]);
+/// let mut net = SyncNetwork::new(Process::corrupt(nodes, vec![(0, Box::new(Equivocating) as _)]));
+/// net.run(6);
+/// let decisions: Vec<_> = (1..4).map(|i| net.node(i).honest().unwrap().decision()).collect();
+/// assert!(decisions.iter().all(|&d| d.is_some() && d == decisions[0]));
+/// ```
+pub enum Process<N> {
+    Honest(N),
+    Byzantine {
+        i: usize,
+        n: usize,
+        strategy: Box<dyn Strategy>,
+    },
+}
+
+impl<N> Process<N> {
+    // Replaces each node i of byzantine by its strategy
+    pub fn corrupt(nodes: Vec<N>, byzantine: Vec<(usize, Box<dyn Strategy>)>) -> Vec<Self> {
+        let n = nodes.len();
+        let mut nodes: Vec<_> = nodes.into_iter().map(Process::Honest).collect();
+        for (i, strategy) in byzantine {
+            nodes[i] = Process::Byzantine { i, n, strategy };
+        }
+        nodes
+    }
+    pub fn honest(&self) -> Option<&N> {
+        match self {
+            Process::Honest(node) => Some(node),
+            Process::Byzantine { .. } => None,
+        }
+    }
+}
+
+impl<N: SyncNode<Msg = bool>> SyncNode for Process<N> {
+    type Msg = bool;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, bool)>) {
+        match self {
+            Process::Honest(node) => node.send(round, out),
+            Process::Byzantine { i, n, strategy } => strategy.send(*i, *n, round, out),
+        }
+    }
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, bool)>) {
+        if let Process::Honest(node) = self {
+            node.recv(round, msgs);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::consensus::byzantine::{Equivocating, Flooding, Process, Random, Silent, Strategy};
+    use crate::consensus::sync_network::SyncNetwork;
+    use crate::consensus::SyncNode;
+
+    // Checks agreement and validity for every binary input and every set of f Byzantine nodes with the same strategy
+    pub(crate) fn check_exhaustively<N: SyncNode<Msg = bool>>(
+        n: usize,
+        f: usize,
+        rounds: usize,
+        cluster: impl Fn(&[bool]) -> Vec<N>,
+        decision: impl Fn(&N) -> Option<bool>,
+    ) {
+        let sets: Vec<u32> = (0..1u32 << n)
+            .filter(|s| s.count_ones() as usize == f)
+            .collect();
+        let mut seed = 0;
+        for bits in 0..1 << n {
+            let inputs: Vec<_> = (0..n).map(|i| bits >> i & 1 == 1).collect();
+            for &set in &sets {
+                // Silent, equivocating, flooding, or random twice with new seeds
+                for k in 0..5 {
+                    let mut strategy = || -> Box<dyn Strategy> {
+                        seed += 1;
+                        match k {
+                            0 => Box::new(Silent),
+                            1 => Box::new(Equivocating),
+                            2 => Box::new(Flooding),
+                            _ => Box::new(Random::new(seed)),
+                        }
+                    };
+                    let byzantine = (0..n)
+                        .filter(|&i| set >> i & 1 == 1)
+                        .map(|i| (i, strategy()))
+                        .collect();
+                    let mut net = SyncNetwork::new(Process::corrupt(cluster(&inputs), byzantine));
+                    net.run(rounds);
+
+                    let honest: Vec<_> = (0..n).filter(|&i| set >> i & 1 == 0).collect();
+                    let decisions: Vec<_> = (honest.iter())
+                        .map(|&i| decision(net.node(i).honest().unwrap()))
+                        .collect();
+                    assert!(decisions[0].is_some());
+                    assert!(
+                        decisions.iter().all(|&d| d == decisions[0]),
+                        "Disagreed on {inputs:?} with Byzantine {set:b}"
+                    );
+                    if honest.iter().all(|&i| inputs[i] == inputs[honest[0]]) {
+                        assert_eq!(decisions[0], Some(inputs[honest[0]]));
+                    }
+                }
+            }
+        }
+    }
+}
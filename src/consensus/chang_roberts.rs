@@ -0,0 +1,160 @@
+use crate::consensus::Node;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    // Candidate id, which is forwarded while it is the largest seen
+    Elect(u64),
+    // Leader id, which is forwarded once around the ring
+    Elected(u64),
+}
+
+/// Leader election by Chang & Roberts on a unidirectional ring of unique ids, where node `i` sends to `i + 1`.
+///
+/// A node forwards candidates larger than its id, discards smaller ones, and becomes a candidate itself on seeing a
+/// smaller one if it has yet to take part. The maximum id is the only candidate that returns to its node, which then
+/// informs the ring. This takes `O(n log n)` messages on average, but `O(n^2)` if ids decrease along the ring.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::chang_roberts::ChangRoberts;
+/// use rads::consensus::network::Network;
+///
+/// let ids = [3, 1, 4, 5, 2];
+/// let mut net = Network::new(ChangRoberts::ring(&ids));
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// assert!(net.nodes().iter().all(|p| p.leader() == Some(5)));
+/// ```
+pub struct ChangRoberts {
+    i: usize,
+    n: usize,
+    id: u64,
+    participant: bool,
+    leader: Option<u64>,
+}
+
+impl ChangRoberts {
+    pub fn new(i: usize, n: usize, id: u64) -> Self {
+        Self {
+            i,
+            n,
+            id,
+            participant: false,
+            leader: None,
+        }
+    }
+    // Node i has id ids[i]
+    pub fn ring(ids: &[u64]) -> Vec<Self> {
+        (ids.iter().enumerate())
+            .map(|(i, &id)| Self::new(i, ids.len(), id))
+            .collect()
+    }
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    pub fn leader(&self) -> Option<u64> {
+        self.leader
+    }
+    fn next(&self) -> usize {
+        (self.i + 1) % self.n
+    }
+}
+
+impl Node for ChangRoberts {
+    type Msg = Msg;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if !self.participant {
+            self.participant = true;
+            out.push((self.next(), Msg::Elect(self.id)));
+        }
+    }
+    fn recv(&mut self, _from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        match msg {
+            Msg::Elect(id) if id > self.id => {
+                self.participant = true;
+                out.push((self.next(), msg));
+            }
+            Msg::Elect(id) if id < self.id => self.start(out),
+            Msg::Elect(_) => {
+                self.leader = Some(self.id);
+                out.push((self.next(), Msg::Elected(self.id)));
+            }
+            Msg::Elected(id) => {
+                self.participant = false;
+                if id != self.id {
+                    self.leader = Some(id);
+                    out.push((self.next(), msg));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::chang_roberts::ChangRoberts;
+    use crate::consensus::network::Network;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    #[test]
+    fn elects_max_id() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n = rng.gen_range(1..=20);
+            let mut ids: Vec<u64> = (0..n).map(|_| rng.gen()).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids.shuffle(&mut rng);
+            let mut net = Network::new(ChangRoberts::ring(&ids));
+            // Any nodes may start
+            (0..ids.len())
+                .filter(|_| rng.gen_bool(0.5))
+                .for_each(|i| net.start(i));
+            net.start(rng.gen_range(0..ids.len()));
+            net.run(&mut rng);
+            let max = ids.iter().max().copied();
+            assert!(net.nodes().iter().all(|p| p.leader() == max));
+        }
+    }
+
+    #[test]
+    fn worst_and_best_case_messages() {
+        for n in 1..=30u64 {
+            // Each candidate travels until the maximum, so n (n + 1) / 2 elect messages, and n elected messages
+            let decreasing: Vec<_> = (1..=n).rev().collect();
+            assert_eq!(n_sent(&decreasing) as u64, n * (n + 1) / 2 + n);
+            // Each candidate but the maximum is discarded by the next node
+            let increasing: Vec<_> = (1..=n).collect();
+            assert_eq!(n_sent(&increasing) as u64, 2 * n - 1 + n);
+        }
+    }
+
+    #[test]
+    fn average_case_messages() {
+        let mut rng = rand::thread_rng();
+        let (n, trials) = (64, 200);
+        let total: usize = (0..trials)
+            .map(|_| {
+                let mut ids: Vec<_> = (0..n as u64).collect();
+                ids.shuffle(&mut rng);
+                n_sent(&ids) - n
+            })
+            .sum();
+        // Expect n H_n elect messages, where H_n is the nth harmonic number
+        let expected = n as f64 * (1..=n).map(|k| 1.0 / k as f64).sum::<f64>();
+        let mean = total as f64 / trials as f64;
+        assert!(
+            (mean - expected).abs() < 0.1 * expected,
+            "{mean} vs {expected}"
+        );
+    }
+
+    fn n_sent(ids: &[u64]) -> usize {
+        let mut net = Network::new(ChangRoberts::ring(ids));
+        net.start_all();
+        net.run(&mut rand::thread_rng());
+        net.n_sent()
+    }
+}
@@ -0,0 +1,129 @@
+use crate::consensus::byzantine::first_by_sender;
+use crate::consensus::SyncNode;
+
+/// Byzantine consensus on binary values by rotating coordinators, or Berman & Garay's phase queen, over a synchronous,
+/// fully connected network of `n >= 4f + 1` nodes with up to `f` Byzantine.
+///
+/// Each of `f + 1` phases has two rounds. First, every node broadcasts its value, and takes the majority it receives.
+/// Then node `k` coordinates phase `k` by broadcasting its majority, which a node adopts unless its own majority has
+/// more than `n / 2 + f` votes. If so, every honest node has the same majority, as Byzantine nodes cast at most `f`
+/// votes, so the coordinator agrees. One phase has an honest coordinator, after which honest nodes agree, and keep
+/// their value, as honest votes alone exceed `n / 2 + f`.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::coordinator::Coordinator;
+/// use rads::consensus::sync_network::SyncNetwork;
+///
+/// let mut net = SyncNetwork::new(Coordinator::cluster(1, &[true, false, true, false, true]));
+/// net.run(4);
+/// assert!(net.nodes().iter().all(|p| p.decision() == Some(true)));
+/// ```
+pub struct Coordinator {
+    i: usize,
+    n: usize,
+    f: usize,
+    value: bool,
+    // Majority of the first round of the phase, and its votes
+    majority: bool,
+    votes: usize,
+    decision: Option<bool>,
+}
+
+impl Coordinator {
+    pub fn new(i: usize, n: usize, f: usize, value: bool) -> Self {
+        assert!(n > 4 * f, "{n} nodes for {f} Byzantine");
+        Self {
+            i,
+            n,
+            f,
+            value,
+            majority: value,
+            votes: 0,
+            decision: None,
+        }
+    }
+    // Node i proposes values[i], tolerating f Byzantine nodes
+    pub fn cluster(f: usize, values: &[bool]) -> Vec<Self> {
+        (values.iter().enumerate())
+            .map(|(i, &v)| Self::new(i, values.len(), f, v))
+            .collect()
+    }
+    pub fn value(&self) -> bool {
+        self.value
+    }
+    // After 2(f + 1) rounds
+    pub fn decision(&self) -> Option<bool> {
+        self.decision
+    }
+}
+
+impl SyncNode for Coordinator {
+    type Msg = bool;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, bool)>) {
+        let (phase, coordinate) = (round / 2, round % 2 == 1);
+        if phase > self.f || (coordinate && phase != self.i) {
+            return;
+        }
+        let v = if coordinate {
+            self.majority
+        } else {
+            self.value
+        };
+        (0..self.n)
+            .filter(|&j| j != self.i)
+            .for_each(|j| out.push((j, v)));
+    }
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, bool)>) {
+        let (phase, coordinate) = (round / 2, round % 2 == 1);
+        if phase > self.f {
+            return;
+        }
+        if !coordinate {
+            // One vote per sender
+            let votes = first_by_sender(&msgs);
+            let n_true = votes.values().filter(|&&v| v).count() + self.value as usize;
+            let n_false = votes.values().filter(|&&v| !v).count() + !self.value as usize;
+            (self.majority, self.votes) = if n_true > n_false {
+                (true, n_true)
+            } else {
+                (false, n_false)
+            };
+        } else {
+            // A missing coordinator is Byzantine, so any value will do
+            let coordinator = match msgs.iter().find(|&&(j, _)| j == phase) {
+                _ if phase == self.i => self.majority,
+                Some(&(_, v)) => v,
+                None => false,
+            };
+            self.value = if 2 * self.votes > self.n + 2 * self.f {
+                self.majority
+            } else {
+                coordinator
+            };
+            if phase == self.f {
+                self.decision = Some(self.value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::byzantine::tests::check_exhaustively;
+    use crate::consensus::coordinator::Coordinator;
+
+    #[test]
+    fn exhaustive_agreement_and_validity() {
+        for (n, f) in [(1, 0), (3, 0), (5, 1), (6, 1), (9, 2)] {
+            check_exhaustively(
+                n,
+                f,
+                2 * (f + 1),
+                |vs| Coordinator::cluster(f, vs),
+                Coordinator::decision,
+            );
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Source of the current time in milliseconds, so that a detector runs in real time or in a simulation.
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+// Milliseconds since created
+pub struct SystemClock(Instant);
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        self.0.elapsed().as_millis() as u64
+    }
+}
+
+// Time set by a simulation, which is shared by clones
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock(Rc<Cell<u64>>);
+
+impl ManualClock {
+    pub fn set(&self, t: u64) {
+        self.0.set(t);
+    }
+    pub fn advance(&self, dt: u64) {
+        self.0.set(self.0.get() + dt);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+/// Failure detector of a node, which suspects other nodes of crashing when it stops hearing their heartbeats.
+///
+/// On an asynchronous network, a slow node cannot be told from a crashed one, so a detector may falsely suspect a live
+/// node, and is only as accurate as the network is timely.
+pub trait FailureDetector {
+    // Records a heartbeat from node i, now
+    fn heartbeat(&mut self, i: usize);
+    fn suspects(&self, i: usize) -> bool;
+    fn suspected(&self, n: usize) -> Vec<usize> {
+        (0..n).filter(|&i| self.suspects(i)).collect()
+    }
+}
+
+/// Failure detector by a fixed timeout, which suspects a node that has not sent a heartbeat within it.
+///
+/// It is perfect if heartbeats are delayed by less than the timeout minus their period, and otherwise falsely suspects
+/// nodes each time a heartbeat is late.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::failure_detector::{FailureDetector, Heartbeat, ManualClock};
+///
+/// let clock = ManualClock::default();
+/// let mut detector = Heartbeat::new(clock.clone(), 2, 100);
+/// clock.advance(60);
+/// detector.heartbeat(0);
+/// clock.advance(60);
+/// assert_eq!(detector.suspected(2), vec![1]);
+/// ```
+pub struct Heartbeat<C: Clock> {
+    clock: C,
+    timeout: u64,
+    // Time of the last heartbeat of each node, from the start
+    last: Vec<u64>,
+}
+
+impl<C: Clock> Heartbeat<C> {
+    pub fn new(clock: C, n: usize, timeout: u64) -> Self {
+        let last = vec![clock.now(); n];
+        Self {
+            clock,
+            timeout,
+            last,
+        }
+    }
+}
+
+impl<C: Clock> FailureDetector for Heartbeat<C> {
+    fn heartbeat(&mut self, i: usize) {
+        self.last[i] = self.clock.now();
+    }
+    fn suspects(&self, i: usize) -> bool {
+        self.clock.now() - self.last[i] > self.timeout
+    }
+}
+
+/// Eventually perfect failure detector ◇P, which raises the timeout of a node each time it falsely suspects it.
+///
+/// If heartbeats are eventually delayed by at most some unknown bound, the timeout of each live node eventually exceeds
+/// it, and then the detector never suspects a live node again. A crashed node stops sending heartbeats, so it is
+/// eventually suspected forever. Then a protocol that waits on suspicions, like a rotating coordinator, terminates
+/// once the network is timely.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::failure_detector::{EventuallyPerfect, FailureDetector, ManualClock};
+///
+/// let clock = ManualClock::default();
+/// let mut detector = EventuallyPerfect::new(clock.clone(), 1, 100, 50);
+/// clock.advance(120);
+/// assert!(detector.suspects(0));
+/// // Late, so wait longer next time
+/// detector.heartbeat(0);
+/// clock.advance(120);
+/// assert!(!detector.suspects(0));
+/// assert_eq!(detector.timeout(0), 150);
+/// ```
+pub struct EventuallyPerfect<C: Clock> {
+    clock: C,
+    // Added to the timeout of a falsely suspected node
+    increment: u64,
+    timeouts: Vec<u64>,
+    last: Vec<u64>,
+}
+
+impl<C: Clock> EventuallyPerfect<C> {
+    pub fn new(clock: C, n: usize, timeout: u64, increment: u64) -> Self {
+        let last = vec![clock.now(); n];
+        Self {
+            clock,
+            increment,
+            timeouts: vec![timeout; n],
+            last,
+        }
+    }
+    pub fn timeout(&self, i: usize) -> u64 {
+        self.timeouts[i]
+    }
+}
+
+impl<C: Clock> FailureDetector for EventuallyPerfect<C> {
+    fn heartbeat(&mut self, i: usize) {
+        if self.suspects(i) {
+            self.timeouts[i] += self.increment;
+        }
+        self.last[i] = self.clock.now();
+    }
+    fn suspects(&self, i: usize) -> bool {
+        self.clock.now() - self.last[i] > self.timeouts[i]
+    }
+}
+
+/// Phi-accrual failure detector by Hayashibara et al., which estimates how unlikely the silence since the last heartbeat
+/// of a node is, given a normal distribution fitted to its recent heartbeat intervals.
+///
+/// The suspicion level is `phi = -log10(P(interval > silence))`, so suspecting when `phi > threshold` would falsely
+/// suspect a node with probability about `10^-threshold` if intervals were normal. The timeout then adapts to the mean
+/// and jitter of each node's heartbeats, rather than being tuned for the worst. Intervals are bootstrapped with an
+/// expected interval, so that a node that never sends a heartbeat is suspected.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::failure_detector::{FailureDetector, ManualClock, PhiAccrual};
+///
+/// let clock = ManualClock::default();
+/// let mut detector = PhiAccrual::new(clock.clone(), 1, 8.0, 100, 100);
+/// for _ in 0..10 {
+///     clock.advance(100);
+///     detector.heartbeat(0);
+/// }
+/// clock.advance(100);
+/// assert!(detector.phi(0) < 1.0);
+/// clock.advance(200);
+/// assert!(detector.suspects(0));
+/// ```
+pub struct PhiAccrual<C: Clock> {
+    clock: C,
+    threshold: f64,
+    // Number of recent intervals to fit
+    window: usize,
+    intervals: Vec<VecDeque<u64>>,
+    last: Vec<u64>,
+}
+
+impl<C: Clock> PhiAccrual<C> {
+    pub fn new(clock: C, n: usize, threshold: f64, window: usize, expected: u64) -> Self {
+        let last = vec![clock.now(); n];
+        Self {
+            clock,
+            threshold,
+            window,
+            intervals: vec![VecDeque::from([expected]); n],
+            last,
+        }
+    }
+    // Suspicion level of node i, which grows with its silence
+    pub fn phi(&self, i: usize) -> f64 {
+        let intervals = &self.intervals[i];
+        let k = intervals.len() as f64;
+        let mean = intervals.iter().sum::<u64>() as f64 / k;
+        let var = intervals
+            .iter()
+            .map(|&t| (t as f64 - mean).powi(2))
+            .sum::<f64>()
+            / k;
+        // Floor the deviation, so that perfectly regular heartbeats do not make the slightest delay suspicious
+        let sd = var.sqrt().max(mean / 10.0).max(1.0);
+        let y = (self.clock.now() - self.last[i]) as f64 - mean;
+        // Logistic approximation of the normal tail, which is accurate to 10^-4
+        let y = y / sd;
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        let p = match y > 0.0 {
+            true => e / (1.0 + e),
+            false => 1.0 - 1.0 / (1.0 + e),
+        };
+        -p.max(f64::MIN_POSITIVE).log10()
+    }
+}
+
+impl<C: Clock> FailureDetector for PhiAccrual<C> {
+    fn heartbeat(&mut self, i: usize) {
+        let now = self.clock.now();
+        if self.intervals[i].len() == self.window {
+            self.intervals[i].pop_front();
+        }
+        self.intervals[i].push_back(now - self.last[i]);
+        self.last[i] = now;
+    }
+    fn suspects(&self, i: usize) -> bool {
+        self.phi(i) > self.threshold
+    }
+}
+
+/// False suspicions and detection latencies of a failure detector, observed against the times that nodes crashed.
+///
+/// A false suspicion is a live node becoming suspected, and the detection latency of a crashed node is the time from
+/// its crash until it is suspected from then on.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::failure_detector::{Heartbeat, ManualClock, Metrics};
+///
+/// let clock = ManualClock::default();
+/// let detector = Heartbeat::new(clock.clone(), 2, 100);
+/// let mut metrics = Metrics::new(2);
+/// // Node 1 crashes at 50, and node 0 is slow
+/// for t in (0..=200).step_by(10) {
+///     clock.set(t);
+///     metrics.observe(t, &detector, &[None, Some(50)]);
+/// }
+/// assert_eq!(metrics.false_suspicions(), 1);
+/// assert_eq!(metrics.latencies(), vec![None, Some(60)]);
+/// ```
+pub struct Metrics {
+    suspected: Vec<bool>,
+    false_suspicions: usize,
+    last_false_suspicion: Option<u64>,
+    // Time each crashed node has been suspected since, if it has
+    detected: Vec<Option<u64>>,
+    crashed: Vec<Option<u64>>,
+}
+
+impl Metrics {
+    pub fn new(n: usize) -> Self {
+        Self {
+            suspected: vec![false; n],
+            false_suspicions: 0,
+            last_false_suspicion: None,
+            detected: vec![None; n],
+            crashed: vec![None; n],
+        }
+    }
+    // Observes whom the detector suspects now, given when each node has crashed
+    pub fn observe(&mut self, now: u64, detector: &impl FailureDetector, crashed: &[Option<u64>]) {
+        for (i, &crash) in crashed.iter().enumerate() {
+            let suspects = detector.suspects(i);
+            let crash = crash.filter(|&t| t <= now);
+            if suspects && !self.suspected[i] && crash.is_none() {
+                self.false_suspicions += 1;
+                self.last_false_suspicion = Some(now);
+            }
+            self.detected[i] = match (suspects, crash) {
+                (true, Some(_)) => self.detected[i].or(Some(now)),
+                _ => None,
+            };
+            self.suspected[i] = suspects;
+            self.crashed[i] = crash;
+        }
+    }
+    pub fn false_suspicions(&self) -> usize {
+        self.false_suspicions
+    }
+    pub fn last_false_suspicion(&self) -> Option<u64> {
+        self.last_false_suspicion
+    }
+    // Time from each node crashing until it was suspected from then on, if it has crashed and is suspected
+    pub fn latencies(&self) -> Vec<Option<u64>> {
+        (self.detected.iter().zip(&self.crashed))
+            .map(|(&detected, &crashed)| Some(detected? - crashed?))
+            .collect()
+    }
+    pub fn mean_latency(&self) -> Option<f64> {
+        let latencies: Vec<_> = self.latencies().into_iter().flatten().collect();
+        match latencies.len() {
+            0 => None,
+            k => Some(latencies.iter().sum::<u64>() as f64 / k as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::failure_detector::{
+        EventuallyPerfect, FailureDetector, Heartbeat, ManualClock, Metrics, PhiAccrual,
+    };
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const N: usize = 8;
+    const PERIOD: u64 = 100;
+    // Heartbeats are delayed by up to 1s and lost before GST, and delayed by up to 50ms afterwards
+    const GST: u64 = 20_000;
+    const END: u64 = 60_000;
+
+    // Simulates heartbeats from nodes to the detector, where the last 3 nodes crash at random times, which it returns
+    fn simulate<D: FailureDetector>(
+        seed: u64,
+        detector: impl FnOnce(ManualClock) -> D,
+    ) -> (Metrics, Vec<Option<u64>>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let clock = ManualClock::default();
+        let mut detector = detector(clock.clone());
+        let crashed: Vec<_> = (0..N)
+            .map(|i| Some(rng.gen_range(0..END - 10_000)).filter(|_| i >= N - 3))
+            .collect();
+        let mut arrivals = Vec::new();
+        for (i, crash) in crashed.iter().enumerate() {
+            for sent in (0..crash.unwrap_or(END)).step_by(PERIOD as usize) {
+                let delay = match sent < GST {
+                    true if rng.gen_bool(0.1) => continue,
+                    true if rng.gen_bool(0.05) => rng.gen_range(0..1000),
+                    _ => rng.gen_range(0..50),
+                };
+                arrivals.push((sent + delay, i));
+            }
+        }
+        arrivals.sort_unstable();
+
+        let mut metrics = Metrics::new(N);
+        let mut arrivals = arrivals.into_iter().peekable();
+        for t in (0..END).step_by(10) {
+            clock.set(t);
+            while let Some((_, i)) = arrivals.next_if(|&(at, _)| at <= t) {
+                detector.heartbeat(i);
+            }
+            metrics.observe(t, &detector, &crashed);
+        }
+        (metrics, crashed)
+    }
+
+    #[test]
+    fn heartbeat_is_perfect_once_timely() {
+        for seed in 0..20 {
+            let (metrics, crashed) = simulate(seed, |clock| Heartbeat::new(clock, N, 200));
+            assert!(metrics.last_false_suspicion().unwrap() < GST + 1000);
+            // Within the timeout of the last heartbeat arriving
+            for (l, crash) in metrics.latencies().iter().zip(crashed).skip(N - 3) {
+                let delay = if crash.unwrap() < GST { 1000 } else { 50 };
+                assert!(l.is_some_and(|l| l <= 200 + delay + 10), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn eventually_perfect_stops_suspecting_live_nodes() {
+        for seed in 0..20 {
+            // Too short to ever be accurate, until raised
+            let (metrics, _) = simulate(seed, |clock| EventuallyPerfect::new(clock, N, 50, 50));
+            assert!(metrics.false_suspicions() > 0);
+            assert!(
+                metrics.last_false_suspicion().unwrap() < GST + 1000,
+                "seed {seed}"
+            );
+            assert!(metrics.latencies()[N - 3..].iter().all(Option::is_some));
+        }
+        let clock = ManualClock::default();
+        let mut detector = EventuallyPerfect::new(clock.clone(), 1, 50, 50);
+        for _ in 0..10 {
+            clock.advance(120);
+            detector.heartbeat(0);
+        }
+        assert_eq!(detector.timeout(0), 150);
+    }
+
+    #[test]
+    fn phi_accrual_adapts_to_intervals() {
+        let clock = ManualClock::default();
+        let mut detector = PhiAccrual::new(clock.clone(), 2, 8.0, 100, 100);
+        let mut rng = StdRng::seed_from_u64(0);
+        // Node 0 is regular, while node 1 is jittery, until both send a heartbeat at once
+        for k in 1..=100 {
+            clock.set(100 * k - rng.gen_range(0..50));
+            detector.heartbeat(1);
+            clock.set(100 * k);
+            detector.heartbeat(0);
+        }
+        detector.heartbeat(1);
+        // Suspicion grows with silence, faster for the regular node once past the mean interval
+        let phis: Vec<_> = (0..10)
+            .map(|_| {
+                clock.advance(20);
+                (detector.phi(0), detector.phi(1))
+            })
+            .collect();
+        assert!(phis
+            .windows(2)
+            .all(|w| w[0].0 <= w[1].0 && w[0].1 <= w[1].1));
+        assert!(phis[..4]
+            .iter()
+            .all(|&(regular, jittery)| regular < 1.0 && jittery < 1.0));
+        assert!(phis[5..].iter().all(|(regular, jittery)| regular > jittery));
+        clock.advance(20);
+        assert_eq!(detector.suspected(2), vec![0]);
+    }
+
+    #[test]
+    fn compares_detectors() {
+        let n_seeds = 20;
+        let mut results = Vec::new();
+        for name in ["Heartbeat", "Eventually perfect", "Phi accrual"] {
+            let (mut false_suspicions, mut latency) = (0, 0.0);
+            for seed in 0..n_seeds {
+                let (metrics, _) = match name {
+                    "Heartbeat" => simulate(seed, |clock| Heartbeat::new(clock, N, 200)),
+                    "Eventually perfect" => {
+                        simulate(seed, |clock| EventuallyPerfect::new(clock, N, 200, 50))
+                    }
+                    _ => simulate(seed, |clock| PhiAccrual::new(clock, N, 3.0, 100, PERIOD)),
+                };
+                assert!(metrics.latencies()[N - 3..].iter().all(Option::is_some));
+                false_suspicions += metrics.false_suspicions();
+                latency += metrics.mean_latency().unwrap() / n_seeds as f64;
+            }
+            println!("{name}: {false_suspicions} false suspicions, {latency:.0}ms mean latency");
+            results.push((false_suspicions, latency));
+        }
+        // Adapting the timeout trades a little latency for fewer false suspicions
+        assert!(results[1].0 < results[0].0 && results[2].0 < results[0].0);
+        assert!(results.iter().all(|&(_, latency)| latency < 500.0));
+    }
+}
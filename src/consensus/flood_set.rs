@@ -0,0 +1,177 @@
+use crate::consensus::SyncNode;
+use std::collections::BTreeSet;
+
+/// Consensus by FloodSet with up to `f` crash failures on a synchronous, fully connected network.
+///
+/// Each node floods the set of values it has seen for `f + 1` rounds, then decides the least value of the set. Of
+/// `f + 1` rounds, one has no crash, so every live node sees the same values by the end of it, and later rounds add
+/// none. This takes `(f + 1) n (n - 1)` messages without crashes. With only `f` rounds, a chain of crashes mid-broadcast
+/// may reveal a value to just one live node in the last round.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::flood_set::FloodSet;
+/// use rads::consensus::sync_network::SyncNetwork;
+///
+/// let mut net = SyncNetwork::new(FloodSet::cluster(1, &[3, 1, 4]));
+/// net.crash(1, 0, vec![2]); // 1 only reaches 2 before crashing
+/// net.run(2);
+/// assert_eq!((net.node(0).decision(), net.node(2).decision()), (Some(&1), Some(&1)));
+/// ```
+pub struct FloodSet<V> {
+    i: usize,
+    n: usize,
+    f: usize,
+    seen: BTreeSet<V>,
+    decision: Option<V>,
+}
+
+impl<V: Ord + Clone> FloodSet<V> {
+    pub fn new(i: usize, n: usize, f: usize, v: V) -> Self {
+        Self {
+            i,
+            n,
+            f,
+            seen: BTreeSet::from([v]),
+            decision: None,
+        }
+    }
+    // Node i proposes values[i], tolerating f crashes
+    pub fn cluster(f: usize, values: &[V]) -> Vec<Self> {
+        (values.iter().enumerate())
+            .map(|(i, v)| Self::new(i, values.len(), f, v.clone()))
+            .collect()
+    }
+    pub fn seen(&self) -> &BTreeSet<V> {
+        &self.seen
+    }
+    pub fn decision(&self) -> Option<&V> {
+        self.decision.as_ref()
+    }
+}
+
+impl<V: Ord + Clone> SyncNode for FloodSet<V> {
+    type Msg = BTreeSet<V>;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, BTreeSet<V>)>) {
+        if round <= self.f {
+            (0..self.n)
+                .filter(|&j| j != self.i)
+                .for_each(|j| out.push((j, self.seen.clone())));
+        }
+    }
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, BTreeSet<V>)>) {
+        if round > self.f {
+            return;
+        }
+        msgs.into_iter().for_each(|(_, vs)| self.seen.extend(vs));
+        if round == self.f {
+            self.decision = self.seen.first().cloned();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::flood_set::FloodSet;
+    use crate::consensus::sync_network::SyncNetwork;
+
+    #[test]
+    fn exhaustive_agreement_and_validity() {
+        for (n, f) in [(2, 1), (3, 1), (3, 2), (4, 1), (4, 2)] {
+            for_each_run(n, f, f, |values, net| {
+                let alive: Vec<_> = (0..n).filter(|&i| !net.is_crashed(i)).collect();
+                let decision = net.node(alive[0]).decision();
+                assert!(decision.is_some_and(|v| values.contains(v)));
+                if values.iter().all(|&v| v == values[0]) {
+                    assert_eq!(decision, Some(&values[0]));
+                }
+                assert!(alive.iter().all(|&i| net.node(i).decision() == decision));
+            });
+        }
+    }
+
+    #[test]
+    fn f_rounds_are_not_enough() {
+        for (n, f) in [(3, 1), (4, 2)] {
+            let mut disagreed = false;
+            // Only tolerating f - 1 crashes, so deciding after f rounds, with two nodes left to disagree
+            for_each_run(n, f - 1, f, |_, net| {
+                let decisions: Vec<_> = (0..n)
+                    .filter(|&i| !net.is_crashed(i))
+                    .map(|i| net.node(i).decision())
+                    .collect();
+                disagreed |= decisions.iter().any(|&d| d != decisions[0]);
+            });
+            assert!(disagreed, "n={n} f={f}");
+        }
+    }
+
+    #[test]
+    fn messages() {
+        for n in 1..=10 {
+            for f in 0..n {
+                let mut net = SyncNetwork::new(FloodSet::cluster(f, &vec![0; n]));
+                net.run(f + 2);
+                assert_eq!(net.n_sent(), (f + 1) * n * (n - 1));
+            }
+        }
+    }
+
+    // Runs FloodSet tolerating f crashes for every binary input and every pattern of at most crashes crashes
+    fn for_each_run(
+        n: usize,
+        f: usize,
+        crashes: usize,
+        mut check: impl FnMut(&[u8], &SyncNetwork<FloodSet<u8>>),
+    ) {
+        let mut patterns = Vec::new();
+        crash_patterns(n, f + 1, crashes, &mut Vec::new(), &mut patterns);
+        for bits in 0..1 << n {
+            let values: Vec<u8> = (0..n).map(|i| (bits >> i) & 1).collect();
+            for pattern in &patterns {
+                let mut net = SyncNetwork::new(FloodSet::cluster(f, &values));
+                for (i, crash) in pattern.iter().enumerate() {
+                    if let Some((round, receivers)) = crash {
+                        net.crash(i, *round, receivers.clone());
+                    }
+                }
+                net.run(f + 1);
+                check(&values, &net);
+            }
+        }
+    }
+
+    // Each node either survives, or crashes in some round after reaching a subset of the other nodes
+    type Crash = Option<(usize, Vec<usize>)>;
+    fn crash_patterns(
+        n: usize,
+        rounds: usize,
+        crashes: usize,
+        pattern: &mut Vec<Crash>,
+        out: &mut Vec<Vec<Crash>>,
+    ) {
+        let i = pattern.len();
+        if i == n {
+            // Some node must survive
+            if pattern.iter().any(Option::is_none) {
+                out.push(pattern.clone());
+            }
+            return;
+        }
+        pattern.push(None);
+        crash_patterns(n, rounds, crashes, pattern, out);
+        pattern.pop();
+        if crashes == 0 {
+            return;
+        }
+        for round in 0..rounds {
+            for subset in (0..1 << n).filter(|s| s >> i & 1 == 0) {
+                let receivers = (0..n).filter(|&j| subset >> j & 1 == 1).collect();
+                pattern.push(Some((round, receivers)));
+                crash_patterns(n, rounds, crashes - 1, pattern, out);
+                pattern.pop();
+            }
+        }
+    }
+}
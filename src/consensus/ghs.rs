@@ -0,0 +1,391 @@
+use crate::consensus::Node;
+use std::collections::VecDeque;
+
+// Edge weight, made unique by its endpoints
+pub type Weight = (u64, usize, usize);
+
+const INF: Weight = (u64::MAX, usize::MAX, usize::MAX);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    Connect(usize),
+    // Level, fragment name and state of the fragment
+    Initiate(usize, Weight, State),
+    Test(usize, Weight),
+    Accept,
+    Reject,
+    // Minimum outgoing edge of the subtree
+    Report(Weight),
+    ChangeRoot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Sleeping,
+    Find,
+    Found,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EdgeState {
+    Basic,
+    Branch,
+    Rejected,
+}
+
+/// Minimum spanning tree by Gallager, Humblet & Spira (GHS) over a connected graph with weighted edges.
+///
+/// Each node starts as a fragment of level 0. A fragment finds its minimum outgoing edge by testing the minimum basic
+/// edge of each node, and reporting the minimum towards its core edge. It then connects over that edge, merging with a
+/// fragment of the same level into a fragment of the next level with that edge as its core, or being absorbed by a
+/// fragment of higher level. Messages that a node cannot answer yet are deferred. Once the core finds no outgoing edge,
+/// the branch edges form the MST, after `O(n log n + m)` messages.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::ghs::Ghs;
+/// use rads::consensus::network::Network;
+///
+/// // Square 0 - 1 - 2 - 3 - 0 with a heavy edge 3 - 0
+/// let edges = [(0, 1, 1), (1, 2, 2), (2, 3, 3), (3, 0, 4)];
+/// let mut net = Network::new(Ghs::graph(4, &edges));
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// assert_eq!(net.node(3).branches(), vec![2]);
+/// assert_eq!(net.node(0).branches(), vec![1]);
+/// ```
+pub struct Ghs {
+    i: usize,
+    // (neighbour, weight) of each edge
+    edges: Vec<(usize, Weight)>,
+    se: Vec<EdgeState>,
+    sn: State,
+    level: usize,
+    name: Weight,
+    best_edge: Option<usize>,
+    best_wt: Weight,
+    test_edge: Option<usize>,
+    in_branch: Option<usize>,
+    find_count: usize,
+    deferred: VecDeque<(usize, Msg)>,
+    halted: bool,
+}
+
+impl Ghs {
+    // Node i with (neighbour, weight) of each of its edges
+    pub fn new(i: usize, edges: &[(usize, u64)]) -> Self {
+        let edges: Vec<_> = (edges.iter())
+            .map(|&(j, w)| (j, (w, i.min(j), i.max(j))))
+            .collect();
+        Self {
+            i,
+            se: vec![EdgeState::Basic; edges.len()],
+            edges,
+            sn: State::Sleeping,
+            level: 0,
+            name: INF,
+            best_edge: None,
+            best_wt: INF,
+            test_edge: None,
+            in_branch: None,
+            find_count: 0,
+            deferred: VecDeque::new(),
+            halted: false,
+        }
+    }
+    // Nodes 0..n with undirected edges (u, v, weight)
+    pub fn graph(n: usize, edges: &[(usize, usize, u64)]) -> Vec<Self> {
+        let mut adj = vec![Vec::new(); n];
+        for &(u, v, w) in edges {
+            adj[u].push((v, w));
+            adj[v].push((u, w));
+        }
+        (adj.iter().enumerate())
+            .map(|(i, es)| Self::new(i, es))
+            .collect()
+    }
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+    pub fn level(&self) -> usize {
+        self.level
+    }
+    // Neighbours over MST edges
+    pub fn branches(&self) -> Vec<usize> {
+        let mut bs: Vec<_> = (0..self.edges.len())
+            .filter(|&e| self.se[e] == EdgeState::Branch)
+            .map(|e| self.edges[e].0)
+            .collect();
+        bs.sort_unstable();
+        bs
+    }
+    // Towards the core, where the core node with the smaller index is the root
+    pub fn parent(&self) -> Option<usize> {
+        let p = self.edges[self.in_branch?].0;
+        (!self.halted || p < self.i).then_some(p)
+    }
+    pub fn children(&self) -> Vec<usize> {
+        let parent = self.parent();
+        (self.branches().into_iter())
+            .filter(|&j| Some(j) != parent)
+            .collect()
+    }
+
+    fn edge(&self, j: usize) -> usize {
+        (self.edges.iter().position(|&(k, _)| k == j)).expect("Message from a non-neighbour")
+    }
+    fn weight(&self, e: usize) -> Weight {
+        self.edges[e].1
+    }
+    fn send(&self, e: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        out.push((self.edges[e].0, msg));
+    }
+
+    fn wakeup(&mut self, out: &mut Vec<(usize, Msg)>) {
+        self.sn = State::Found;
+        self.level = 0;
+        self.find_count = 0;
+        let m = (0..self.edges.len()).min_by_key(|&e| self.weight(e));
+        match m {
+            Some(m) => {
+                self.se[m] = EdgeState::Branch;
+                self.send(m, Msg::Connect(0), out);
+            }
+            // Only node of the graph
+            None => self.halted = true,
+        }
+    }
+    // Returns false to defer the message
+    fn handle(&mut self, e: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) -> bool {
+        if self.sn == State::Sleeping {
+            self.wakeup(out);
+        }
+        match msg {
+            Msg::Connect(l) if l < self.level => {
+                self.se[e] = EdgeState::Branch;
+                self.send(e, Msg::Initiate(self.level, self.name, self.sn), out);
+                if self.sn == State::Find {
+                    self.find_count += 1;
+                }
+            }
+            Msg::Connect(_) if self.se[e] == EdgeState::Basic => return false,
+            Msg::Connect(_) => {
+                let msg = Msg::Initiate(self.level + 1, self.weight(e), State::Find);
+                self.send(e, msg, out);
+            }
+            Msg::Initiate(l, f, s) => {
+                (self.level, self.name, self.sn) = (l, f, s);
+                self.in_branch = Some(e);
+                self.best_edge = None;
+                self.best_wt = INF;
+                for k in 0..self.edges.len() {
+                    if k != e && self.se[k] == EdgeState::Branch {
+                        self.send(k, msg, out);
+                        if s == State::Find {
+                            self.find_count += 1;
+                        }
+                    }
+                }
+                if s == State::Find {
+                    self.test(out);
+                }
+            }
+            Msg::Test(l, _) if l > self.level => return false,
+            Msg::Test(_, f) if f != self.name => self.send(e, Msg::Accept, out),
+            Msg::Test(..) => {
+                if self.se[e] == EdgeState::Basic {
+                    self.se[e] = EdgeState::Rejected;
+                }
+                if self.test_edge != Some(e) {
+                    self.send(e, Msg::Reject, out);
+                } else {
+                    self.test(out);
+                }
+            }
+            Msg::Accept => {
+                self.test_edge = None;
+                if self.weight(e) < self.best_wt {
+                    self.best_edge = Some(e);
+                    self.best_wt = self.weight(e);
+                }
+                self.report(out);
+            }
+            Msg::Reject => {
+                if self.se[e] == EdgeState::Basic {
+                    self.se[e] = EdgeState::Rejected;
+                }
+                self.test(out);
+            }
+            Msg::Report(w) if Some(e) != self.in_branch => {
+                self.find_count -= 1;
+                if w < self.best_wt {
+                    self.best_wt = w;
+                    self.best_edge = Some(e);
+                }
+                self.report(out);
+            }
+            Msg::Report(_) if self.sn == State::Find => return false,
+            Msg::Report(w) if w > self.best_wt => self.change_root(out),
+            Msg::Report(w) => {
+                if w == INF && self.best_wt == INF {
+                    self.halted = true;
+                }
+            }
+            Msg::ChangeRoot => self.change_root(out),
+        }
+        true
+    }
+    // Tests the minimum basic edge, if any
+    fn test(&mut self, out: &mut Vec<(usize, Msg)>) {
+        self.test_edge = (0..self.edges.len())
+            .filter(|&e| self.se[e] == EdgeState::Basic)
+            .min_by_key(|&e| self.weight(e));
+        match self.test_edge {
+            Some(e) => self.send(e, Msg::Test(self.level, self.name), out),
+            None => self.report(out),
+        }
+    }
+    fn report(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if self.find_count == 0 && self.test_edge.is_none() {
+            self.sn = State::Found;
+            let e = self.in_branch.unwrap();
+            self.send(e, Msg::Report(self.best_wt), out);
+        }
+    }
+    fn change_root(&mut self, out: &mut Vec<(usize, Msg)>) {
+        let e = self.best_edge.unwrap();
+        if self.se[e] == EdgeState::Branch {
+            self.send(e, Msg::ChangeRoot, out);
+        } else {
+            self.send(e, Msg::Connect(self.level), out);
+            self.se[e] = EdgeState::Branch;
+        }
+    }
+}
+
+impl Node for Ghs {
+    type Msg = Msg;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if self.sn == State::Sleeping {
+            self.wakeup(out);
+        }
+    }
+    fn recv(&mut self, from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        let e = self.edge(from);
+        if !self.handle(e, msg, out) {
+            self.deferred.push_back((e, msg));
+            return;
+        }
+        // Retry deferred messages until none can be handled
+        let mut n_tries = self.deferred.len();
+        while n_tries > 0 {
+            let (e, msg) = self.deferred.pop_front().unwrap();
+            if self.handle(e, msg, out) {
+                n_tries = self.deferred.len();
+            } else {
+                self.deferred.push_back((e, msg));
+                n_tries -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::ghs::Ghs;
+    use crate::consensus::network::Network;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    #[test]
+    fn agrees_with_kruskal() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..300 {
+            let n = rng.gen_range(1..=20);
+            let (p, ties) = (rng.gen_range(0.0..0.6), rng.gen_bool(0.5));
+            let edges = rand_graph(&mut rng, n, p, ties);
+            let mut net = Network::new(Ghs::graph(n, &edges));
+            // Any nodes may wake up spontaneously
+            net.start(rng.gen_range(0..n));
+            (0..n)
+                .filter(|_| rng.gen_bool(0.3))
+                .for_each(|i| net.start(i));
+            net.run(&mut rng);
+
+            let mut mst = vec![Vec::new(); n];
+            for (u, v, _) in kruskal(n, &edges) {
+                mst[u].push(v);
+                mst[v].push(u);
+            }
+            mst.iter_mut().for_each(|vs| vs.sort_unstable());
+            let roots: Vec<_> = (0..n).filter(|&i| net.node(i).parent().is_none()).collect();
+            assert_eq!(roots.len(), 1);
+            for (i, p) in net.nodes().iter().enumerate() {
+                assert_eq!(p.branches(), mst[i]);
+                if let Some(j) = p.parent() {
+                    assert!(net.node(j).children().contains(&i));
+                }
+            }
+            // Only the 2 core nodes detect termination
+            assert_eq!(
+                net.nodes().iter().filter(|p| p.is_halted()).count(),
+                n.min(2)
+            );
+        }
+    }
+
+    #[test]
+    fn levels_at_most_log_n() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let n = rng.gen_range(1..=64);
+            let edges = rand_graph(&mut rng, n, 0.2, false);
+            let mut net = Network::new(Ghs::graph(n, &edges));
+            net.start_all();
+            net.run(&mut rng);
+            // A fragment of level l has at least 2^l nodes
+            let max_level = net.nodes().iter().map(Ghs::level).max().unwrap();
+            assert!(1 << max_level <= n);
+            // At most 2m test-reject messages, and 5n per level
+            assert!(net.n_sent() <= 2 * edges.len() + 5 * n * (max_level + 1));
+        }
+    }
+
+    // Random connected graph, as a random tree with extra edges, with distinct or tied weights
+    fn rand_graph(rng: &mut impl Rng, n: usize, p: f64, ties: bool) -> Vec<(usize, usize, u64)> {
+        let mut edges: Vec<_> = (1..n).map(|i| (rng.gen_range(0..i), i)).collect();
+        for i in 0..n {
+            for j in 0..i {
+                if rng.gen_bool(p) && !edges.contains(&(j, i)) {
+                    edges.push((j, i));
+                }
+            }
+        }
+        let mut ws: Vec<u64> = (0..edges.len() as u64).collect();
+        ws.shuffle(rng);
+        (edges.into_iter().zip(ws))
+            .map(|((u, v), w)| (u, v, if ties { w % 3 } else { w }))
+            .collect()
+    }
+
+    // Centralized MST, breaking ties by endpoints like GHS
+    fn kruskal(n: usize, edges: &[(usize, usize, u64)]) -> Vec<(usize, usize, u64)> {
+        let mut sorted = edges.to_vec();
+        sorted.sort_by_key(|&(u, v, w)| (w, u.min(v), u.max(v)));
+        let mut root: Vec<_> = (0..n).collect();
+        fn find(root: &mut [usize], i: usize) -> usize {
+            if root[i] != i {
+                root[i] = find(root, root[i]);
+            }
+            root[i]
+        }
+        (sorted.into_iter())
+            .filter(|&(u, v, _)| {
+                let (ru, rv) = (find(&mut root, u), find(&mut root, v));
+                root[ru] = rv;
+                ru != rv
+            })
+            .collect()
+    }
+}
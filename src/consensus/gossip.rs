@@ -0,0 +1,333 @@
+use crate::consensus::Node;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Number of updates from each origin that a replica has, which are always a prefix of the origin's updates.
+///
+/// Like a [VectorClock](crate::order::vector_clock::VectorClock), `u < v` if v has every update of u and more, and
+/// they are concurrent (`None`) if each has an update the other lacks.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::gossip::VersionVector;
+///
+/// let (u, v) = (VersionVector::from(vec![1, 0]), VersionVector::from(vec![1, 2]));
+/// assert!(u < v);
+/// assert_eq!(u.partial_cmp(&VersionVector::from(vec![0, 1])), None);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionVector(Vec<usize>);
+
+impl VersionVector {
+    pub fn new(n: usize) -> Self {
+        Self(vec![0; n])
+    }
+}
+
+impl From<Vec<usize>> for VersionVector {
+    fn from(v: Vec<usize>) -> Self {
+        Self(v)
+    }
+}
+
+impl std::ops::Index<usize> for VersionVector {
+    type Output = usize;
+    fn index(&self, j: usize) -> &usize {
+        &self.0[j]
+    }
+}
+
+impl PartialOrd for VersionVector {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.0.len() != other.0.len() {
+            return None;
+        }
+        (self.0.iter().zip(&other.0)).try_fold(Ordering::Equal, |acc, (s, t)| {
+            match (acc, s.cmp(t)) {
+                (Ordering::Less, Ordering::Greater) | (Ordering::Greater, Ordering::Less) => None,
+                (Ordering::Equal, ord) | (ord, Ordering::Equal) => Some(ord),
+                (ord, _) => Some(ord),
+            }
+        })
+    }
+}
+
+// Write of a key, numbered by its origin and timestamped by a Lamport clock to order concurrent writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Update {
+    pub origin: usize,
+    pub seq: usize,
+    pub time: (usize, usize),
+    pub key: u64,
+    pub value: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // The initiator sends its missing updates to the peer
+    Push,
+    // The peer sends its missing updates to the initiator
+    Pull,
+    PushPull,
+}
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+    // Initiator's versions if it pulls
+    Syn(Option<VersionVector>),
+    // Peer's versions, and the updates the initiator lacks if it pulls
+    Ack(VersionVector, Vec<Update>),
+    // Updates the peer lacks
+    Delta(Vec<Update>),
+}
+
+/// Replica of a key-value store, which converges with others by gossiping with a random peer on each timeout.
+///
+/// Each write is an update numbered by its origin, so a replica's updates are summarised by a version vector. On a
+/// timeout, a replica exchanges version vectors with a random peer, and either sends the updates the peer lacks (push),
+/// receives those it lacks (pull), or both. So only the delta is transferred, and a lost message is repaired by a later
+/// exchange. An update reaches every replica in `O(log n)` rounds with high probability, as the number of replicas
+/// that have it roughly doubles each round, and push-pull finishes fastest as pulls reach the last few replicas. The
+/// store is last-writer-wins by Lamport timestamp, so replicas with the same version vector have the same state.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::gossip::{Mode, Replica};
+/// use rads::consensus::network::Network;
+///
+/// let mut net = Network::new(Replica::cluster(8, Mode::PushPull));
+/// net.act(0, |p, _| p.write(1, 5));
+/// net.act(7, |p, _| p.write(2, 6));
+/// net.run_for(&mut rand::thread_rng(), 20);
+/// assert!(Replica::converged(net.nodes()));
+/// assert_eq!(net.node(3).get(1), Some(5));
+/// ```
+pub struct Replica {
+    i: usize,
+    n: usize,
+    mode: Mode,
+    version: VersionVector,
+    // Updates by origin, in order
+    log: Vec<Vec<Update>>,
+    // Value of each key, and the time of its write
+    store: BTreeMap<u64, (u64, (usize, usize))>,
+    // Lamport clock
+    time: usize,
+    rng: StdRng,
+    n_received: usize,
+}
+
+impl Replica {
+    pub fn new(i: usize, n: usize, mode: Mode) -> Self {
+        Self {
+            i,
+            n,
+            mode,
+            version: VersionVector::new(n),
+            log: vec![Vec::new(); n],
+            store: BTreeMap::new(),
+            time: 0,
+            rng: StdRng::seed_from_u64(i as u64),
+            n_received: 0,
+        }
+    }
+    pub fn cluster(n: usize, mode: Mode) -> Vec<Self> {
+        (0..n).map(|i| Self::new(i, n, mode)).collect()
+    }
+    // Whether every replica has every update, and so the same state
+    pub fn converged(replicas: &[Self]) -> bool {
+        replicas.iter().all(|r| r.version == replicas[0].version)
+    }
+    pub fn version(&self) -> &VersionVector {
+        &self.version
+    }
+    pub fn get(&self, key: u64) -> Option<u64> {
+        self.store.get(&key).map(|&(v, _)| v)
+    }
+    // Updates received from peers, including duplicates of concurrent exchanges
+    pub fn n_received(&self) -> usize {
+        self.n_received
+    }
+    pub fn write(&mut self, key: u64, value: u64) {
+        self.time += 1;
+        self.apply(Update {
+            origin: self.i,
+            seq: self.version[self.i] + 1,
+            time: (self.time, self.i),
+            key,
+            value,
+        });
+    }
+
+    fn apply(&mut self, u: Update) {
+        // Skip duplicates, as updates of each origin arrive in order
+        if u.seq != self.version.0[u.origin] + 1 {
+            return;
+        }
+        self.version.0[u.origin] = u.seq;
+        self.log[u.origin].push(u);
+        self.time = self.time.max(u.time.0);
+        let (value, time) = self.store.entry(u.key).or_insert((u.value, u.time));
+        if u.time > *time {
+            (*value, *time) = (u.value, u.time);
+        }
+    }
+    // Updates that a replica with the version lacks, none if it has every update of this one
+    fn delta(&self, version: &VersionVector) -> Vec<Update> {
+        if version >= &self.version {
+            return Vec::new();
+        }
+        (self.log.iter().enumerate())
+            .flat_map(|(j, us)| &us[version[j].min(us.len())..])
+            .copied()
+            .collect()
+    }
+    fn recv_delta(&mut self, delta: Vec<Update>) {
+        self.n_received += delta.len();
+        delta.into_iter().for_each(|u| self.apply(u));
+    }
+}
+
+impl Node for Replica {
+    type Msg = Msg;
+
+    fn start(&mut self, _out: &mut Vec<(usize, Msg)>) {}
+    fn recv(&mut self, from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        match msg {
+            Msg::Syn(version) => {
+                let delta = version.map(|v| self.delta(&v)).unwrap_or_default();
+                out.push((from, Msg::Ack(self.version.clone(), delta)));
+            }
+            Msg::Ack(version, delta) => {
+                self.recv_delta(delta);
+                let delta = self.delta(&version);
+                if self.mode != Mode::Pull && !delta.is_empty() {
+                    out.push((from, Msg::Delta(delta)));
+                }
+            }
+            Msg::Delta(delta) => self.recv_delta(delta),
+        }
+    }
+    fn timeout(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if self.n > 1 {
+            let j = (self.i + self.rng.gen_range(1..self.n)) % self.n;
+            let version = Some(self.version.clone()).filter(|_| self.mode != Mode::Push);
+            out.push((j, Msg::Syn(version)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::gossip::{Mode, Msg, Replica, VersionVector};
+    use crate::consensus::network::Network;
+    use crate::consensus::Node;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Rounds of gossip until every replica has every write, which are made by random replicas in the first rounds
+    fn converge(
+        n: usize,
+        mode: Mode,
+        loss: f64,
+        n_writes: usize,
+        rng: &mut StdRng,
+    ) -> (Network<Replica>, usize) {
+        let mut net = Network::new(Replica::cluster(n, mode));
+        net.set_loss(loss);
+        for k in 0..n_writes {
+            let (i, key, value) = (rng.gen_range(0..n), rng.gen_range(0..10), k as u64);
+            net.act(i, |p, _| p.write(key, value));
+            if rng.gen_bool(0.5) {
+                net.run_for(rng, 1);
+            }
+        }
+        let mut rounds = 0;
+        while !Replica::converged(net.nodes()) {
+            net.run_for(rng, 1);
+            rounds += 1;
+        }
+        (net, rounds)
+    }
+
+    #[test]
+    fn converges_despite_losses() {
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=32);
+            let mode = [Mode::Push, Mode::Pull, Mode::PushPull][rng.gen_range(0..3)];
+            let n_writes = rng.gen_range(0..50);
+            let (net, _) = converge(n, mode, rng.gen_range(0.0..0.3), n_writes, &mut rng);
+            let total: usize = (0..n).map(|j| net.node(0).version()[j]).sum();
+            assert_eq!(total, n_writes);
+            for key in 0..10 {
+                assert!(net
+                    .nodes()
+                    .iter()
+                    .all(|p| p.get(key) == net.node(0).get(key)));
+            }
+        }
+    }
+
+    #[test]
+    fn measures_convergence() {
+        let n_seeds = 50;
+        for loss in [0.0, 0.2] {
+            for n in [16, 64] {
+                let (mut means, mut redundancies) = (Vec::new(), Vec::new());
+                for mode in [Mode::Push, Mode::Pull, Mode::PushPull] {
+                    let (mut rounds, mut received) = (Vec::new(), 0);
+                    for seed in 0..n_seeds {
+                        let mut rng = StdRng::seed_from_u64(seed);
+                        let (net, r) = converge(n, mode, loss, 20, &mut rng);
+                        rounds.push(r);
+                        received += net.nodes().iter().map(Replica::n_received).sum::<usize>();
+                    }
+                    let mean = rounds.iter().sum::<usize>() as f64 / n_seeds as f64;
+                    // Updates received per replica that lacked them
+                    let redundancy = received as f64 / (n_seeds as usize * 20 * (n - 1)) as f64;
+                    let max = rounds.iter().max().unwrap();
+                    println!("loss={loss} n={n} {mode:?}: {mean:.1} mean, {max} max rounds, {redundancy:.2} transfers per update");
+                    // Only deltas are transferred, which are only duplicated by concurrent exchanges
+                    assert!((1.0..1.5).contains(&redundancy));
+                    if loss == 0.0 {
+                        assert!(mean <= 2.0 * (n as f64).log2());
+                    }
+                    means.push(mean);
+                    redundancies.push(redundancy);
+                }
+                // Pushes duplicate updates that several replicas push at once, while pulls are sent to one replica
+                assert!(redundancies[1] < redundancies[0]);
+                assert!(means[2] < means[0] && means[2] < means[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn only_sends_updates_the_peer_lacks() {
+        let mut p = Replica::new(0, 2, Mode::PushPull);
+        p.write(1, 5);
+        let syn = |v: Vec<usize>| Msg::Syn(Some(VersionVector::from(v)));
+        let mut out = Vec::new();
+        for (version, n_updates) in [
+            (vec![0, 0], 1),
+            (vec![1, 0], 0),
+            (vec![1, 3], 0),
+            (vec![0, 3], 1),
+        ] {
+            p.recv(1, syn(version.clone()), &mut out);
+            assert!(matches!(out.pop(), Some((1, Msg::Ack(_, us))) if us.len() == n_updates));
+
+            // Pushes nothing either if the peer has every update
+            p.recv(
+                1,
+                Msg::Ack(VersionVector::from(version), Vec::new()),
+                &mut out,
+            );
+            assert_eq!(out.len(), n_updates);
+            out.clear();
+        }
+    }
+}
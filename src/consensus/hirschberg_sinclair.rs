@@ -0,0 +1,243 @@
+use crate::consensus::Node;
+
+// Direction of travel on the ring, where Right is from node i to i + 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dir {
+    Left,
+    Right,
+}
+
+impl Dir {
+    fn rev(self) -> Self {
+        match self {
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    // Candidate id of phase k, which has travelled hops of at most 2^k
+    Probe {
+        id: u64,
+        phase: u32,
+        hops: usize,
+        dir: Dir,
+    },
+    // Candidate id survived 2^k hops in one direction
+    Reply {
+        id: u64,
+        phase: u32,
+        dir: Dir,
+    },
+    Elected(u64),
+}
+
+/// Leader election by Hirschberg & Sinclair on a bidirectional ring of unique ids.
+///
+/// In phase k, each remaining candidate probes `2^k` nodes in both directions. A probe is discarded by a larger id, and
+/// otherwise replied to by the last node. A candidate with replies from both directions continues to the next phase,
+/// and a probe that returns to its candidate has travelled the whole ring, so that candidate is the maximum id. At
+/// most `n / (2^(k-1) + 1)` candidates start phase k, each with `O(2^k)` messages, so election takes `O(n log n)`
+/// messages in the worst case.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::hirschberg_sinclair::HirschbergSinclair;
+/// use rads::consensus::network::Network;
+///
+/// let ids = [3, 1, 4, 5, 2];
+/// let mut net = Network::new(HirschbergSinclair::ring(&ids));
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// assert!(net.nodes().iter().all(|p| p.leader() == Some(5)));
+/// ```
+pub struct HirschbergSinclair {
+    i: usize,
+    n: usize,
+    id: u64,
+    // Latest phase, if it is a candidate
+    phase: Option<u32>,
+    n_replies: usize,
+    leader: Option<u64>,
+}
+
+impl HirschbergSinclair {
+    pub fn new(i: usize, n: usize, id: u64) -> Self {
+        Self {
+            i,
+            n,
+            id,
+            phase: None,
+            n_replies: 0,
+            leader: None,
+        }
+    }
+    // Node i has id ids[i]
+    pub fn ring(ids: &[u64]) -> Vec<Self> {
+        (ids.iter().enumerate())
+            .map(|(i, &id)| Self::new(i, ids.len(), id))
+            .collect()
+    }
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    pub fn leader(&self) -> Option<u64> {
+        self.leader
+    }
+    fn neighbour(&self, dir: Dir) -> usize {
+        match dir {
+            Dir::Left => (self.i + self.n - 1) % self.n,
+            Dir::Right => (self.i + 1) % self.n,
+        }
+    }
+    fn probe(&mut self, phase: u32, out: &mut Vec<(usize, Msg)>) {
+        self.phase = Some(phase);
+        self.n_replies = 0;
+        for dir in [Dir::Left, Dir::Right] {
+            let (id, hops) = (self.id, 1);
+            out.push((
+                self.neighbour(dir),
+                Msg::Probe {
+                    id,
+                    phase,
+                    hops,
+                    dir,
+                },
+            ));
+        }
+    }
+}
+
+impl Node for HirschbergSinclair {
+    type Msg = Msg;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if self.phase.is_none() && self.leader.is_none() {
+            self.probe(0, out);
+        }
+    }
+    fn recv(&mut self, _from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        match msg {
+            Msg::Probe { id, .. } if id == self.id => {
+                // Only the first of the probes in both directions
+                if self.leader.is_none() {
+                    self.leader = Some(id);
+                    out.push((self.neighbour(Dir::Right), Msg::Elected(id)));
+                }
+            }
+            Msg::Probe {
+                id,
+                phase,
+                hops,
+                dir,
+            } if id > self.id => {
+                self.start(out);
+                if hops < 1 << phase {
+                    let hops = hops + 1;
+                    out.push((
+                        self.neighbour(dir),
+                        Msg::Probe {
+                            id,
+                            phase,
+                            hops,
+                            dir,
+                        },
+                    ));
+                } else {
+                    let dir = dir.rev();
+                    out.push((self.neighbour(dir), Msg::Reply { id, phase, dir }));
+                }
+            }
+            Msg::Probe { .. } => self.start(out),
+            Msg::Reply { id, phase, dir } if id != self.id => {
+                out.push((self.neighbour(dir), Msg::Reply { id, phase, dir }));
+            }
+            Msg::Reply { phase, .. } => {
+                if self.phase == Some(phase) && self.leader.is_none() {
+                    self.n_replies += 1;
+                    if self.n_replies == 2 {
+                        self.probe(phase + 1, out);
+                    }
+                }
+            }
+            Msg::Elected(id) => {
+                if id != self.id {
+                    self.leader = Some(id);
+                    out.push((self.neighbour(Dir::Right), msg));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::chang_roberts::ChangRoberts;
+    use crate::consensus::hirschberg_sinclair::HirschbergSinclair;
+    use crate::consensus::network::Network;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    #[test]
+    fn elects_max_id() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n = rng.gen_range(1..=20);
+            let mut ids: Vec<u64> = (0..n).map(|_| rng.gen()).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids.shuffle(&mut rng);
+            let mut net = Network::new(HirschbergSinclair::ring(&ids));
+            net.start(rng.gen_range(0..ids.len()));
+            (0..ids.len())
+                .filter(|_| rng.gen_bool(0.5))
+                .for_each(|i| net.start(i));
+            net.run(&mut rng);
+            let max = ids.iter().max().copied();
+            assert!(net.nodes().iter().all(|p| p.leader() == max));
+        }
+    }
+
+    #[test]
+    fn worst_case_messages() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 2, 3, 7, 8, 100, 256, 1000] {
+            let mut ids: Vec<_> = (0..n as u64).collect();
+            let log_n = (n as f64).log2().ceil() as usize;
+            for _ in 0..3 {
+                let mut net = Network::new(HirschbergSinclair::ring(&ids));
+                net.start_all();
+                net.run(&mut rng);
+                // At most 4 * 2^k messages per candidate in phase k, and n elected messages
+                assert!(
+                    net.n_sent() <= 8 * n * (log_n + 1) + n,
+                    "{} for n={n}",
+                    net.n_sent()
+                );
+                ids.shuffle(&mut rng);
+            }
+        }
+    }
+
+    #[test]
+    fn fewer_messages_than_chang_roberts_on_sorted_ring() {
+        let ids: Vec<_> = (0..512).rev().collect();
+        let n_sent = |hs: bool| {
+            let mut rng = rand::thread_rng();
+            if hs {
+                let mut net = Network::new(HirschbergSinclair::ring(&ids));
+                net.start_all();
+                net.run(&mut rng);
+                net.n_sent()
+            } else {
+                let mut net = Network::new(ChangRoberts::ring(&ids));
+                net.start_all();
+                net.run(&mut rng);
+                net.n_sent()
+            }
+        };
+        assert!(4 * n_sent(true) < n_sent(false));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::consensus::raft::StateMachine;
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    Get(u64),
+    // Returns the previous value
+    Put(u64, u64),
+}
+
+impl Op {
+    pub fn key(&self) -> u64 {
+        match *self {
+            Op::Get(k) | Op::Put(k, _) => k,
+        }
+    }
+}
+
+/// Key-value store as a state machine, where every operation returns the value of its key before it.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::kv::{Kv, Op};
+/// use rads::consensus::raft::StateMachine;
+///
+/// let mut kv = Kv::default();
+/// assert_eq!(kv.apply(&Op::Put(1, 5)), None);
+/// assert_eq!(kv.apply(&Op::Put(1, 6)), Some(5));
+/// assert_eq!(kv.apply(&Op::Get(1)), Some(6));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Kv(BTreeMap<u64, u64>);
+
+impl Kv {
+    pub fn get(&self, k: u64) -> Option<u64> {
+        self.0.get(&k).copied()
+    }
+}
+
+impl StateMachine for Kv {
+    type Cmd = Op;
+    type Output = Option<u64>;
+
+    fn apply(&mut self, op: &Op) -> Option<u64> {
+        match *op {
+            Op::Get(k) => self.get(k),
+            Op::Put(k, v) => self.0.insert(k, v),
+        }
+    }
+}
+
+// Operation invoked at a time, which returned an output at a later time, or never
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub op: Op,
+    pub invoked: usize,
+    pub returned: Option<(usize, Option<u64>)>,
+}
+
+/// History of concurrent operations on a key-value store, checked for linearizability by Wing & Gong's search.
+///
+/// A history is linearizable if each operation takes effect at some instant between its invocation and return, so
+/// that the outputs are those of a single store applying the operations in that order. An operation that never
+/// returned may take effect at any time after its invocation, or never. Since linearizability is local, each key is
+/// checked on its own. The search repeatedly takes effect of some operation invoked before every remaining operation
+/// returned, and backtracks on a wrong output, memoizing the sets of operations and values that failed.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::kv::{History, Op};
+///
+/// let mut history = History::default();
+/// let put = history.invoke(Op::Put(1, 5));
+/// let get = history.invoke(Op::Get(1));
+/// history.ret(get, Some(5));
+/// history.ret(put, None);
+/// assert!(history.is_linearizable());
+///
+/// // A later read of an older value
+/// let get = history.invoke(Op::Get(1));
+/// history.ret(get, None);
+/// assert!(!history.is_linearizable());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    calls: Vec<Call>,
+    time: usize,
+}
+
+impl History {
+    // Invokes the operation, returning its id
+    pub fn invoke(&mut self, op: Op) -> usize {
+        self.time += 1;
+        self.calls.push(Call {
+            op,
+            invoked: self.time,
+            returned: None,
+        });
+        self.calls.len() - 1
+    }
+    pub fn ret(&mut self, id: usize, output: Option<u64>) {
+        self.time += 1;
+        self.calls[id].returned = Some((self.time, output));
+    }
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+    pub fn is_linearizable(&self) -> bool {
+        let keys: HashSet<_> = self.calls.iter().map(|c| c.op.key()).collect();
+        keys.into_iter().all(|k| {
+            // Reads that never returned have no effect
+            let calls: Vec<_> = (self.calls.iter())
+                .filter(|c| {
+                    c.op.key() == k && (c.returned.is_some() || matches!(c.op, Op::Put(..)))
+                })
+                .collect();
+            search(
+                &calls,
+                &mut vec![false; calls.len()],
+                None,
+                &mut HashSet::new(),
+            )
+        })
+    }
+}
+
+// Whether the calls not yet done linearize from the value
+fn search(
+    calls: &[&Call],
+    done: &mut Vec<bool>,
+    value: Option<u64>,
+    failed: &mut HashSet<(Vec<bool>, Option<u64>)>,
+) -> bool {
+    let remaining = || (0..calls.len()).filter(|&c| !done[c]);
+    let Some(deadline) = remaining()
+        .filter_map(|c| calls[c].returned)
+        .map(|(t, _)| t)
+        .min()
+    else {
+        return true;
+    };
+    if failed.contains(&(done.clone(), value)) {
+        return false;
+    }
+    let next: Vec<_> = remaining()
+        .filter(|&c| calls[c].invoked < deadline)
+        .collect();
+    for c in next {
+        if calls[c].returned.is_some_and(|(_, output)| output != value) {
+            continue;
+        }
+        let after = match calls[c].op {
+            Op::Get(_) => value,
+            Op::Put(_, v) => Some(v),
+        };
+        done[c] = true;
+        let found = search(calls, done, after, failed);
+        done[c] = false;
+        if found {
+            return true;
+        }
+    }
+    failed.insert((done.clone(), value));
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::kv::{History, Kv, Op};
+    use crate::consensus::network::Network;
+    use crate::consensus::raft::Raft;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn checks_linearizability() {
+        // Concurrent puts may take effect in either order, but reads must agree on it
+        let mut history = History::default();
+        let (a, b) = (history.invoke(Op::Put(0, 1)), history.invoke(Op::Put(0, 2)));
+        history.ret(a, None);
+        history.ret(b, Some(1));
+        let get = history.invoke(Op::Get(0));
+        history.ret(get, Some(2));
+        assert!(history.is_linearizable());
+        let get = history.invoke(Op::Get(0));
+        history.ret(get, Some(1));
+        assert!(!history.is_linearizable());
+
+        // A put that never returned may take effect late, but not come back
+        let mut history = History::default();
+        let put = history.invoke(Op::Put(0, 1));
+        history.ret(put, None);
+        history.invoke(Op::Put(0, 2));
+        let get = history.invoke(Op::Get(0));
+        history.ret(get, Some(1));
+        let get = history.invoke(Op::Get(0));
+        history.ret(get, Some(2));
+        assert!(history.is_linearizable());
+        let get = history.invoke(Op::Get(0));
+        history.ret(get, Some(1));
+        assert!(!history.is_linearizable());
+
+        // Keys are independent, but a read cannot precede a put that it sees
+        let mut history = History::default();
+        let get = history.invoke(Op::Get(1));
+        history.ret(get, Some(1));
+        let put = history.invoke(Op::Put(1, 1));
+        history.ret(put, None);
+        assert!(!history.is_linearizable());
+    }
+
+    #[test]
+    fn raft_is_linearizable_despite_partitions() {
+        for seed in 0..40 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=5);
+            let mut net = Network::new(Raft::cluster(n, n, Kv::default(), rng.gen_range(0..10)));
+            net.set_loss(rng.gen_range(0.0..0.1));
+            let mut history = History::default();
+            // Each client waits on at most one call, submitted to some node at an index and term, until a deadline
+            let mut clients = vec![None; 3];
+            for tick in 0..300 {
+                if tick % 10 == 0 {
+                    let side: Vec<_> = (0..n).filter(|_| rng.gen_bool(0.3)).collect();
+                    net.partition(&side);
+                }
+                if tick >= 250 {
+                    net.heal();
+                }
+                for client in &mut clients {
+                    if client.is_none() && rng.gen_bool(0.5) {
+                        let k = rng.gen_range(0..3);
+                        let op = match rng.gen_bool(0.5) {
+                            true => Op::Get(k),
+                            false => Op::Put(k, rng.gen_range(0..100)),
+                        };
+                        let (i, mut slot) = (rng.gen_range(0..n), None);
+                        net.act(i, |p, out| slot = p.submit(op, out));
+                        *client = slot.map(|slot| (history.invoke(op), i, slot, tick + 30));
+                    }
+                }
+                loop {
+                    for client in &mut clients {
+                        if let Some((id, i, slot, deadline)) = *client {
+                            if let Some(&output) = net.node(i).output(slot) {
+                                history.ret(id, output);
+                                *client = None;
+                            } else if tick > deadline {
+                                *client = None;
+                            }
+                        }
+                    }
+                    if !net.step(&mut rng) {
+                        break;
+                    }
+                }
+                net.tick();
+            }
+            assert!(history.is_linearizable(), "seed {seed}");
+            let n_returned = history
+                .calls()
+                .iter()
+                .filter(|c| c.returned.is_some())
+                .count();
+            assert!(n_returned > 0, "seed {seed}");
+        }
+    }
+}
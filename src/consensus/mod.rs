@@ -0,0 +1,48 @@
+pub mod ben_or;
+pub mod broadcast;
+pub mod bully;
+pub mod byzantine;
+pub mod chang_roberts;
+pub mod coordinator;
+pub mod failure_detector;
+pub mod flood_set;
+pub mod ghs;
+pub mod gossip;
+pub mod hirschberg_sinclair;
+pub mod kv;
+pub mod multi_paxos;
+pub mod network;
+pub mod oral_messages;
+pub mod paxos;
+pub mod phase_king;
+pub mod raft;
+pub mod random_attack;
+pub mod spanning_tree;
+pub mod sync_network;
+
+/// Process of a message passing algorithm, which only acts on starting, receiving or timing out.
+///
+/// Messages are sent by pushing `(receiver, message)` to `out`, so that the network decides when to deliver them.
+pub trait Node {
+    type Msg;
+
+    fn start(&mut self, out: &mut Vec<(usize, Self::Msg)>);
+    fn recv(&mut self, from: usize, msg: Self::Msg, out: &mut Vec<(usize, Self::Msg)>);
+    // Called once no message is in flight, i.e. after any bounded message delay
+    fn timeout(&mut self, _out: &mut Vec<(usize, Self::Msg)>) {}
+    // Waits for a timeout, so the network keeps timing out even if no message is in flight
+    fn has_timer(&self) -> bool {
+        false
+    }
+}
+
+/// Process of a synchronous algorithm, which acts in lock-step rounds of sending then receiving.
+///
+/// Every message sent in a round is delivered by the end of that round, so a missing message means its sender crashed.
+pub trait SyncNode {
+    type Msg;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, Self::Msg)>);
+    // Every message sent to this node in the round, as (sender, message) by sender
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, Self::Msg)>);
+}
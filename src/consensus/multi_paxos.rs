@@ -0,0 +1,424 @@
+use crate::consensus::paxos::Ballot;
+use crate::consensus::Node;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+// Timeouts a lease lasts from its heartbeat
+pub const LEASE: usize = 3;
+
+// Entry of the log, where None is a no-op filling a gap
+pub type Entry<V> = Option<V>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Msg<V> {
+    Prepare(Ballot),
+    // Promise to ignore lower ballots, with every entry accepted as (slot, ballot, entry)
+    Promise(Ballot, Vec<(usize, Ballot, Entry<V>)>),
+    Accept(Ballot, usize, Entry<V>),
+    Accepted(Ballot, usize),
+    // Already promised a higher ballot
+    Nack(Ballot),
+    Chosen(usize, Entry<V>),
+    // Leader renews its lease from the time it sent the heartbeat
+    Heartbeat(Ballot, usize),
+    // Acknowledges the heartbeat, with the length of the log chosen without gaps
+    HeartbeatAck(Ballot, usize, usize),
+    // Command forwarded to the leader
+    Forward(V),
+}
+
+/// Replicated log by Multi-Paxos, over an asynchronous network of `n` nodes that may crash or lose messages.
+///
+/// A stable leader runs phase 1 once for every slot, re-proposing any entry accepted in a higher ballot, and filling
+/// gaps with no-ops. Then it only needs phase 2 for each command, and retransmits until a majority accepts. Nodes time
+/// out together, and the leader's heartbeats grant it a lease of `LEASE` timeouts from the time it sent them, during
+/// which acceptors refuse any other node's ballot. So while its lease holds, no other node can choose entries, and the
+/// leader reads its log locally. A node elects itself after a random backoff once its lease to the leader expires.
+/// Commands go through the leader, and may be dropped if forwarded to a node that crashes or loses them, or proposed by
+/// a leader that is preempted before a majority accepts, so clients retry.
+///
+/// Nodes time out forever, so the network should only run for a number of timeouts.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::multi_paxos::MultiPaxos;
+/// use rads::consensus::network::Network;
+///
+/// let mut rng = rand::thread_rng();
+/// let mut net = Network::new(MultiPaxos::cluster(3));
+/// net.run_for(&mut rng, 10);
+/// net.act(1, |p, out| p.submit("x", out));
+/// net.act(2, |p, out| p.submit("y", out));
+/// net.run_for(&mut rng, 10);
+/// let log = net.node(0).committed();
+/// assert!(log == [&"x", &"y"] || log == [&"y", &"x"]);
+/// assert!(net.nodes().iter().any(|p| p.read() == Some(log.clone())));
+/// ```
+pub struct MultiPaxos<V> {
+    i: usize,
+    n: usize,
+    now: usize,
+    // As acceptor
+    promised: Ballot,
+    accepted: BTreeMap<usize, (Ballot, Entry<V>)>,
+    // Time it last heard from the leader of the promised ballot
+    heard: Option<usize>,
+    // As learner
+    chosen: BTreeMap<usize, Entry<V>>,
+    // As proposer, with a ballot that leads once promised by a majority
+    ballot: Ballot,
+    leading: bool,
+    promises: HashMap<usize, Vec<(usize, Ballot, Entry<V>)>>,
+    // Entries proposed but not yet chosen, and acceptors of each
+    proposed: BTreeMap<usize, Entry<V>>,
+    acks: HashMap<usize, BTreeSet<usize>>,
+    next_slot: usize,
+    // Acknowledgements of heartbeats by the time sent, and the end of the lease
+    lease_acks: BTreeMap<usize, BTreeSet<usize>>,
+    lease_until: usize,
+    // Commands waiting for a leader
+    pending: VecDeque<V>,
+    // Highest round seen, to propose above it
+    seen: u64,
+    // Timeouts to wait before electing itself, drawn at random
+    backoff: usize,
+    waited: usize,
+    rng: StdRng,
+}
+
+impl<V: Clone> MultiPaxos<V> {
+    pub fn new(i: usize, n: usize) -> Self {
+        Self {
+            i,
+            n,
+            now: 0,
+            promised: (0, 0),
+            accepted: BTreeMap::new(),
+            heard: None,
+            chosen: BTreeMap::new(),
+            ballot: (0, i),
+            leading: false,
+            promises: HashMap::new(),
+            proposed: BTreeMap::new(),
+            acks: HashMap::new(),
+            next_slot: 0,
+            lease_acks: BTreeMap::new(),
+            lease_until: 0,
+            pending: VecDeque::new(),
+            seen: 0,
+            backoff: 1,
+            waited: 0,
+            rng: StdRng::seed_from_u64(i as u64),
+        }
+    }
+    pub fn cluster(n: usize) -> Vec<Self> {
+        (0..n).map(|i| Self::new(i, n)).collect()
+    }
+    pub fn is_leading(&self) -> bool {
+        self.leading
+    }
+    pub fn has_lease(&self) -> bool {
+        self.leading && self.now < self.lease_until
+    }
+    // Leader of the promised ballot
+    pub fn leader(&self) -> Option<usize> {
+        Some(self.promised.1).filter(|_| self.promised.0 > 0)
+    }
+    pub fn chosen(&self) -> &BTreeMap<usize, Entry<V>> {
+        &self.chosen
+    }
+    // Commands of the log chosen without gaps
+    pub fn committed(&self) -> Vec<&V> {
+        (0..self.prefix())
+            .filter_map(|s| self.chosen[&s].as_ref())
+            .collect()
+    }
+    // Committed commands, read locally by the leader while its lease holds and every entry it proposed is chosen
+    pub fn read(&self) -> Option<Vec<&V>> {
+        (self.has_lease() && self.proposed.is_empty()).then(|| self.committed())
+    }
+
+    // Appends the command to the log through the leader
+    pub fn submit(&mut self, v: V, out: &mut Vec<(usize, Msg<V>)>) {
+        match self.leader() {
+            _ if self.leading => self.propose(self.next_slot, Some(v), out),
+            Some(j) if j != self.i => out.push((j, Msg::Forward(v))),
+            _ => self.pending.push_back(v),
+        }
+    }
+
+    fn prefix(&self) -> usize {
+        (0..).find(|s| !self.chosen.contains_key(s)).unwrap()
+    }
+    fn broadcast(&self, msg: Msg<V>, out: &mut Vec<(usize, Msg<V>)>) {
+        (0..self.n).for_each(|j| out.push((j, msg.clone())));
+    }
+    fn propose(&mut self, slot: usize, e: Entry<V>, out: &mut Vec<(usize, Msg<V>)>) {
+        self.next_slot = self.next_slot.max(slot + 1);
+        self.proposed.insert(slot, e.clone());
+        self.acks.remove(&slot);
+        self.broadcast(Msg::Accept(self.ballot, slot, e), out);
+    }
+    fn elect(&mut self, out: &mut Vec<(usize, Msg<V>)>) {
+        self.ballot = (self.seen.max(self.promised.0) + 1, self.i);
+        self.leading = false;
+        self.promises.clear();
+        self.broadcast(Msg::Prepare(self.ballot), out);
+    }
+    // Re-proposes the entry accepted in the highest ballot for each slot not chosen, or a no-op for a gap
+    fn lead(&mut self, out: &mut Vec<(usize, Msg<V>)>) {
+        self.leading = true;
+        self.proposed.clear();
+        self.lease_acks.clear();
+        let mut entries: BTreeMap<usize, (Ballot, Entry<V>)> = BTreeMap::new();
+        for (s, b, e) in self.promises.drain().flat_map(|(_, es)| es) {
+            if entries.get(&s).is_none_or(|(c, _)| *c < b) {
+                entries.insert(s, (b, e));
+            }
+        }
+        let end = (entries.keys().chain(self.chosen.keys()).max()).map_or(0, |s| s + 1);
+        self.next_slot = end;
+        for s in 0..end {
+            if self.chosen.contains_key(&s) {
+                continue;
+            }
+            let e = entries.remove(&s).and_then(|(_, e)| e);
+            self.propose(s, e, out);
+        }
+        while let Some(v) = self.pending.pop_front() {
+            self.propose(self.next_slot, Some(v), out);
+        }
+        self.broadcast(Msg::Heartbeat(self.ballot, self.now), out);
+    }
+    // Follows a ballot at least as high as promised, renewing its lease
+    fn follow(&mut self, b: Ballot, out: &mut Vec<(usize, Msg<V>)>) -> bool {
+        if b < self.promised {
+            return false;
+        }
+        if b > self.ballot && self.leading {
+            self.leading = false;
+        }
+        self.promised = b;
+        self.heard = Some(self.now);
+        if b.1 != self.i {
+            self.pending
+                .drain(..)
+                .for_each(|v| out.push((b.1, Msg::Forward(v))));
+        }
+        true
+    }
+    fn lease_expired(&self) -> bool {
+        self.heard.is_none_or(|t| self.now >= t + LEASE)
+    }
+}
+
+impl<V: Clone> Node for MultiPaxos<V> {
+    type Msg = Msg<V>;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg<V>)>) {
+        self.elect(out);
+    }
+    fn recv(&mut self, from: usize, msg: Msg<V>, out: &mut Vec<(usize, Msg<V>)>) {
+        match msg {
+            Msg::Prepare(b) => {
+                self.seen = self.seen.max(b.0);
+                // Refuse other ballots while the lease to the leader holds
+                let leased = self.leader().is_some_and(|j| j != from) && !self.lease_expired();
+                if b >= self.promised && !leased {
+                    if b > self.ballot && self.leading {
+                        self.leading = false;
+                    }
+                    self.promised = b;
+                    let accepted = (self.accepted.iter())
+                        .map(|(&s, (b, e))| (s, *b, e.clone()))
+                        .collect();
+                    out.push((from, Msg::Promise(b, accepted)));
+                } else {
+                    out.push((from, Msg::Nack(self.promised)));
+                }
+            }
+            Msg::Promise(b, accepted) => {
+                if b == self.ballot && !self.leading {
+                    self.promises.insert(from, accepted);
+                    if 2 * self.promises.len() > self.n {
+                        self.lead(out);
+                    }
+                }
+            }
+            Msg::Accept(b, slot, e) => {
+                if self.follow(b, out) {
+                    self.accepted.insert(slot, (b, e));
+                    out.push((from, Msg::Accepted(b, slot)));
+                } else {
+                    out.push((from, Msg::Nack(self.promised)));
+                }
+            }
+            Msg::Accepted(b, slot) => {
+                if !self.leading || b != self.ballot || !self.proposed.contains_key(&slot) {
+                    return;
+                }
+                let acks = self.acks.entry(slot).or_default();
+                acks.insert(from);
+                if 2 * acks.len() > self.n {
+                    let e = self.proposed.remove(&slot).unwrap();
+                    self.acks.remove(&slot);
+                    self.chosen.insert(slot, e.clone());
+                    (0..self.n)
+                        .filter(|&j| j != self.i)
+                        .for_each(|j| out.push((j, Msg::Chosen(slot, e.clone()))));
+                }
+            }
+            Msg::Nack(p) => {
+                self.seen = self.seen.max(p.0);
+                if p > self.ballot {
+                    self.leading = false;
+                }
+            }
+            Msg::Chosen(slot, e) => {
+                self.chosen.insert(slot, e);
+            }
+            Msg::Heartbeat(b, t) => {
+                if self.follow(b, out) {
+                    out.push((from, Msg::HeartbeatAck(b, t, self.prefix())));
+                } else {
+                    out.push((from, Msg::Nack(self.promised)));
+                }
+            }
+            Msg::HeartbeatAck(b, t, prefix) => {
+                if !self.leading || b != self.ballot {
+                    return;
+                }
+                let acks = self.lease_acks.entry(t).or_default();
+                acks.insert(from);
+                if 2 * acks.len() > self.n {
+                    self.lease_until = self.lease_until.max(t + LEASE);
+                }
+                // Catch up a follower that missed chosen entries
+                for s in (prefix..self.prefix()).take(8) {
+                    out.push((from, Msg::Chosen(s, self.chosen[&s].clone())));
+                }
+            }
+            Msg::Forward(v) => self.submit(v, out),
+        }
+    }
+    fn timeout(&mut self, out: &mut Vec<(usize, Msg<V>)>) {
+        self.now += 1;
+        if self.leading {
+            self.lease_acks.retain(|&t, _| t + LEASE > self.now);
+            self.broadcast(Msg::Heartbeat(self.ballot, self.now), out);
+            // Retransmit entries that may have been lost
+            for (&s, e) in &self.proposed {
+                self.broadcast(Msg::Accept(self.ballot, s, e.clone()), out);
+            }
+        } else if self.lease_expired() {
+            self.waited += 1;
+            if self.waited >= self.backoff {
+                self.waited = 0;
+                self.backoff = self.rng.gen_range(1..=2 * LEASE);
+                self.elect(out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::multi_paxos::MultiPaxos;
+    use crate::consensus::network::Network;
+    use crate::consensus::paxos::SafetyChecker;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn replicates_log() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let mut net = Network::new(MultiPaxos::cluster(n));
+            // Elect a stable leader, which no one preempts while its lease holds
+            net.run_for(&mut rng, 20);
+            let mut submitted = Vec::new();
+            for cmd in 0..20 {
+                net.act(rng.gen_range(0..n), |p, out| p.submit(cmd, out));
+                submitted.push(cmd);
+                let ticks = rng.gen_range(0..3);
+                net.run_for(&mut rng, ticks);
+            }
+            net.run_for(&mut rng, 20);
+
+            let log = net.node(0).committed();
+            assert!(net.nodes().iter().all(|p| p.committed() == log));
+            let mut sorted: Vec<_> = log.into_iter().copied().collect();
+            sorted.sort_unstable();
+            assert_eq!(sorted, submitted, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn safe_despite_crashes_losses_and_partitions() {
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let mut net = Network::new(MultiPaxos::cluster(n));
+            net.set_loss(rng.gen_range(0.0..0.3));
+            let mut crashes: Vec<_> = (0..n)
+                .filter(|_| rng.gen_bool(0.5))
+                .take((n - 1) / 2)
+                .collect();
+            let mut checker = SafetyChecker::default();
+            for tick in 0..100 {
+                // Partition at random, so that leases expire on either side
+                if tick % 5 == 0 {
+                    let side: Vec<_> = (0..n).filter(|_| rng.gen_bool(0.3)).collect();
+                    net.partition(&side);
+                }
+                if tick % 3 == 0 {
+                    net.act(rng.gen_range(0..n), |p, out| p.submit(tick, out));
+                }
+                if rng.gen_bool(0.05) {
+                    if let Some(i) = crashes.pop() {
+                        net.crash(i);
+                    }
+                }
+                while net.step(&mut rng) {
+                    check(&net, &mut checker);
+                }
+                net.tick();
+                check(&net, &mut checker);
+            }
+            // A majority is live, so the log grows once healed
+            net.heal();
+            for _ in 0..20 {
+                while net.step(&mut rng) {
+                    check(&net, &mut checker);
+                }
+                net.tick();
+            }
+            assert!(!checker.chosen().is_empty(), "seed {seed}");
+        }
+    }
+
+    // No two learners choose different entries for a slot, at most one leader holds a lease, and a lease read returns
+    // every command chosen without gaps
+    fn check(net: &Network<MultiPaxos<usize>>, checker: &mut SafetyChecker<Option<usize>>) {
+        for (i, p) in net.nodes().iter().enumerate() {
+            for (&s, e) in p.chosen() {
+                checker.check(i, s, e).unwrap();
+            }
+        }
+        let leased: Vec<_> = (0..net.len())
+            .filter(|&i| !net.is_crashed(i) && net.node(i).has_lease())
+            .collect();
+        assert!(leased.len() <= 1, "Leases held by {leased:?}");
+        for i in leased {
+            if let Some(read) = net.node(i).read() {
+                let chosen = checker.chosen();
+                let all = (0..)
+                    .take_while(|s| chosen.contains_key(s))
+                    .filter_map(|s| chosen[&s].as_ref());
+                assert!(read.into_iter().eq(all));
+            }
+        }
+    }
+}
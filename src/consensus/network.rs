@@ -0,0 +1,160 @@
+use crate::consensus::Node;
+use rand::Rng;
+use std::collections::VecDeque;
+
+/// Asynchronous network of nodes, where each link is a lossless FIFO channel that delivers after an arbitrary delay.
+///
+/// The delay is simulated by delivering the first message of a random non-empty link. A crashed node stops acting,
+/// and messages to it are dropped, but its messages in flight are still delivered. On lossy links, each message is
+/// instead lost with some probability, and a partition loses every message between its two sides.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::network::Network;
+/// use rads::consensus::Node;
+///
+/// // Each node pings every other node
+/// struct Ping(usize, usize);
+/// impl Node for Ping {
+///     type Msg = ();
+///     fn start(&mut self, out: &mut Vec<(usize, ())>) {
+///         (0..self.1).filter(|&j| j != self.0).for_each(|j| out.push((j, ())));
+///     }
+///     fn recv(&mut self, _from: usize, _msg: (), _out: &mut Vec<(usize, ())>) {}
+/// }
+/// let mut net = Network::new((0..3).map(|i| Ping(i, 3)).collect());
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// assert_eq!(net.n_sent(), 6);
+/// ```
+pub struct Network<N: Node> {
+    nodes: Vec<N>,
+    // By sender then receiver
+    links: Vec<Vec<VecDeque<N::Msg>>>,
+    // Links with messages in flight
+    busy: Vec<(usize, usize)>,
+    crashed: Vec<bool>,
+    // Probability of losing each message
+    loss: f64,
+    // Side of the partition of each node
+    side: Vec<bool>,
+    n_sent: usize,
+}
+
+impl<N: Node> Network<N> {
+    pub fn new(nodes: Vec<N>) -> Self {
+        let n = nodes.len();
+        Self {
+            nodes,
+            links: (0..n)
+                .map(|_| (0..n).map(|_| VecDeque::new()).collect())
+                .collect(),
+            busy: Vec::new(),
+            crashed: vec![false; n],
+            loss: 0.0,
+            side: vec![false; n],
+            n_sent: 0,
+        }
+    }
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+    pub fn node(&self, i: usize) -> &N {
+        &self.nodes[i]
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    pub fn n_sent(&self) -> usize {
+        self.n_sent
+    }
+    pub fn is_crashed(&self, i: usize) -> bool {
+        self.crashed[i]
+    }
+    pub fn crash(&mut self, i: usize) {
+        self.crashed[i] = true;
+    }
+    pub fn set_loss(&mut self, p: f64) {
+        self.loss = p;
+    }
+    // Partitions the nodes from the rest, until healed
+    pub fn partition(&mut self, nodes: &[usize]) {
+        self.heal();
+        nodes.iter().for_each(|&i| self.side[i] = true);
+    }
+    pub fn heal(&mut self) {
+        self.side.fill(false);
+    }
+    pub fn in_flight(&self) -> usize {
+        self.links.iter().flatten().map(VecDeque::len).sum()
+    }
+
+    pub fn start(&mut self, i: usize) {
+        self.act(i, N::start);
+    }
+    // Acts on node i from outside the network, e.g. on a client request, unless it has crashed
+    pub fn act<F: FnOnce(&mut N, &mut Vec<(usize, N::Msg)>)>(&mut self, i: usize, f: F) {
+        if !self.crashed[i] {
+            let mut out = Vec::new();
+            f(&mut self.nodes[i], &mut out);
+            self.send(i, out);
+        }
+    }
+    pub fn start_all(&mut self) {
+        (0..self.len()).for_each(|i| self.start(i));
+    }
+    // Delivers a message of a random link, returning false if none is in flight
+    pub fn step<R: Rng>(&mut self, rng: &mut R) -> bool {
+        if self.busy.is_empty() {
+            return false;
+        }
+        let k = rng.gen_range(0..self.busy.len());
+        let (i, j) = self.busy[k];
+        let msg = self.links[i][j].pop_front().unwrap();
+        if self.links[i][j].is_empty() {
+            self.busy.swap_remove(k);
+        }
+        let lost = self.side[i] != self.side[j] || (self.loss > 0.0 && rng.gen_bool(self.loss));
+        if !self.crashed[j] && !lost {
+            let mut out = Vec::new();
+            self.nodes[j].recv(i, msg, &mut out);
+            self.send(j, out);
+        }
+        true
+    }
+    // Times out every live node
+    pub fn tick(&mut self) {
+        (0..self.len()).for_each(|i| self.act(i, N::timeout));
+    }
+    // Delivers every message, then times out live nodes until none sends or waits for a timeout
+    pub fn run<R: Rng>(&mut self, rng: &mut R) {
+        loop {
+            while self.step(rng) {}
+            self.tick();
+            let has_timer = (0..self.len()).any(|i| !self.crashed[i] && self.nodes[i].has_timer());
+            if self.busy.is_empty() && !has_timer {
+                return;
+            }
+        }
+    }
+    // Delivers every message then times out live nodes, for the given number of timeouts
+    pub fn run_for<R: Rng>(&mut self, rng: &mut R, ticks: usize) {
+        for _ in 0..ticks {
+            while self.step(rng) {}
+            self.tick();
+        }
+    }
+
+    fn send(&mut self, i: usize, out: Vec<(usize, N::Msg)>) {
+        self.n_sent += out.len();
+        for (j, msg) in out {
+            if self.links[i][j].is_empty() {
+                self.busy.push((i, j));
+            }
+            self.links[i][j].push_back(msg);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::consensus::SyncNode;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::collections::HashMap;
+
+// Value relayed along a path of nodes, starting from the commander
+pub type Msg = (Vec<usize>, bool);
+
+// How a traitor corrupts the value it sends or relays to each node
+pub enum Traitor {
+    Silent,
+    // True to the lower half of nodes, and false to the upper half
+    Equivocating,
+    Flipping,
+    // A random value, or nothing
+    Random(Box<StdRng>),
+}
+
+impl Traitor {
+    fn lie(&mut self, n: usize, to: usize, v: bool) -> Option<bool> {
+        match self {
+            Traitor::Silent => None,
+            Traitor::Equivocating => Some(2 * to < n),
+            Traitor::Flipping => Some(!v),
+            Traitor::Random(rng) => rng.gen_bool(2.0 / 3.0).then(|| rng.gen()),
+        }
+    }
+}
+
+/// Byzantine generals by Lamport, Shostak & Pease's oral messages `OM(m)`, over a synchronous, fully connected network of
+/// `n >= 3m + 1` generals with up to `m` traitors, where general 0 commands whether to attack.
+///
+/// In `OM(0)`, the commander sends its order to every lieutenant, who obeys it, or retreats if none arrives. In `OM(m)`,
+/// each lieutenant then relays the order it received, as the commander of `OM(m - 1)` to the other lieutenants, and
+/// obeys the majority of its order and the orders they relay. The recursion runs in `m + 1` rounds, where a value
+/// received in round `r` has a path of `r + 1` generals, and takes `(n - 1) + (n - 1)(n - 2) + ...` messages, i.e.
+/// `O(n^(m + 1))`. Every loyal lieutenant obeys the same order, which is the commander's if loyal. With `n = 3m`, a
+/// traitor may tell one lieutenant to attack and another to retreat, which neither can tell from a lying lieutenant.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::oral_messages::{OralMessages, Traitor};
+/// use rads::consensus::sync_network::SyncNetwork;
+///
+/// let mut net = SyncNetwork::new(OralMessages::generals(4, 1, true, vec![(2, Traitor::Flipping)]));
+/// net.run(2);
+/// assert!([1, 3].iter().all(|&i| net.node(i).decision() == Some(true)));
+/// assert_eq!(net.n_sent(), OralMessages::n_messages(4, 1));
+/// ```
+pub struct OralMessages {
+    i: usize,
+    n: usize,
+    m: usize,
+    // Order of the commander
+    order: Option<bool>,
+    traitor: Option<Traitor>,
+    // Values received by path
+    values: HashMap<Vec<usize>, bool>,
+    // Paths received in the last round, to relay
+    relay: Vec<Vec<usize>>,
+    decision: Option<bool>,
+}
+
+impl OralMessages {
+    pub fn new(
+        i: usize,
+        n: usize,
+        m: usize,
+        order: Option<bool>,
+        traitor: Option<Traitor>,
+    ) -> Self {
+        Self {
+            i,
+            n,
+            m,
+            order,
+            traitor,
+            values: HashMap::new(),
+            relay: Vec::new(),
+            decision: None,
+        }
+    }
+    // General 0 commands the order, and each general i of traitors lies by its strategy
+    pub fn generals(n: usize, m: usize, order: bool, traitors: Vec<(usize, Traitor)>) -> Vec<Self> {
+        let mut traitors: HashMap<_, _> = traitors.into_iter().collect();
+        (0..n)
+            .map(|i| Self::new(i, n, m, Some(order).filter(|_| i == 0), traitors.remove(&i)))
+            .collect()
+    }
+    // Messages of OM(m) with n generals and no silent traitor
+    pub fn n_messages(n: usize, m: usize) -> usize {
+        (1..=m + 1)
+            .scan(1, |product, k| {
+                *product *= n.saturating_sub(k);
+                Some(*product)
+            })
+            .sum()
+    }
+    pub fn is_traitor(&self) -> bool {
+        self.traitor.is_some()
+    }
+    // Order obeyed after m + 1 rounds, or the commander's own
+    pub fn decision(&self) -> Option<bool> {
+        self.order.or(self.decision)
+    }
+
+    fn send_to(&mut self, path: &[usize], v: bool, out: &mut Vec<(usize, Msg)>) {
+        let mut path = path.to_vec();
+        path.push(self.i);
+        for j in (0..self.n).filter(|j| !path.contains(j)) {
+            let v = match &mut self.traitor {
+                Some(traitor) => traitor.lie(self.n, j, v),
+                None => Some(v),
+            };
+            if let Some(v) = v {
+                out.push((j, (path.clone(), v)));
+            }
+        }
+    }
+    // Value of OM(m - k) for the path of k + 1 generals, by majority of its value and those relayed by the others
+    fn om(&self, path: &mut Vec<usize>) -> bool {
+        let v = self.values.get(path).copied().unwrap_or(false);
+        if path.len() == self.m + 1 {
+            return v;
+        }
+        let (mut n_true, mut n_false) = (v as usize, !v as usize);
+        let others: Vec<_> = (0..self.n)
+            .filter(|j| *j != self.i && !path.contains(j))
+            .collect();
+        for j in others {
+            path.push(j);
+            match self.om(path) {
+                true => n_true += 1,
+                false => n_false += 1,
+            }
+            path.pop();
+        }
+        n_true > n_false
+    }
+}
+
+impl SyncNode for OralMessages {
+    type Msg = Msg;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, Msg)>) {
+        if let (0, Some(order)) = (round, self.order) {
+            self.send_to(&[], order, out);
+        } else if round <= self.m {
+            for path in std::mem::take(&mut self.relay) {
+                let v = self.values[&path];
+                self.send_to(&path, v, out);
+            }
+        }
+    }
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, Msg)>) {
+        if self.order.is_some() || round > self.m {
+            return;
+        }
+        for (from, (path, v)) in msgs {
+            // Only accept a path of distinct generals from the commander, ending at its sender
+            let valid = path.len() == round + 1
+                && path[0] == 0
+                && path.last() == Some(&from)
+                && !path.contains(&self.i)
+                && (1..path.len()).all(|k| !path[..k].contains(&path[k]));
+            if valid && !self.values.contains_key(&path) {
+                self.values.insert(path.clone(), v);
+                self.relay.push(path);
+            }
+        }
+        if round == self.m {
+            self.decision = Some(self.om(&mut vec![0]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::oral_messages::{OralMessages, Traitor};
+    use crate::consensus::sync_network::SyncNetwork;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn interactive_consistency() {
+        for m in 0..=2 {
+            for n in 3 * m + 1..=3 * m + 2 {
+                for_each_run(n, m, |order, traitors, net| {
+                    assert!(
+                        consistent(order, traitors, net),
+                        "n={n} m={m} traitors {traitors:b}"
+                    );
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn fails_with_3m_generals() {
+        for m in 1..=2 {
+            let mut failed = false;
+            for_each_run(3 * m, m, |order, traitors, net| {
+                failed |= !consistent(order, traitors, net);
+            });
+            assert!(failed, "m={m}");
+        }
+    }
+
+    #[test]
+    fn messages() {
+        for n in 1..=8 {
+            for m in 0..=3 {
+                let mut net = SyncNetwork::new(OralMessages::generals(n, m, true, Vec::new()));
+                net.run(m + 1);
+                assert_eq!(net.n_sent(), OralMessages::n_messages(n, m));
+            }
+        }
+        // Exponential in m
+        assert_eq!(OralMessages::n_messages(7, 2), 6 + 6 * 5 + 6 * 5 * 4);
+        assert_eq!(
+            OralMessages::n_messages(10, 3),
+            9 + 9 * 8 + 9 * 8 * 7 + 9 * 8 * 7 * 6
+        );
+    }
+
+    // Every loyal lieutenant obeys the same order, which is the commander's if loyal
+    fn consistent(order: bool, traitors: u32, net: &SyncNetwork<OralMessages>) -> bool {
+        let loyal: Vec<_> = (1..net.len()).filter(|&i| traitors >> i & 1 == 0).collect();
+        let decisions: Vec<_> = loyal.iter().map(|&i| net.node(i).decision()).collect();
+        decisions.iter().all(|&d| d.is_some() && d == decisions[0])
+            && (traitors & 1 == 1 || decisions.iter().all(|&d| d == Some(order)))
+    }
+
+    // Runs OM(m) for both orders and every set of at most m traitors, each by every strategy
+    fn for_each_run(
+        n: usize,
+        m: usize,
+        mut check: impl FnMut(bool, u32, &SyncNetwork<OralMessages>),
+    ) {
+        let mut seed = 0;
+        for order in [false, true] {
+            for traitors in (0..1u32 << n).filter(|t| t.count_ones() as usize <= m) {
+                for k in 0..5 {
+                    let mut strategy = || {
+                        seed += 1;
+                        match k {
+                            0 => Traitor::Silent,
+                            1 => Traitor::Equivocating,
+                            2 => Traitor::Flipping,
+                            _ => Traitor::Random(Box::new(StdRng::seed_from_u64(seed))),
+                        }
+                    };
+                    let strategies = (0..n)
+                        .filter(|&i| traitors >> i & 1 == 1)
+                        .map(|i| (i, strategy()))
+                        .collect();
+                    let mut net = SyncNetwork::new(OralMessages::generals(n, m, order, strategies));
+                    net.run(m + 1);
+                    check(order, traitors, &net);
+                }
+            }
+        }
+    }
+}
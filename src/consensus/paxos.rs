@@ -0,0 +1,369 @@
+use crate::consensus::Node;
+use anyhow::ensure;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Debug;
+
+// Round, then proposer to break ties
+pub type Ballot = (u64, usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Msg<V> {
+    Prepare(Ballot),
+    // Promise to ignore lower ballots, with the last value accepted
+    Promise(Ballot, Option<(Ballot, V)>),
+    Accept(Ballot, V),
+    Accepted(Ballot, V),
+    // Already promised a higher ballot
+    Nack(Ballot),
+}
+
+// Acceptor of single-decree Paxos, whose state must survive crashes
+pub struct Acceptor<V> {
+    promised: Option<Ballot>,
+    accepted: Option<(Ballot, V)>,
+}
+
+impl<V> Default for Acceptor<V> {
+    fn default() -> Self {
+        Self {
+            promised: None,
+            accepted: None,
+        }
+    }
+}
+
+impl<V: Clone> Acceptor<V> {
+    pub fn promised(&self) -> Option<Ballot> {
+        self.promised
+    }
+    pub fn accepted(&self) -> Option<&(Ballot, V)> {
+        self.accepted.as_ref()
+    }
+    // Promises the ballot with the last value accepted, or refuses with the ballot promised
+    pub fn prepare(&mut self, b: Ballot) -> Result<Option<(Ballot, V)>, Ballot> {
+        match self.promised {
+            Some(p) if p > b => Err(p),
+            _ => {
+                self.promised = Some(b);
+                Ok(self.accepted.clone())
+            }
+        }
+    }
+    pub fn accept(&mut self, b: Ballot, v: V) -> Result<(), Ballot> {
+        match self.promised {
+            Some(p) if p > b => Err(p),
+            _ => {
+                self.promised = Some(b);
+                self.accepted = Some((b, v));
+                Ok(())
+            }
+        }
+    }
+}
+
+// Proposer of single-decree Paxos, which proposes its own value unless another may have been chosen
+pub struct Proposer<V> {
+    i: usize,
+    n: usize,
+    value: V,
+    ballot: Ballot,
+    // Promises for the ballot by acceptor
+    promises: HashMap<usize, Option<(Ballot, V)>>,
+    // Value proposed for the ballot, once promised by a majority
+    proposed: Option<V>,
+}
+
+impl<V: Clone> Proposer<V> {
+    pub fn new(i: usize, n: usize, value: V) -> Self {
+        Self {
+            i,
+            n,
+            value,
+            ballot: (0, i),
+            promises: HashMap::new(),
+            proposed: None,
+        }
+    }
+    pub fn ballot(&self) -> Ballot {
+        self.ballot
+    }
+    pub fn proposed(&self) -> Option<&V> {
+        self.proposed.as_ref()
+    }
+    // Starts phase 1 with a ballot above any round seen
+    pub fn prepare(&mut self, seen: u64, out: &mut Vec<(usize, Msg<V>)>) {
+        self.ballot = (self.ballot.0.max(seen) + 1, self.i);
+        self.promises.clear();
+        self.proposed = None;
+        (0..self.n).for_each(|j| out.push((j, Msg::Prepare(self.ballot))));
+    }
+    // Starts phase 2 once a majority promises, with the value accepted in the highest ballot, if any
+    pub fn promise(
+        &mut self,
+        from: usize,
+        b: Ballot,
+        accepted: Option<(Ballot, V)>,
+        out: &mut Vec<(usize, Msg<V>)>,
+    ) {
+        if b != self.ballot || self.proposed.is_some() {
+            return;
+        }
+        self.promises.insert(from, accepted);
+        if 2 * self.promises.len() > self.n {
+            let v = (self.promises.values().flatten())
+                .max_by_key(|(b, _)| *b)
+                .map_or(self.value.clone(), |(_, v)| v.clone());
+            self.proposed = Some(v.clone());
+            (0..self.n).for_each(|j| out.push((j, Msg::Accept(self.ballot, v.clone()))));
+        }
+    }
+}
+
+// Learner of single-decree Paxos, which learns a value accepted by a majority in the same ballot
+pub struct Learner<V> {
+    n: usize,
+    votes: HashMap<Ballot, BTreeSet<usize>>,
+    chosen: Option<V>,
+}
+
+impl<V> Learner<V> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            votes: HashMap::new(),
+            chosen: None,
+        }
+    }
+    pub fn chosen(&self) -> Option<&V> {
+        self.chosen.as_ref()
+    }
+    pub fn accepted(&mut self, from: usize, b: Ballot, v: V) {
+        let votes = self.votes.entry(b).or_default();
+        votes.insert(from);
+        if 2 * votes.len() > self.n && self.chosen.is_none() {
+            self.chosen = Some(v);
+        }
+    }
+}
+
+/// Single-decree Paxos by Lamport, over an asynchronous network of `n` nodes that may crash or lose messages, where
+/// every node is an acceptor and learner, and some are proposers.
+///
+/// A proposer first asks acceptors to promise a ballot, and then to accept a value, which is the value accepted in the
+/// highest ballot by any of a majority that promised, or else its own. A value accepted by a majority is chosen, and any
+/// higher ballot proposes it, as its majority of promises includes an acceptor of the chosen value. So at most one value
+/// is chosen, while any majority of live nodes is enough to choose. As FLP forbids guaranteeing termination, proposers
+/// retry after a random backoff, so that they rarely preempt each other forever.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::network::Network;
+/// use rads::consensus::paxos::Paxos;
+///
+/// let mut net = Network::new(Paxos::cluster(&[Some("a"), None, Some("b"), None, None]));
+/// net.crash(3);
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// let chosen = net.node(0).chosen();
+/// assert!(chosen == Some(&"a") || chosen == Some(&"b"));
+/// assert_eq!(net.node(2).chosen(), chosen);
+/// ```
+pub struct Paxos<V> {
+    n: usize,
+    proposer: Option<Proposer<V>>,
+    acceptor: Acceptor<V>,
+    learner: Learner<V>,
+    // Highest round seen, to propose above it
+    seen: u64,
+    // Timeouts to wait before retrying, drawn at random
+    backoff: usize,
+    waited: usize,
+    rng: StdRng,
+}
+
+impl<V: Clone> Paxos<V> {
+    // Node i proposes value, if any
+    pub fn new(i: usize, n: usize, value: Option<V>) -> Self {
+        Self {
+            n,
+            proposer: value.map(|v| Proposer::new(i, n, v)),
+            acceptor: Acceptor::default(),
+            learner: Learner::new(n),
+            seen: 0,
+            backoff: 1,
+            waited: 0,
+            rng: StdRng::seed_from_u64(i as u64),
+        }
+    }
+    // Node i proposes values[i], if any
+    pub fn cluster(values: &[Option<V>]) -> Vec<Self> {
+        (values.iter().enumerate())
+            .map(|(i, v)| Self::new(i, values.len(), v.clone()))
+            .collect()
+    }
+    pub fn proposer(&self) -> Option<&Proposer<V>> {
+        self.proposer.as_ref()
+    }
+    pub fn acceptor(&self) -> &Acceptor<V> {
+        &self.acceptor
+    }
+    pub fn chosen(&self) -> Option<&V> {
+        self.learner.chosen()
+    }
+}
+
+impl<V: Clone> Node for Paxos<V> {
+    type Msg = Msg<V>;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg<V>)>) {
+        if let Some(p) = &mut self.proposer {
+            p.prepare(self.seen, out);
+        }
+    }
+    fn recv(&mut self, from: usize, msg: Msg<V>, out: &mut Vec<(usize, Msg<V>)>) {
+        match msg {
+            Msg::Prepare(b) => {
+                self.seen = self.seen.max(b.0);
+                match self.acceptor.prepare(b) {
+                    Ok(accepted) => out.push((from, Msg::Promise(b, accepted))),
+                    Err(p) => out.push((from, Msg::Nack(p))),
+                }
+            }
+            Msg::Promise(b, accepted) => {
+                if let Some(p) = &mut self.proposer {
+                    p.promise(from, b, accepted, out);
+                }
+            }
+            Msg::Accept(b, v) => match self.acceptor.accept(b, v.clone()) {
+                Ok(()) => (0..self.n).for_each(|j| out.push((j, Msg::Accepted(b, v.clone())))),
+                Err(p) => out.push((from, Msg::Nack(p))),
+            },
+            Msg::Accepted(b, v) => self.learner.accepted(from, b, v),
+            Msg::Nack(p) => self.seen = self.seen.max(p.0),
+        }
+    }
+    // Retries with a higher ballot until learning the chosen value
+    fn timeout(&mut self, out: &mut Vec<(usize, Msg<V>)>) {
+        if !self.has_timer() {
+            return;
+        }
+        self.waited += 1;
+        if self.waited >= self.backoff {
+            self.waited = 0;
+            self.backoff = self.rng.gen_range(1..=4);
+            self.start(out);
+        }
+    }
+    fn has_timer(&self) -> bool {
+        self.proposer.is_some() && self.learner.chosen().is_none()
+    }
+}
+
+/// Checker that learners never choose different values for a slot.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::paxos::SafetyChecker;
+///
+/// let mut checker = SafetyChecker::default();
+/// assert!(checker.check(0, 0, &"a").is_ok());
+/// assert!(checker.check(1, 0, &"a").is_ok());
+/// assert!(checker.check(2, 0, &"b").is_err());
+/// ```
+pub struct SafetyChecker<V> {
+    chosen: BTreeMap<usize, V>,
+}
+
+impl<V> Default for SafetyChecker<V> {
+    fn default() -> Self {
+        Self {
+            chosen: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V: Clone + PartialEq + Debug> SafetyChecker<V> {
+    // Values chosen by any learner, by slot
+    pub fn chosen(&self) -> &BTreeMap<usize, V> {
+        &self.chosen
+    }
+    // Observes that the learner has chosen v for the slot
+    pub fn check(&mut self, learner: usize, slot: usize, v: &V) -> anyhow::Result<()> {
+        match self.chosen.get(&slot) {
+            Some(w) => ensure!(
+                w == v,
+                "Learner {learner} chose {v:?} for slot {slot}, but {w:?} was chosen"
+            ),
+            None => {
+                self.chosen.insert(slot, v.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::network::Network;
+    use crate::consensus::paxos::{Paxos, SafetyChecker};
+    use crate::consensus::Node;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn chooses_one_proposed_value() {
+        for seed in 0..300 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let values: Vec<_> = (0..n)
+                .map(|i| Some(i).filter(|_| rng.gen_bool(0.5)))
+                .collect();
+            let mut net = Network::new(Paxos::cluster(&values));
+            let lossy = rng.gen_bool(0.5);
+            if lossy {
+                net.set_loss(rng.gen_range(0.0..0.3));
+            }
+            // Crash a minority at random steps
+            let mut crashes: Vec<_> = (0..n)
+                .filter(|_| rng.gen_bool(0.3))
+                .take((n - 1) / 2)
+                .collect();
+            net.start_all();
+
+            let mut checker = SafetyChecker::default();
+            loop {
+                while net.step(&mut rng) {
+                    if rng.gen_bool(0.05) {
+                        if let Some(i) = crashes.pop() {
+                            net.crash(i);
+                        }
+                    }
+                    for (i, p) in net.nodes().iter().enumerate() {
+                        if let Some(v) = p.chosen() {
+                            checker.check(i, 0, v).unwrap();
+                        }
+                    }
+                }
+                net.tick();
+                let live = |i: &usize| !net.is_crashed(*i);
+                if net.in_flight() == 0 && !(0..n).filter(live).any(|i| net.node(i).has_timer()) {
+                    break;
+                }
+            }
+
+            // Every live proposer learns, and so does every live node without losses
+            let chosen = checker.chosen().get(&0);
+            assert!(chosen.is_none_or(|v| values.contains(&Some(*v))));
+            for i in (0..n).filter(|&i| !net.is_crashed(i)) {
+                if values[i].is_some() || !lossy {
+                    assert_eq!(net.node(i).chosen(), chosen, "seed {seed}");
+                }
+            }
+            if (0..n).any(|i| !net.is_crashed(i) && values[i].is_some()) {
+                assert!(chosen.is_some());
+            }
+        }
+    }
+}
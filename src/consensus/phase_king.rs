@@ -0,0 +1,142 @@
+use crate::consensus::byzantine::first_by_sender;
+use crate::consensus::SyncNode;
+
+/// Byzantine consensus on binary values by Berman, Garay & Perry's phase king, over a synchronous, fully connected
+/// network of `n >= 3f + 1` nodes with up to `f` Byzantine.
+///
+/// Each of `f + 1` phases has three rounds. First, every node broadcasts its value, and proposes any value it receives
+/// from `n - f` nodes, which only one value can be for honest nodes. Then every node broadcasts its proposal, adopts a
+/// value proposed by more than `f` nodes, and is firm about it if proposed by `n - f`. Last, node `k` is king of phase
+/// `k`, and broadcasts its value, which a node adopts unless firm. A firm node means every honest node adopted its
+/// value, so the king agrees. One phase has an honest king, after which honest nodes agree, and stay firm. This takes
+/// one more round per phase than the [coordinator](crate::consensus::coordinator::Coordinator), but tolerates a third
+/// of nodes being Byzantine, rather than a quarter.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::phase_king::PhaseKing;
+/// use rads::consensus::sync_network::SyncNetwork;
+///
+/// let mut net = SyncNetwork::new(PhaseKing::cluster(1, &[true, false, true, true]));
+/// net.run(6);
+/// assert!(net.nodes().iter().all(|p| p.decision() == Some(true)));
+/// ```
+pub struct PhaseKing {
+    i: usize,
+    n: usize,
+    f: usize,
+    value: bool,
+    proposal: Option<bool>,
+    firm: bool,
+    decision: Option<bool>,
+}
+
+impl PhaseKing {
+    pub fn new(i: usize, n: usize, f: usize, value: bool) -> Self {
+        assert!(n > 3 * f, "{n} nodes for {f} Byzantine");
+        Self {
+            i,
+            n,
+            f,
+            value,
+            proposal: None,
+            firm: false,
+            decision: None,
+        }
+    }
+    // Node i proposes values[i], tolerating f Byzantine nodes
+    pub fn cluster(f: usize, values: &[bool]) -> Vec<Self> {
+        (values.iter().enumerate())
+            .map(|(i, &v)| Self::new(i, values.len(), f, v))
+            .collect()
+    }
+    pub fn value(&self) -> bool {
+        self.value
+    }
+    // After 3(f + 1) rounds
+    pub fn decision(&self) -> Option<bool> {
+        self.decision
+    }
+    // Votes for false and true, one per sender, including its own
+    fn count(msgs: &[(usize, bool)], own: Option<bool>) -> [usize; 2] {
+        let mut votes = [0; 2];
+        (first_by_sender(msgs).into_values())
+            .chain(own)
+            .for_each(|v| votes[v as usize] += 1);
+        votes
+    }
+}
+
+impl SyncNode for PhaseKing {
+    type Msg = bool;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, bool)>) {
+        let phase = round / 3;
+        let v = match round % 3 {
+            _ if phase > self.f => None,
+            0 => Some(self.value),
+            1 => self.proposal,
+            _ => Some(self.value).filter(|_| phase == self.i),
+        };
+        if let Some(v) = v {
+            (0..self.n)
+                .filter(|&j| j != self.i)
+                .for_each(|j| out.push((j, v)));
+        }
+    }
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, bool)>) {
+        let phase = round / 3;
+        if phase > self.f {
+            return;
+        }
+        match round % 3 {
+            0 => {
+                let votes = Self::count(&msgs, Some(self.value));
+                self.proposal = [false, true]
+                    .into_iter()
+                    .find(|&v| votes[v as usize] >= self.n - self.f);
+            }
+            1 => {
+                let votes = Self::count(&msgs, self.proposal);
+                let v = votes[1] > votes[0];
+                if votes[v as usize] > self.f {
+                    self.value = v;
+                }
+                self.firm = votes[v as usize] >= self.n - self.f;
+            }
+            _ => {
+                // A missing king is Byzantine, so any value will do
+                let king = match msgs.iter().find(|&&(j, _)| j == phase) {
+                    _ if phase == self.i => self.value,
+                    Some(&(_, v)) => v,
+                    None => false,
+                };
+                if !self.firm {
+                    self.value = king;
+                }
+                if phase == self.f {
+                    self.decision = Some(self.value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::byzantine::tests::check_exhaustively;
+    use crate::consensus::phase_king::PhaseKing;
+
+    #[test]
+    fn exhaustive_agreement_and_validity() {
+        for (n, f) in [(1, 0), (3, 0), (4, 1), (5, 1), (7, 2)] {
+            check_exhaustively(
+                n,
+                f,
+                3 * (f + 1),
+                |vs| PhaseKing::cluster(f, vs),
+                PhaseKing::decision,
+            );
+        }
+    }
+}
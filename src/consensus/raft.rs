@@ -0,0 +1,803 @@
+use crate::consensus::Node;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeSet, HashMap};
+
+// Timeouts without a leader before electing itself, which is drawn from [ELECTION, 2 ELECTION)
+pub const ELECTION: usize = 5;
+
+/// Deterministic state machine replicated by Raft, which applies committed commands in log order.
+pub trait StateMachine: Clone {
+    type Cmd: Clone;
+    type Output;
+
+    fn apply(&mut self, cmd: &Self::Cmd) -> Self::Output;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry<C> {
+    // Appended by a new leader to commit entries of earlier terms
+    Noop,
+    Cmd(C),
+    // Members from the time it is appended
+    Config(Vec<usize>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+#[derive(Clone)]
+pub enum Msg<S: StateMachine> {
+    RequestVote {
+        term: u64,
+        last_index: usize,
+        last_term: u64,
+    },
+    Vote {
+        term: u64,
+        granted: bool,
+    },
+    Append {
+        term: u64,
+        prev_index: usize,
+        prev_term: u64,
+        entries: Vec<(u64, Entry<S::Cmd>)>,
+        commit: usize,
+    },
+    // Index matched if successful, or else the index to retry after
+    AppendAck {
+        term: u64,
+        success: bool,
+        index: usize,
+    },
+    // State after applying every entry up to the index
+    Snapshot {
+        term: u64,
+        index: usize,
+        last_term: u64,
+        state: S,
+        config: Vec<usize>,
+    },
+}
+
+/// Replicated state machine by Ongaro & Ousterhout's Raft, over an asynchronous network of nodes that may crash, lose
+/// messages or be partitioned, where some nodes are members of the cluster.
+///
+/// A member that times out without hearing from a leader starts an election for the next term, and becomes leader once
+/// a majority votes for it, which only members with logs at least as up to date do. The leader appends commands to its
+/// log and replicates them on each timeout, and a follower only accepts entries following one it has, so logs that
+/// agree on an index and term agree on every entry before it. An entry of the leader's term is committed once a
+/// majority has it, and so is in the log of every later leader. Nodes apply committed entries in order, and compact
+/// their logs into a snapshot of the state machine, which the leader sends to followers that lag behind it. Members
+/// change one at a time, from the time the change is appended, so any majorities of the old and new members overlap.
+/// A node ignores elections while it hears from a leader, so that a removed node cannot disrupt the cluster.
+///
+/// Nodes time out forever, so the network should only run for a number of timeouts.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::network::Network;
+/// use rads::consensus::raft::{Raft, StateMachine};
+///
+/// #[derive(Clone, Default)]
+/// struct Sum(u64);
+/// impl StateMachine for Sum {
+///     type Cmd = u64;
+///     type Output = u64;
+///     fn apply(&mut self, x: &u64) -> u64 {
+///         self.0 += x;
+///         self.0
+///     }
+/// }
+///
+/// let mut rng = rand::thread_rng();
+/// let mut net = Network::new(Raft::cluster(3, 3, Sum::default(), 0));
+/// net.run_for(&mut rng, 30);
+/// let leader = (0..3).find(|&i| net.node(i).is_leader()).unwrap();
+/// let mut index = None;
+/// net.act(leader, |p, out| index = p.submit(5, out));
+/// net.run_for(&mut rng, 5);
+/// assert_eq!(net.node(leader).output(index.unwrap()), Some(&5));
+/// assert!(net.nodes().iter().all(|p| p.state().0 == 5));
+/// ```
+pub struct Raft<S: StateMachine> {
+    i: usize,
+    term: u64,
+    voted_for: Option<usize>,
+    role: Role,
+    leader: Option<usize>,
+    // Entries after the snapshot, by term
+    log: Vec<(u64, Entry<S::Cmd>)>,
+    snap_index: usize,
+    snap_term: u64,
+    snap_config: Vec<usize>,
+    snapshot: S,
+    // Compacts the log once it has this many applied entries, or never if 0
+    compact_every: usize,
+    commit: usize,
+    applied: usize,
+    state: S,
+    votes: BTreeSet<usize>,
+    // As leader, the next index to send to each node, and the last index known to match
+    next: Vec<usize>,
+    matched: Vec<usize>,
+    // Timeouts since hearing from the leader, or sending heartbeats as leader
+    elapsed: usize,
+    timeout: usize,
+    rng: StdRng,
+    // Terms of entries submitted to this node by index, and their outputs once applied
+    submitted: HashMap<usize, u64>,
+    outputs: HashMap<(usize, u64), S::Output>,
+}
+
+impl<S: StateMachine> Raft<S> {
+    // Node i of n, with the initial members and state
+    pub fn new(i: usize, n: usize, config: Vec<usize>, state: S, compact_every: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(i as u64);
+        Self {
+            i,
+            term: 0,
+            voted_for: None,
+            role: Role::Follower,
+            leader: None,
+            log: Vec::new(),
+            snap_index: 0,
+            snap_term: 0,
+            snap_config: config,
+            snapshot: state.clone(),
+            compact_every,
+            commit: 0,
+            applied: 0,
+            state,
+            votes: BTreeSet::new(),
+            next: vec![1; n],
+            matched: vec![0; n],
+            elapsed: 0,
+            timeout: rng.gen_range(ELECTION..2 * ELECTION),
+            rng,
+            submitted: HashMap::new(),
+            outputs: HashMap::new(),
+        }
+    }
+    // Nodes 0..n, where 0..members are members
+    pub fn cluster(n: usize, members: usize, state: S, compact_every: usize) -> Vec<Self> {
+        (0..n)
+            .map(|i| Self::new(i, n, (0..members).collect(), state.clone(), compact_every))
+            .collect()
+    }
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+    pub fn role(&self) -> Role {
+        self.role
+    }
+    pub fn is_leader(&self) -> bool {
+        self.role == Role::Leader
+    }
+    pub fn leader(&self) -> Option<usize> {
+        self.leader
+    }
+    pub fn commit_index(&self) -> usize {
+        self.commit
+    }
+    pub fn last_index(&self) -> usize {
+        self.snap_index + self.log.len()
+    }
+    pub fn snapshot_index(&self) -> usize {
+        self.snap_index
+    }
+    // Term of the entry at the index, unless compacted
+    pub fn term_at(&self, index: usize) -> Option<u64> {
+        match index.checked_sub(self.snap_index) {
+            Some(0) => Some(self.snap_term),
+            Some(k) => self.log.get(k - 1).map(|(t, _)| *t),
+            None => None,
+        }
+    }
+    // State after applying every committed entry
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+    // Latest members in the log
+    pub fn config(&self) -> &[usize] {
+        match self.config_index() - self.snap_index {
+            0 => &self.snap_config,
+            k => match &self.log[k - 1].1 {
+                Entry::Config(c) => c,
+                _ => unreachable!(),
+            },
+        }
+    }
+    // Output of the command submitted at the index, once applied if the term still matches
+    pub fn output(&self, (index, term): (usize, u64)) -> Option<&S::Output> {
+        self.outputs.get(&(index, term))
+    }
+
+    // Appends the command if leader, returning its index and term
+    pub fn submit(&mut self, cmd: S::Cmd, out: &mut Vec<(usize, Msg<S>)>) -> Option<(usize, u64)> {
+        if !self.is_leader() {
+            return None;
+        }
+        // A single member commits on appending
+        self.submitted.insert(self.last_index() + 1, self.term);
+        self.append(Entry::Cmd(cmd));
+        self.replicate(out);
+        Some((self.last_index(), self.term))
+    }
+    // Adds or removes one member if leader, once it has committed in its term and no other change is uncommitted
+    pub fn change(&mut self, config: Vec<usize>, out: &mut Vec<(usize, Msg<S>)>) -> bool {
+        let old: BTreeSet<_> = self.config().iter().copied().collect();
+        let new: BTreeSet<_> = config.iter().copied().collect();
+        if !self.is_leader()
+            || self.term_at(self.commit) != Some(self.term)
+            || self.config_index() > self.commit
+            || old.symmetric_difference(&new).count() != 1
+        {
+            return false;
+        }
+        for j in new.difference(&old) {
+            (self.next[*j], self.matched[*j]) = (self.last_index() + 1, 0);
+        }
+        self.append(Entry::Config(config));
+        self.replicate(out);
+        true
+    }
+
+    fn append(&mut self, e: Entry<S::Cmd>) {
+        self.log.push((self.term, e));
+        self.matched[self.i] = self.last_index();
+        self.advance_commit();
+    }
+    // Index of the latest members in the log, or of the snapshot
+    fn config_index(&self) -> usize {
+        let k = (self.log.iter()).rposition(|(_, e)| matches!(e, Entry::Config(_)));
+        self.snap_index + k.map_or(0, |k| k + 1)
+    }
+    fn quorum(&self, f: impl Fn(usize) -> bool) -> bool {
+        let config = self.config();
+        2 * config.iter().filter(|&&j| f(j)).count() > config.len()
+    }
+    fn follow(&mut self, term: u64) {
+        if term > self.term {
+            (self.term, self.voted_for) = (term, None);
+        }
+        self.role = Role::Follower;
+        self.leader = None;
+    }
+    fn campaign(&mut self, out: &mut Vec<(usize, Msg<S>)>) {
+        self.term += 1;
+        self.role = Role::Candidate;
+        self.leader = None;
+        self.voted_for = Some(self.i);
+        self.votes = BTreeSet::from([self.i]);
+        self.elapsed = 0;
+        self.timeout = self.rng.gen_range(ELECTION..2 * ELECTION);
+        if self.quorum(|j| j == self.i) {
+            return self.lead(out);
+        }
+        let (last_index, last_term) = (self.last_index(), self.term_at(self.last_index()).unwrap());
+        let msg = Msg::RequestVote {
+            term: self.term,
+            last_index,
+            last_term,
+        };
+        (self.config().iter())
+            .filter(|&&j| j != self.i)
+            .for_each(|&j| out.push((j, msg.clone())));
+    }
+    fn lead(&mut self, out: &mut Vec<(usize, Msg<S>)>) {
+        self.role = Role::Leader;
+        self.leader = Some(self.i);
+        let next = self.last_index() + 1;
+        self.next.fill(next);
+        self.matched.fill(0);
+        self.append(Entry::Noop);
+        self.replicate(out);
+    }
+    fn replicate(&mut self, out: &mut Vec<(usize, Msg<S>)>) {
+        self.elapsed = 0;
+        let peers: Vec<_> = self
+            .config()
+            .iter()
+            .copied()
+            .filter(|&j| j != self.i)
+            .collect();
+        peers.into_iter().for_each(|j| self.send_append(j, out));
+    }
+    fn send_append(&self, j: usize, out: &mut Vec<(usize, Msg<S>)>) {
+        let (term, next) = (self.term, self.next[j]);
+        if next <= self.snap_index {
+            let msg = Msg::Snapshot {
+                term,
+                index: self.snap_index,
+                last_term: self.snap_term,
+                state: self.snapshot.clone(),
+                config: self.snap_config.clone(),
+            };
+            return out.push((j, msg));
+        }
+        let msg = Msg::Append {
+            term,
+            prev_index: next - 1,
+            prev_term: self.term_at(next - 1).unwrap(),
+            entries: self.log[next - self.snap_index - 1..].to_vec(),
+            commit: self.commit,
+        };
+        out.push((j, msg));
+    }
+    // Commits the last entry of its term that a majority has
+    fn advance_commit(&mut self) {
+        if let Some(k) = (self.commit + 1..=self.last_index())
+            .rev()
+            .take_while(|&k| self.term_at(k) == Some(self.term))
+            .find(|&k| self.quorum(|j| self.matched[j] >= k))
+        {
+            self.commit = k;
+            self.apply();
+        }
+        // A leader that is no longer a member steps down once its removal commits
+        if self.is_leader()
+            && !self.config().contains(&self.i)
+            && self.commit >= self.config_index()
+        {
+            self.follow(self.term);
+        }
+    }
+    fn apply(&mut self) {
+        while self.applied < self.commit {
+            self.applied += 1;
+            let (term, e) = &self.log[self.applied - self.snap_index - 1];
+            if let Entry::Cmd(cmd) = e {
+                let output = self.state.apply(cmd);
+                if self.submitted.remove(&self.applied) == Some(*term) {
+                    self.outputs.insert((self.applied, *term), output);
+                }
+            }
+        }
+        if self.compact_every > 0 && self.applied - self.snap_index >= self.compact_every {
+            self.compact();
+        }
+    }
+    fn compact(&mut self) {
+        let k = self.applied - self.snap_index;
+        let config = (self.log[..k].iter().rev()).find_map(|(_, e)| match e {
+            Entry::Config(c) => Some(c.clone()),
+            _ => None,
+        });
+        self.snap_term = self.term_at(self.applied).unwrap();
+        self.snap_config = config.unwrap_or(std::mem::take(&mut self.snap_config));
+        self.log.drain(..k);
+        self.snap_index = self.applied;
+        self.snapshot = self.state.clone();
+    }
+    // Accepts entries following the previous index and term, and returns the last index matched
+    fn accept(
+        &mut self,
+        mut prev_index: usize,
+        mut prev_term: u64,
+        mut entries: Vec<(u64, Entry<S::Cmd>)>,
+    ) -> Option<usize> {
+        // Compacted entries are committed, and so match
+        if prev_index < self.snap_index {
+            let skip = (self.snap_index - prev_index).min(entries.len());
+            entries.drain(..skip);
+            prev_index += skip;
+            if prev_index < self.snap_index {
+                return Some(prev_index);
+            }
+            prev_term = self.snap_term;
+        }
+        if self.term_at(prev_index) != Some(prev_term) {
+            return None;
+        }
+        let matched = prev_index + entries.len();
+        for (k, e) in entries.into_iter().enumerate() {
+            let index = prev_index + 1 + k;
+            match self.term_at(index) {
+                Some(t) if t == e.0 => continue,
+                Some(_) => self.log.truncate(index - self.snap_index - 1),
+                None => {}
+            }
+            self.log.push(e);
+        }
+        Some(matched)
+    }
+}
+
+impl<S: StateMachine> Node for Raft<S> {
+    type Msg = Msg<S>;
+
+    // Elections start on timeouts
+    fn start(&mut self, _out: &mut Vec<(usize, Msg<S>)>) {}
+    fn recv(&mut self, from: usize, msg: Msg<S>, out: &mut Vec<(usize, Msg<S>)>) {
+        match msg {
+            Msg::RequestVote {
+                term,
+                last_index,
+                last_term,
+            } => {
+                // Ignore elections while hearing from a leader
+                if self.leader.is_some_and(|j| j != from) && self.elapsed < ELECTION {
+                    return;
+                }
+                if term > self.term {
+                    self.follow(term);
+                }
+                let up_to_date = (last_term, last_index)
+                    >= (self.term_at(self.last_index()).unwrap(), self.last_index());
+                let granted =
+                    term == self.term && self.voted_for.is_none_or(|j| j == from) && up_to_date;
+                if granted {
+                    self.voted_for = Some(from);
+                    self.elapsed = 0;
+                }
+                out.push((
+                    from,
+                    Msg::Vote {
+                        term: self.term,
+                        granted,
+                    },
+                ));
+            }
+            Msg::Vote { term, granted } => {
+                if term > self.term {
+                    self.follow(term);
+                } else if self.role == Role::Candidate && term == self.term && granted {
+                    self.votes.insert(from);
+                    if self.quorum(|j| self.votes.contains(&j)) {
+                        self.lead(out);
+                    }
+                }
+            }
+            Msg::Append {
+                term,
+                prev_index,
+                prev_term,
+                entries,
+                commit,
+            } => {
+                if term < self.term {
+                    let (term, index) = (self.term, self.last_index());
+                    return out.push((
+                        from,
+                        Msg::AppendAck {
+                            term,
+                            success: false,
+                            index,
+                        },
+                    ));
+                }
+                self.follow(term);
+                (self.leader, self.elapsed) = (Some(from), 0);
+                let msg = match self.accept(prev_index, prev_term, entries) {
+                    Some(index) => {
+                        self.commit = self.commit.max(commit.min(index));
+                        self.apply();
+                        Msg::AppendAck {
+                            term,
+                            success: true,
+                            index,
+                        }
+                    }
+                    None => {
+                        let index = self.last_index().min(prev_index - 1);
+                        Msg::AppendAck {
+                            term,
+                            success: false,
+                            index,
+                        }
+                    }
+                };
+                out.push((from, msg));
+            }
+            Msg::AppendAck {
+                term,
+                success,
+                index,
+            } => {
+                if term > self.term {
+                    return self.follow(term);
+                }
+                if !self.is_leader() || term < self.term {
+                    return;
+                }
+                if success {
+                    self.matched[from] = self.matched[from].max(index);
+                    self.next[from] = self.matched[from] + 1;
+                    self.advance_commit();
+                } else {
+                    self.next[from] = (self.next[from] - 1).min(index + 1).max(1);
+                    self.send_append(from, out);
+                }
+            }
+            Msg::Snapshot {
+                term,
+                index,
+                last_term,
+                state,
+                config,
+            } => {
+                if term < self.term {
+                    let (term, index) = (self.term, self.last_index());
+                    return out.push((
+                        from,
+                        Msg::AppendAck {
+                            term,
+                            success: false,
+                            index,
+                        },
+                    ));
+                }
+                self.follow(term);
+                (self.leader, self.elapsed) = (Some(from), 0);
+                if index > self.commit {
+                    if self.term_at(index) == Some(last_term) {
+                        self.log.drain(..index - self.snap_index);
+                    } else {
+                        self.log.clear();
+                    }
+                    (self.snap_index, self.snap_term, self.snap_config) =
+                        (index, last_term, config);
+                    (self.commit, self.applied) = (index, index);
+                    self.snapshot = state.clone();
+                    self.state = state;
+                }
+                out.push((
+                    from,
+                    Msg::AppendAck {
+                        term,
+                        success: true,
+                        index,
+                    },
+                ));
+            }
+        }
+    }
+    fn timeout(&mut self, out: &mut Vec<(usize, Msg<S>)>) {
+        self.elapsed += 1;
+        if self.is_leader() {
+            self.replicate(out);
+        } else if self.elapsed >= self.timeout && self.config().contains(&self.i) {
+            self.campaign(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::kv::{Kv, Op};
+    use crate::consensus::network::Network;
+    use crate::consensus::paxos::SafetyChecker;
+    use crate::consensus::raft::{Msg, Raft, ELECTION};
+    use crate::consensus::Node;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    #[test]
+    fn safe_despite_crashes_losses_and_partitions() {
+        for seed in 0..100 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let n = rng.gen_range(1..=7);
+            let mut net = Network::new(Raft::cluster(n, n, Kv::default(), rng.gen_range(0..10)));
+            net.set_loss(rng.gen_range(0.0..0.3));
+            let mut crashes: Vec<_> = (0..n)
+                .filter(|_| rng.gen_bool(0.5))
+                .take((n - 1) / 2)
+                .collect();
+            let (mut leaders, mut checker) = (HashMap::new(), SafetyChecker::default());
+            for tick in 0..200 {
+                if tick % 5 == 0 {
+                    let side: Vec<_> = (0..n).filter(|_| rng.gen_bool(0.3)).collect();
+                    net.partition(&side);
+                }
+                for i in 0..n {
+                    net.act(i, |p, out| {
+                        p.submit(Op::Put(0, tick), out);
+                    });
+                }
+                if rng.gen_bool(0.05) {
+                    if let Some(i) = crashes.pop() {
+                        net.crash(i);
+                    }
+                }
+                while net.step(&mut rng) {
+                    check(&net, &mut leaders, &mut checker);
+                }
+                net.tick();
+                check(&net, &mut leaders, &mut checker);
+            }
+            // A majority is live, so the leader commits once healed
+            let commit = |net: &Network<Raft<Kv>>| net.nodes().iter().map(Raft::commit_index).max();
+            let committed = commit(&net);
+            net.heal();
+            net.set_loss(0.0);
+            net.run_for(&mut rng, 100);
+            let leader = (0..n).find(|&i| !net.is_crashed(i) && net.node(i).is_leader());
+            net.act(leader.unwrap(), |p, out| {
+                p.submit(Op::Put(0, 0), out);
+            });
+            for _ in 0..5 {
+                while net.step(&mut rng) {
+                    check(&net, &mut leaders, &mut checker);
+                }
+                net.tick();
+                check(&net, &mut leaders, &mut checker);
+            }
+            assert!(commit(&net) > committed, "seed {seed}");
+            // Live nodes have applied the same entries
+            let live: Vec<_> = (0..n).filter(|&i| !net.is_crashed(i)).collect();
+            assert!((live.iter()).all(|&i| net.node(i).state() == net.node(live[0]).state()));
+        }
+    }
+
+    // At most one leader per term, and no two nodes commit different entries at an index
+    fn check(
+        net: &Network<Raft<Kv>>,
+        leaders: &mut HashMap<u64, usize>,
+        checker: &mut SafetyChecker<u64>,
+    ) {
+        for (i, p) in net.nodes().iter().enumerate() {
+            if p.is_leader() {
+                let leader = *leaders.entry(p.term()).or_insert(i);
+                assert_eq!(leader, i, "Two leaders in term {}", p.term());
+            }
+            for k in p.snapshot_index().max(1)..=p.commit_index() {
+                checker.check(i, k, &p.term_at(k).unwrap()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn catches_up_by_snapshot() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut net = Network::new(Raft::cluster(3, 3, Kv::default(), 4));
+        net.run_for(&mut rng, 30);
+        let leader = (0..3).find(|&i| net.node(i).is_leader()).unwrap();
+        let lagging = (leader + 1) % 3;
+        net.partition(&[lagging]);
+        for v in 0..20 {
+            net.act(leader, |p, out| {
+                p.submit(Op::Put(v % 3, v), out);
+            });
+            net.run_for(&mut rng, 1);
+        }
+        assert!(net.node(leader).snapshot_index() > net.node(lagging).last_index());
+        assert!(net.node(leader).last_index() - net.node(leader).snapshot_index() < 4);
+
+        // It disrupts the leader with its higher term, but cannot be elected with its stale log
+        net.heal();
+        net.run_for(&mut rng, 30);
+        let p = net.node(lagging);
+        assert!(!p.is_leader());
+        assert!(p.snapshot_index() > 0);
+        assert!(net
+            .nodes()
+            .iter()
+            .all(|q| q.commit_index() == p.commit_index() && q.state() == p.state()));
+        assert_eq!(p.state().get(1), Some(19));
+    }
+
+    #[test]
+    fn changes_membership_one_at_a_time() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut net = Network::new(Raft::cluster(5, 3, Kv::default(), 4));
+            net.set_loss(rng.gen_range(0.0..0.2));
+            let leader = |net: &Network<Raft<Kv>>| (0..5).find(|&i| net.node(i).is_leader());
+            net.run_for(&mut rng, 30);
+            let old = leader(&net).unwrap();
+
+            // Add 3 and 4, then remove the leader, retrying changes and commands until committed
+            let mut config = vec![0, 1, 2];
+            let mut values = 0..;
+            for j in [3, 4, old] {
+                match config.contains(&j) {
+                    true => config.retain(|&k| k != j),
+                    false => config.push(j),
+                }
+                loop {
+                    let Some(i) = leader(&net) else {
+                        net.run_for(&mut rng, 1);
+                        continue;
+                    };
+                    let (mut changed, v) = (false, values.next().unwrap());
+                    net.act(i, |p, out| {
+                        p.submit(Op::Put(0, v), out);
+                        changed = p.change(config.clone(), out);
+                    });
+                    net.run_for(&mut rng, 10);
+                    let committed = (config.iter()).filter(|&&j| net.node(j).config() == config);
+                    if changed && 2 * committed.count() > config.len() {
+                        break;
+                    }
+                }
+            }
+            net.set_loss(0.0);
+            net.run_for(&mut rng, 30);
+
+            // The old leader stepped down for good, and the new members replicate the log
+            let i = leader(&net).unwrap();
+            assert!(i != old && !net.node(old).is_leader(), "seed {seed}");
+            for &j in &config {
+                assert_eq!(net.node(j).config(), config, "seed {seed}");
+                assert_eq!(net.node(j).state(), net.node(i).state(), "seed {seed}");
+            }
+            assert!(net.node(3).state().get(0).is_some());
+        }
+    }
+
+    // Delivers the messages and every reply, dropping those not allowed
+    fn deliver<F>(nodes: &mut [Raft<Kv>], from: usize, out: Vec<(usize, Msg<Kv>)>, allow: F)
+    where
+        F: Fn(usize, usize, &Msg<Kv>) -> bool,
+    {
+        let mut queue: Vec<_> = out.into_iter().map(|(j, msg)| (from, j, msg)).collect();
+        while !queue.is_empty() {
+            let (i, j, msg) = queue.remove(0);
+            if allow(i, j, &msg) {
+                let mut out = Vec::new();
+                nodes[j].recv(i, msg, &mut out);
+                queue.extend(out.into_iter().map(|(k, msg)| (j, k, msg)));
+            }
+        }
+    }
+
+    // Times out node i into an election, where no node ignores it for hearing from a leader
+    fn elect<F>(nodes: &mut [Raft<Kv>], i: usize, allow: F)
+    where
+        F: Fn(usize, usize, &Msg<Kv>) -> bool,
+    {
+        nodes.iter_mut().for_each(|p| p.elapsed = ELECTION);
+        nodes[i].elapsed = nodes[i].timeout;
+        let mut out = Vec::new();
+        nodes[i].timeout(&mut out);
+        deliver(nodes, i, out, allow);
+        assert!(nodes[i].is_leader());
+    }
+
+    #[test]
+    fn changes_membership_only_after_committing_in_term() {
+        let mut nodes = Raft::cluster(5, 4, Kv::default(), 0);
+        let within = |side: &'static [usize]| {
+            move |i: usize, j: usize, _: &Msg<Kv>| side.contains(&i) && side.contains(&j)
+        };
+        elect(&mut nodes, 0, within(&[0, 1, 2, 3]));
+        assert_eq!(nodes[0].commit_index(), 1);
+
+        // Only 4 learns that 0 adds it
+        let mut out = Vec::new();
+        assert!(nodes[0].change(vec![0, 1, 2, 3, 4], &mut out));
+        deliver(&mut nodes, 0, out, within(&[0, 4]));
+
+        // 1 is elected by 2 and 3, but only 2 hears from it, so it must not remove 0 before committing
+        elect(&mut nodes, 1, |i, j, msg| {
+            let voting = matches!(msg, Msg::RequestVote { .. } | Msg::Vote { .. });
+            [i, j].contains(&1) && ([i, j].contains(&2) || voting && [i, j].contains(&3))
+        });
+        let mut out = Vec::new();
+        assert!(!nodes[1].change(vec![1, 2, 3], &mut out));
+        deliver(&mut nodes, 1, out, within(&[1, 2]));
+
+        // 0 hears of term 2, then is elected by 3 and 4 under its config
+        let mut out = Vec::new();
+        let vote = Msg::Vote {
+            term: 2,
+            granted: false,
+        };
+        nodes[0].recv(3, vote, &mut out);
+        elect(&mut nodes, 0, within(&[0, 3, 4]));
+
+        let mut checker = SafetyChecker::default();
+        for (i, p) in nodes.iter().enumerate() {
+            for k in 1..=p.commit_index() {
+                checker.check(i, k, &p.term_at(k).unwrap()).unwrap();
+            }
+        }
+        assert_eq!(nodes[0].commit_index(), 3);
+    }
+}
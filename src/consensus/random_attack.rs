@@ -0,0 +1,219 @@
+use crate::consensus::SyncNode;
+use rand::Rng;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Msg {
+    key: Option<usize>,
+    // Input of each node, if known
+    inputs: Vec<Option<bool>>,
+    // Least level of each node, if known
+    levels: Vec<Option<usize>>,
+}
+
+/// Randomized coordinated attack by Varghese & Lynch over `R` rounds of a synchronous network with lossy links.
+///
+/// Each node attacks or not, as no deterministic protocol can agree on unreliable links. A node's level rises to one
+/// more than the least level it knows of every other node, so the levels of any two nodes differ by at most one. Node 0
+/// draws a key uniformly from `1..=R`, and a node attacks if it knows the key and every input is to attack, and its
+/// level reaches the key. Then nodes only disagree if the key is the greater of two levels, with probability at most
+/// `1 / R` against any adversary that chooses losses without knowing the key. Without losses, every level reaches `R`,
+/// so every node attacks if every input is to attack.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::random_attack::RandomAttack;
+/// use rads::consensus::sync_network::SyncNetwork;
+///
+/// let mut net = SyncNetwork::new(RandomAttack::cluster(5, &[true, true], &mut rand::thread_rng()));
+/// net.run(5);
+/// assert!(net.nodes().iter().all(|p| p.decision() == Some(true)));
+/// ```
+pub struct RandomAttack {
+    i: usize,
+    rounds: usize,
+    key: Option<usize>,
+    inputs: Vec<Option<bool>>,
+    levels: Vec<Option<usize>>,
+    decision: Option<bool>,
+}
+
+impl RandomAttack {
+    pub fn new(i: usize, n: usize, rounds: usize, input: bool, key: Option<usize>) -> Self {
+        assert!(n >= 2 && rounds >= 1, "{n} nodes over {rounds} rounds");
+        let mut inputs = vec![None; n];
+        inputs[i] = Some(input);
+        let mut levels = vec![None; n];
+        levels[i] = Some(0);
+        Self {
+            i,
+            rounds,
+            key,
+            inputs,
+            levels,
+            decision: None,
+        }
+    }
+    // Node i attacks if inputs[i], where node 0 draws the key
+    pub fn cluster<R: Rng>(rounds: usize, inputs: &[bool], rng: &mut R) -> Vec<Self> {
+        let key = rng.gen_range(1..=rounds);
+        (inputs.iter().enumerate())
+            .map(|(i, &input)| {
+                Self::new(i, inputs.len(), rounds, input, Some(key).filter(|_| i == 0))
+            })
+            .collect()
+    }
+    pub fn level(&self) -> usize {
+        self.levels[self.i].unwrap()
+    }
+    // Whether to attack, after R rounds
+    pub fn decision(&self) -> Option<bool> {
+        self.decision
+    }
+}
+
+impl SyncNode for RandomAttack {
+    type Msg = Msg;
+
+    fn send(&mut self, round: usize, out: &mut Vec<(usize, Msg)>) {
+        if round < self.rounds {
+            let msg = Msg {
+                key: self.key,
+                inputs: self.inputs.clone(),
+                levels: self.levels.clone(),
+            };
+            (0..self.inputs.len())
+                .filter(|&j| j != self.i)
+                .for_each(|j| out.push((j, msg.clone())));
+        }
+    }
+    fn recv(&mut self, round: usize, msgs: Vec<(usize, Msg)>) {
+        if round >= self.rounds {
+            return;
+        }
+        for (_, msg) in msgs {
+            self.key = self.key.or(msg.key);
+            for (v, w) in self.inputs.iter_mut().zip(msg.inputs) {
+                *v = v.or(w);
+            }
+            for (l, m) in self.levels.iter_mut().zip(msg.levels) {
+                *l = (*l).max(m);
+            }
+        }
+        let others = (self.levels.iter().enumerate())
+            .filter(|&(j, _)| j != self.i)
+            .map(|(_, &l)| l)
+            .min()
+            .flatten();
+        if let Some(l) = others {
+            self.levels[self.i] = self.levels[self.i].max(Some(l + 1));
+        }
+        if round + 1 == self.rounds {
+            let attack = self.inputs.iter().all(|&v| v == Some(true));
+            self.decision = Some(attack && self.key.is_some_and(|k| self.level() >= k));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::random_attack::RandomAttack;
+    use crate::consensus::sync_network::SyncNetwork;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn validity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let (n, rounds) = (rng.gen_range(2..=5), rng.gen_range(1..=10));
+            let inputs: Vec<_> = (0..n).map(|_| rng.gen_bool(0.8)).collect();
+            let mut net = SyncNetwork::new(RandomAttack::cluster(rounds, &inputs, &mut rng));
+            let lossy = rng.gen_bool(0.5);
+            if lossy {
+                lose_randomly(&mut net, rounds, 0.3, &mut rng);
+            }
+            net.run(rounds);
+            // Never attack if any input is not to, and always attack if every input is to without losses
+            let attack = inputs.iter().all(|&v| v);
+            if !attack || !lossy {
+                assert!(net.nodes().iter().all(|p| p.decision() == Some(attack)));
+            }
+            let (min, max) = (net.nodes().iter().map(RandomAttack::level))
+                .fold((usize::MAX, 0), |(min, max), l| (min.min(l), max.max(l)));
+            assert!(max <= min + 1);
+        }
+    }
+
+    #[test]
+    fn disagrees_with_probability_at_most_1_over_r() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let n_trials = 3000;
+        for n in [2, 3] {
+            for rounds in [2, 5, 10] {
+                // Losing each message with probability p, or cutting every link in some round but one
+                let adversaries = [Some(0.1), Some(0.5), Some(0.9), None];
+                for p in adversaries {
+                    let mut n_disagreed = 0;
+                    for _ in 0..n_trials {
+                        let mut net = SyncNetwork::new(RandomAttack::cluster(
+                            rounds,
+                            &vec![true; n],
+                            &mut rng,
+                        ));
+                        match p {
+                            Some(p) => lose_randomly(&mut net, rounds, p, &mut rng),
+                            None => cut(&mut net, rounds, rng.gen_range(0..rounds)),
+                        }
+                        net.run(rounds);
+                        let decisions: Vec<_> =
+                            net.nodes().iter().map(RandomAttack::decision).collect();
+                        if decisions.iter().any(|&d| d != decisions[0]) {
+                            n_disagreed += 1;
+                        }
+                    }
+                    // Within 4 standard deviations
+                    let (rate, bound) = (n_disagreed as f64 / n_trials as f64, 1.0 / rounds as f64);
+                    let sd = (bound * (1.0 - bound) / n_trials as f64).sqrt();
+                    assert!(
+                        rate <= bound + 4.0 * sd,
+                        "{rate} > 1/{rounds} for n={n} p={p:?}"
+                    );
+                    // The cut is the optimal adversary for 2 nodes
+                    if n == 2 && p.is_none() {
+                        assert!(rate >= bound - 4.0 * sd, "{rate} < 1/{rounds}");
+                    }
+                }
+            }
+        }
+    }
+
+    fn lose_randomly(
+        net: &mut SyncNetwork<RandomAttack>,
+        rounds: usize,
+        p: f64,
+        rng: &mut impl Rng,
+    ) {
+        let n = net.len();
+        for r in 0..rounds {
+            for i in 0..n {
+                for j in (0..n).filter(|&j| j != i) {
+                    if rng.gen_bool(p) {
+                        net.lose(r, i, j);
+                    }
+                }
+            }
+        }
+    }
+
+    // Delivers every message before round c, only from 0 to 1 in round c, and none afterwards
+    fn cut(net: &mut SyncNetwork<RandomAttack>, rounds: usize, c: usize) {
+        let n = net.len();
+        for r in c..rounds {
+            for i in 0..n {
+                for j in (0..n).filter(|&j| j != i && (r, i, j) != (c, 0, 1)) {
+                    net.lose(r, i, j);
+                }
+            }
+        }
+    }
+}
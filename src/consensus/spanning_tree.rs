@@ -0,0 +1,178 @@
+use crate::consensus::Node;
+use std::collections::BTreeSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    // Sender is at distance d from the root
+    Explore(usize),
+    // Sender adopts the receiver as its parent
+    Join,
+    // Sender adopts another parent
+    Leave,
+}
+
+/// BFS spanning tree by flooding distances from the root over an arbitrary connected graph.
+///
+/// A node that learns of a shorter distance through a neighbour adopts it as its parent, and floods its new distance to
+/// its other neighbours. Since messages are delayed arbitrarily, a longer path may arrive first, so a node may change
+/// its parent several times before the network is quiescent. Then each node has its BFS distance, and children are
+/// consistent as join and leave messages to a parent share a FIFO link.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::network::Network;
+/// use rads::consensus::spanning_tree::BfsTree;
+///
+/// // 0 - 1 - 2 - 3 - 0
+/// let adj = [vec![1, 3], vec![0, 2], vec![1, 3], vec![2, 0]];
+/// let mut net = Network::new(BfsTree::graph(&adj, 0));
+/// net.start_all();
+/// net.run(&mut rand::thread_rng());
+/// assert_eq!(net.node(2).dist(), Some(2));
+/// assert_eq!(net.node(0).children(), vec![1, 3]);
+/// ```
+pub struct BfsTree {
+    i: usize,
+    is_root: bool,
+    neighbours: Vec<usize>,
+    dist: Option<usize>,
+    parent: Option<usize>,
+    children: BTreeSet<usize>,
+}
+
+impl BfsTree {
+    pub fn new(i: usize, neighbours: Vec<usize>, is_root: bool) -> Self {
+        Self {
+            i,
+            is_root,
+            neighbours,
+            dist: None,
+            parent: None,
+            children: BTreeSet::new(),
+        }
+    }
+    // Node i has neighbours adj[i]
+    pub fn graph(adj: &[Vec<usize>], root: usize) -> Vec<Self> {
+        (adj.iter().enumerate())
+            .map(|(i, ns)| Self::new(i, ns.clone(), i == root))
+            .collect()
+    }
+    pub fn dist(&self) -> Option<usize> {
+        self.dist
+    }
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+    pub fn children(&self) -> Vec<usize> {
+        self.children.iter().copied().collect()
+    }
+    fn explore(&mut self, d: usize, from: Option<usize>, out: &mut Vec<(usize, Msg)>) {
+        self.dist = Some(d);
+        (self.neighbours.iter())
+            .filter(|&&j| Some(j) != from)
+            .for_each(|&j| out.push((j, Msg::Explore(d))));
+    }
+}
+
+impl Node for BfsTree {
+    type Msg = Msg;
+
+    fn start(&mut self, out: &mut Vec<(usize, Msg)>) {
+        if self.is_root && self.dist.is_none() {
+            self.explore(0, None, out);
+        }
+    }
+    fn recv(&mut self, from: usize, msg: Msg, out: &mut Vec<(usize, Msg)>) {
+        match msg {
+            Msg::Explore(d) if self.dist.is_none_or(|dist| d + 1 < dist) => {
+                if let Some(p) = self.parent.replace(from) {
+                    out.push((p, Msg::Leave));
+                }
+                out.push((from, Msg::Join));
+                self.explore(d + 1, Some(from), out);
+            }
+            Msg::Explore(_) => {}
+            Msg::Join => {
+                self.children.insert(from);
+            }
+            Msg::Leave => {
+                self.children.remove(&from);
+            }
+        }
+        debug_assert!(
+            !self.is_root || self.parent.is_none(),
+            "Root {} has a parent",
+            self.i
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consensus::network::Network;
+    use crate::consensus::spanning_tree::BfsTree;
+    use rand::Rng;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn bfs_distances_and_consistent_children() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n = rng.gen_range(1..=20);
+            let p = rng.gen_range(0.0..0.5);
+            let adj = rand_graph(&mut rng, n, p);
+            let root = rng.gen_range(0..n);
+            let mut net = Network::new(BfsTree::graph(&adj, root));
+            net.start_all();
+            net.run(&mut rng);
+
+            let dist = bfs(&adj, root);
+            for (i, p) in net.nodes().iter().enumerate() {
+                assert_eq!(p.dist(), Some(dist[i]));
+                match p.parent() {
+                    None => assert_eq!(i, root),
+                    Some(j) => {
+                        assert!(adj[i].contains(&j));
+                        assert_eq!(dist[j] + 1, dist[i]);
+                        assert!(net.node(j).children().contains(&i));
+                    }
+                }
+                assert!((p.children().iter()).all(|&j| net.node(j).parent() == Some(i)));
+            }
+        }
+    }
+
+    // Random connected graph, as a random tree with extra edges
+    fn rand_graph(rng: &mut impl Rng, n: usize, p: f64) -> Vec<Vec<usize>> {
+        let mut adj = vec![Vec::new(); n];
+        for i in 1..n {
+            let j = rng.gen_range(0..i);
+            adj[i].push(j);
+            adj[j].push(i);
+        }
+        for i in 0..n {
+            for j in 0..i {
+                if !adj[i].contains(&j) && rng.gen_bool(p) {
+                    adj[i].push(j);
+                    adj[j].push(i);
+                }
+            }
+        }
+        adj
+    }
+
+    fn bfs(adj: &[Vec<usize>], root: usize) -> Vec<usize> {
+        let mut dist = vec![usize::MAX; adj.len()];
+        dist[root] = 0;
+        let mut queue = VecDeque::from([root]);
+        while let Some(i) = queue.pop_front() {
+            for &j in &adj[i] {
+                if dist[j] == usize::MAX {
+                    dist[j] = dist[i] + 1;
+                    queue.push_back(j);
+                }
+            }
+        }
+        dist
+    }
+}
@@ -0,0 +1,119 @@
+use crate::consensus::SyncNode;
+use std::collections::HashSet;
+
+/// Synchronous network of nodes, which runs each round by collecting every message sent, then delivering them.
+///
+/// A node may crash in any round, possibly in the middle of its broadcast, so only some receivers get its messages in
+/// that round. It neither receives in that round, nor acts afterwards. On unreliable links, an adversary may also lose
+/// any message, by choosing the losses before the run.
+///
+/// # Examples
+/// ```
+/// use rads::consensus::sync_network::SyncNetwork;
+/// use rads::consensus::SyncNode;
+///
+/// // Each node counts the messages it receives from every other node
+/// struct Count(usize, usize, usize);
+/// impl SyncNode for Count {
+///     type Msg = ();
+///     fn send(&mut self, _round: usize, out: &mut Vec<(usize, ())>) {
+///         (0..self.1).filter(|&j| j != self.0).for_each(|j| out.push((j, ())));
+///     }
+///     fn recv(&mut self, _round: usize, msgs: Vec<(usize, ())>) {
+///         self.2 += msgs.len();
+///     }
+/// }
+/// let mut net = SyncNetwork::new((0..3).map(|i| Count(i, 3, 0)).collect());
+/// net.crash(2, 1, vec![0]); // 2 only reaches 0 in round 1
+/// net.run(3);
+/// assert_eq!((net.node(0).2, net.node(1).2), (5, 4));
+/// ```
+pub struct SyncNetwork<N: SyncNode> {
+    nodes: Vec<N>,
+    round: usize,
+    // Round in which each node crashes, and the receivers of its messages in that round
+    crashes: Vec<Option<(usize, Vec<usize>)>>,
+    // Messages lost, as (round, sender, receiver)
+    lost: HashSet<(usize, usize, usize)>,
+    n_sent: usize,
+}
+
+impl<N: SyncNode> SyncNetwork<N> {
+    pub fn new(nodes: Vec<N>) -> Self {
+        let n = nodes.len();
+        Self {
+            nodes,
+            round: 0,
+            crashes: vec![None; n],
+            lost: HashSet::new(),
+            n_sent: 0,
+        }
+    }
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+    pub fn node(&self, i: usize) -> &N {
+        &self.nodes[i]
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    // Next round to run
+    pub fn round(&self) -> usize {
+        self.round
+    }
+    // Messages delivered, including to nodes that crash, but not lost
+    pub fn n_sent(&self) -> usize {
+        self.n_sent
+    }
+    // Crashed in a round already run
+    pub fn is_crashed(&self, i: usize) -> bool {
+        self.crashes[i]
+            .as_ref()
+            .is_some_and(|(r, _)| *r < self.round)
+    }
+    // Crashes node i in the given round, after sending only to the receivers
+    pub fn crash(&mut self, i: usize, round: usize, receivers: Vec<usize>) {
+        assert!(round >= self.round, "Round {round} has already run");
+        self.crashes[i] = Some((round, receivers));
+    }
+    // Loses the message from i to j in the given round
+    pub fn lose(&mut self, round: usize, i: usize, j: usize) {
+        assert!(round >= self.round, "Round {round} has already run");
+        self.lost.insert((round, i, j));
+    }
+
+    pub fn step(&mut self) {
+        let round = self.round;
+        let mut inboxes: Vec<_> = (0..self.len()).map(|_| Vec::new()).collect();
+        for i in 0..self.len() {
+            if self.is_crashed(i) {
+                continue;
+            }
+            let mut out = Vec::new();
+            self.nodes[i].send(round, &mut out);
+            for (j, msg) in out {
+                match &self.crashes[i] {
+                    Some((r, receivers)) if *r == round && !receivers.contains(&j) => {}
+                    _ if self.lost.contains(&(round, i, j)) => {}
+                    _ => {
+                        self.n_sent += 1;
+                        inboxes[j].push((i, msg));
+                    }
+                }
+            }
+        }
+        for (i, msgs) in inboxes.into_iter().enumerate() {
+            if self.crashes[i].as_ref().is_none_or(|(r, _)| *r > round) {
+                self.nodes[i].recv(round, msgs);
+            }
+        }
+        self.round += 1;
+    }
+    pub fn run(&mut self, rounds: usize) {
+        (0..rounds).for_each(|_| self.step());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::crdt::{CmRdt, CvRdt};
+
+/// Grow-only counter of `n` replicas, where each replica only increments its own count.
+///
+/// The value is the sum of counts, and states merge by taking the maximum count of each replica, which is the latest.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::counter::GCounter;
+/// use rads::crdt::CvRdt;
+///
+/// let (mut a, mut b) = (GCounter::new(2), GCounter::new(2));
+/// a.inc(0, 2);
+/// b.inc(1, 3);
+/// a.merge(&b);
+/// a.merge(&b);
+/// assert_eq!(a.value(), 5);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GCounter(Vec<u64>);
+
+impl GCounter {
+    pub fn new(n: usize) -> Self {
+        Self(vec![0; n])
+    }
+    pub fn value(&self) -> u64 {
+        self.0.iter().sum()
+    }
+    // Increments by k at replica i
+    pub fn inc(&mut self, i: usize, k: u64) -> (usize, u64) {
+        self.apply(&(i, k));
+        (i, k)
+    }
+}
+
+impl CvRdt for GCounter {
+    fn merge(&mut self, other: &Self) {
+        for (c, d) in self.0.iter_mut().zip(&other.0) {
+            *c = (*c).max(*d);
+        }
+    }
+}
+
+impl CmRdt for GCounter {
+    // Replica and increment
+    type Op = (usize, u64);
+
+    fn apply(&mut self, &(i, k): &(usize, u64)) {
+        self.0[i] += k;
+    }
+}
+
+/// Counter of `n` replicas that may also decrement, as the difference of grow-only counters of increments and
+/// decrements.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::counter::PNCounter;
+/// use rads::crdt::CvRdt;
+///
+/// let (mut a, mut b) = (PNCounter::new(2), PNCounter::new(2));
+/// a.add(0, 2);
+/// b.add(1, -3);
+/// b.merge(&a);
+/// assert_eq!(b.value(), -1);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PNCounter {
+    inc: GCounter,
+    dec: GCounter,
+}
+
+impl PNCounter {
+    pub fn new(n: usize) -> Self {
+        Self {
+            inc: GCounter::new(n),
+            dec: GCounter::new(n),
+        }
+    }
+    pub fn value(&self) -> i64 {
+        self.inc.value() as i64 - self.dec.value() as i64
+    }
+    // Adds k at replica i
+    pub fn add(&mut self, i: usize, k: i64) -> (usize, i64) {
+        self.apply(&(i, k));
+        (i, k)
+    }
+}
+
+impl CvRdt for PNCounter {
+    fn merge(&mut self, other: &Self) {
+        self.inc.merge(&other.inc);
+        self.dec.merge(&other.dec);
+    }
+}
+
+impl CmRdt for PNCounter {
+    type Op = (usize, i64);
+
+    fn apply(&mut self, &(i, k): &(usize, i64)) {
+        match k >= 0 {
+            true => self.inc.apply(&(i, k as u64)),
+            false => self.dec.apply(&(i, k.unsigned_abs())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crdt::counter::{GCounter, PNCounter};
+    use crate::crdt::tests::check_convergence;
+    use rand::Rng;
+
+    #[test]
+    fn g_counter_converges() {
+        check_convergence(4, GCounter::new(4), |c, i, rng| {
+            c.inc(i, rng.gen_range(0..5))
+        });
+    }
+
+    #[test]
+    fn pn_counter_converges() {
+        check_convergence(4, PNCounter::new(4), |c, i, rng| {
+            c.add(i, rng.gen_range(-5..5))
+        });
+    }
+}
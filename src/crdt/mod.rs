@@ -0,0 +1,105 @@
+pub mod counter;
+pub mod or_set;
+pub mod register;
+pub mod rga;
+
+/// State-based CRDT, whose states form a join semilattice under merge, so that replicas converge by merging states.
+///
+/// Merging is commutative, associative and idempotent, so states may be merged in any order, any number of times, e.g.
+/// by gossip over lossy links that duplicate and reorder messages.
+pub trait CvRdt {
+    // Joins the other state into this one, i.e. the least state that has both
+    fn merge(&mut self, other: &Self);
+}
+
+/// Operation-based CRDT, whose concurrent operations commute, so that replicas applying the same operations converge.
+///
+/// Each mutator applies an operation locally and returns it to be applied by every other replica, exactly once and
+/// after every operation applied before it at its origin, e.g. by causal broadcast.
+pub trait CmRdt {
+    type Op;
+
+    fn apply(&mut self, op: &Self::Op);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::crdt::{CmRdt, CvRdt};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::fmt::Debug;
+
+    fn merged<C: CvRdt + Clone>(a: &C, b: &C) -> C {
+        let mut c = a.clone();
+        c.merge(b);
+        c
+    }
+
+    // Checks that merging is commutative, associative and idempotent over every triple of states
+    pub(crate) fn check_semilattice<C: CvRdt + Clone + PartialEq + Debug>(states: &[C]) {
+        for a in states {
+            assert_eq!(&merged(a, a), a, "Merge is not idempotent");
+            for b in states {
+                assert_eq!(merged(a, b), merged(b, a), "Merge is not commutative");
+                for c in states {
+                    let (ab_c, a_bc) = (merged(&merged(a, b), c), merged(a, &merged(b, c)));
+                    assert_eq!(ab_c, a_bc, "Merge is not associative");
+                }
+            }
+        }
+    }
+
+    // Checks that replicas converge, whether they apply each other's operations in causal order or merge states, over
+    // random histories where replicas mutate by mutate(state, replica, rng) and sync pairwise
+    pub(crate) fn check_convergence<C, F>(n: usize, init: C, mut mutate: F)
+    where
+        C: CvRdt + CmRdt + Clone + PartialEq + Debug,
+        F: FnMut(&mut C, usize, &mut StdRng) -> C::Op,
+    {
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut states = vec![init.clone(); n];
+            // Operations by id, and the ids each replica has applied in order
+            let (mut ops, mut logs) = (Vec::new(), vec![Vec::new(); n]);
+            let mut samples = Vec::new();
+            for _ in 0..30 {
+                let i = rng.gen_range(0..n);
+                if rng.gen_bool(0.6) {
+                    ops.push(mutate(&mut states[i], i, &mut rng));
+                    logs[i].push(ops.len() - 1);
+                } else {
+                    sync(&mut states, &mut logs, &ops, i, rng.gen_range(0..n));
+                }
+                if rng.gen_bool(0.2) {
+                    samples.push(states[i].clone());
+                }
+            }
+            samples.truncate(6);
+            check_semilattice(&samples);
+
+            let merged = states.iter().fold(init.clone(), |s, t| merged(&s, t));
+            for i in 0..n {
+                (0..n).for_each(|j| sync(&mut states, &mut logs, &ops, i, j));
+            }
+            for (i, s) in states.iter().enumerate() {
+                assert_eq!(s, &merged, "Replica {i} diverged with seed {seed}");
+            }
+        }
+    }
+
+    // Applies operations that replica j has applied, and i has not, in the order j applied them
+    fn sync<C: CmRdt>(
+        states: &mut [C],
+        logs: &mut [Vec<usize>],
+        ops: &[C::Op],
+        i: usize,
+        j: usize,
+    ) {
+        for k in logs[j].clone() {
+            if !logs[i].contains(&k) {
+                states[i].apply(&ops[k]);
+                logs[i].push(k);
+            }
+        }
+    }
+}
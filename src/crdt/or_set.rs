@@ -0,0 +1,153 @@
+use crate::crdt::{CmRdt, CvRdt};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Replica that added, then its count of adds to make it unique
+pub type Tag = (usize, u64);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<E> {
+    Add(E, Tag),
+    // Removes the adds observed
+    Remove(E, BTreeSet<Tag>),
+}
+
+/// Observed-remove set of `n` replicas, where an element added concurrently with its removal stays in the set.
+///
+/// Each add is tagged uniquely, and a remove only removes the tags it observed, so a concurrent add survives. Removed
+/// tags are kept as tombstones, so that merging with a state that has not seen the remove does not bring them back.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::or_set::OrSet;
+/// use rads::crdt::CvRdt;
+///
+/// let (mut a, mut b) = (OrSet::new(2), OrSet::new(2));
+/// a.add(0, "x");
+/// b.merge(&a);
+/// // b removes the x it saw, while a adds it again
+/// b.remove("x");
+/// a.add(0, "x");
+/// a.merge(&b);
+/// assert!(a.contains(&"x"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrSet<E: Ord> {
+    // Tags of each element not yet removed, without empty entries
+    adds: BTreeMap<E, BTreeSet<Tag>>,
+    removed: BTreeSet<Tag>,
+    // Count of adds seen by replica
+    counts: Vec<u64>,
+}
+
+impl<E: Ord + Clone> OrSet<E> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            adds: BTreeMap::new(),
+            removed: BTreeSet::new(),
+            counts: vec![0; n],
+        }
+    }
+    pub fn contains(&self, e: &E) -> bool {
+        self.adds.contains_key(e)
+    }
+    pub fn elements(&self) -> impl Iterator<Item = &E> {
+        self.adds.keys()
+    }
+    pub fn len(&self) -> usize {
+        self.adds.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.adds.is_empty()
+    }
+    // Adds e at replica i
+    pub fn add(&mut self, i: usize, e: E) -> Op<E> {
+        let op = Op::Add(e, (i, self.counts[i] + 1));
+        self.apply(&op);
+        op
+    }
+    pub fn remove(&mut self, e: E) -> Op<E> {
+        let tags = self.adds.get(&e).cloned().unwrap_or_default();
+        let op = Op::Remove(e, tags);
+        self.apply(&op);
+        op
+    }
+
+    fn insert(&mut self, e: &E, tags: &BTreeSet<Tag>) {
+        for &(i, k) in tags {
+            self.counts[i] = self.counts[i].max(k);
+        }
+        let tags: BTreeSet<_> = tags.difference(&self.removed).copied().collect();
+        if !tags.is_empty() {
+            self.adds.entry(e.clone()).or_default().extend(tags);
+        }
+    }
+}
+
+impl<E: Ord + Clone> CvRdt for OrSet<E> {
+    fn merge(&mut self, other: &Self) {
+        self.removed.extend(&other.removed);
+        for tags in self.adds.values_mut() {
+            tags.retain(|t| !other.removed.contains(t));
+        }
+        self.adds.retain(|_, tags| !tags.is_empty());
+        other.adds.iter().for_each(|(e, tags)| self.insert(e, tags));
+        for (c, d) in self.counts.iter_mut().zip(&other.counts) {
+            *c = (*c).max(*d);
+        }
+    }
+}
+
+impl<E: Ord + Clone> CmRdt for OrSet<E> {
+    type Op = Op<E>;
+
+    fn apply(&mut self, op: &Op<E>) {
+        match op {
+            Op::Add(e, tag) => self.insert(e, &BTreeSet::from([*tag])),
+            Op::Remove(e, tags) => {
+                self.removed.extend(tags);
+                if let Some(added) = self.adds.get_mut(e) {
+                    added.retain(|t| !tags.contains(t));
+                    if added.is_empty() {
+                        self.adds.remove(e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crdt::or_set::OrSet;
+    use crate::crdt::tests::check_convergence;
+    use crate::crdt::CvRdt;
+    use rand::Rng;
+
+    #[test]
+    fn converges() {
+        check_convergence(3, OrSet::new(3), |s, i, rng| {
+            let e = rng.gen_range(0..4);
+            match rng.gen_bool(0.6) {
+                true => s.add(i, e),
+                false => s.remove(e),
+            }
+        });
+    }
+
+    #[test]
+    fn add_wins_over_concurrent_remove() {
+        let (mut a, mut b) = (OrSet::new(2), OrSet::new(2));
+        a.add(0, 1);
+        b.merge(&a);
+        b.remove(1);
+        b.add(1, 2);
+        let c = a.clone();
+        // Concurrent with b's remove, so 1 stays
+        a.add(0, 1);
+        a.merge(&b);
+        assert_eq!(a.elements().collect::<Vec<_>>(), vec![&1, &2]);
+        // Only the observed 1 is removed
+        b.merge(&c);
+        assert_eq!(b.elements().collect::<Vec<_>>(), vec![&2]);
+    }
+}
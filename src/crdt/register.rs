@@ -0,0 +1,281 @@
+use crate::crdt::{CmRdt, CvRdt};
+use crate::order::vector_clock::VectorClock;
+use crate::order::LogicalClock;
+use std::cmp::Ordering::Less;
+
+// Wall time, logical count within it, and the replica to break ties
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub wall: u64,
+    pub logical: u64,
+    pub replica: usize,
+}
+
+/// Hybrid logical clock by Kulkarni et al., which timestamps events close to physical time while respecting causality.
+///
+/// The wall time is the greatest physical time seen, so it stays within the clock skew of physical time, and the
+/// logical count orders events within it like a Lamport clock. With a physical time of always 0, it is a Lamport clock.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::register::Hlc;
+///
+/// let (mut a, mut b) = (Hlc::new(0), Hlc::new(1));
+/// let s = a.now(100);
+/// // b's physical clock lags, but its timestamps still follow what it receives
+/// b.recv(s, 90);
+/// assert!(b.now(95) > s);
+/// ```
+pub struct Hlc {
+    i: usize,
+    wall: u64,
+    logical: u64,
+}
+
+impl Hlc {
+    pub fn new(i: usize) -> Self {
+        Self {
+            i,
+            wall: 0,
+            logical: 0,
+        }
+    }
+    // Timestamps a local or send event at the physical time
+    pub fn now(&mut self, physical: u64) -> Timestamp {
+        self.recv(self.timestamp(), physical);
+        self.timestamp()
+    }
+    // Observes a timestamp received at the physical time
+    pub fn recv(&mut self, t: Timestamp, physical: u64) {
+        let wall = self.wall.max(t.wall).max(physical);
+        self.logical = match (wall == self.wall, wall == t.wall) {
+            (true, true) => self.logical.max(t.logical) + 1,
+            (true, false) => self.logical + 1,
+            (false, true) => t.logical + 1,
+            (false, false) => 0,
+        };
+        self.wall = wall;
+    }
+    fn timestamp(&self) -> Timestamp {
+        Timestamp {
+            wall: self.wall,
+            logical: self.logical,
+            replica: self.i,
+        }
+    }
+}
+
+/// Last-writer-wins register, which keeps the value with the greatest timestamp.
+///
+/// Concurrent writes are ordered arbitrarily by timestamp, so all but one are lost. Timestamps must be unique, e.g. by
+/// breaking ties by replica, and should follow causality, e.g. from an [Hlc], so that a write replaces what it read.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::register::{Hlc, LwwRegister};
+/// use rads::crdt::CvRdt;
+///
+/// let (mut a, mut b) = (LwwRegister::default(), LwwRegister::default());
+/// let (mut ca, mut cb) = (Hlc::new(0), Hlc::new(1));
+/// a.set("x", ca.now(100));
+/// b.set("y", cb.now(100));
+/// a.merge(&b);
+/// assert_eq!(a.get(), Some(&"y"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LwwRegister<V, T = Timestamp> {
+    value: Option<(V, T)>,
+}
+
+impl<V, T> Default for LwwRegister<V, T> {
+    fn default() -> Self {
+        Self { value: None }
+    }
+}
+
+impl<V: Clone, T: Ord + Clone> LwwRegister<V, T> {
+    pub fn get(&self) -> Option<&V> {
+        self.value.as_ref().map(|(v, _)| v)
+    }
+    pub fn timestamp(&self) -> Option<&T> {
+        self.value.as_ref().map(|(_, t)| t)
+    }
+    pub fn set(&mut self, v: V, t: T) -> (V, T) {
+        let op = (v, t);
+        self.apply(&op);
+        op
+    }
+}
+
+impl<V: Clone, T: Ord + Clone> CvRdt for LwwRegister<V, T> {
+    fn merge(&mut self, other: &Self) {
+        if let Some(op) = &other.value {
+            self.apply(op);
+        }
+    }
+}
+
+impl<V: Clone, T: Ord + Clone> CmRdt for LwwRegister<V, T> {
+    type Op = (V, T);
+
+    fn apply(&mut self, (v, t): &(V, T)) {
+        if self.timestamp().is_none_or(|s| t > s) {
+            self.value = Some((v.clone(), t.clone()));
+        }
+    }
+}
+
+/// Multi-value register of `n` replicas, which keeps every value written concurrently until a later write replaces
+/// them.
+///
+/// Each write is stamped by a [VectorClock] greater than those of the values it replaces, so a value is kept unless
+/// another value's clock is greater. Concurrent values are left to the application to resolve, e.g. by writing their
+/// union.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::register::MvRegister;
+/// use rads::crdt::CvRdt;
+///
+/// let (mut a, mut b) = (MvRegister::new(2), MvRegister::new(2));
+/// a.set(0, "x");
+/// b.set(1, "y");
+/// a.merge(&b);
+/// assert_eq!(a.values(), vec![&"x", &"y"]);
+/// a.set(0, "z");
+/// b.merge(&a);
+/// assert_eq!(b.values(), vec![&"z"]);
+/// ```
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct MvRegister<V> {
+    n: usize,
+    values: Vec<(V, VectorClock)>,
+}
+
+impl<V: Clone + PartialEq> MvRegister<V> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            values: Vec::new(),
+        }
+    }
+    // Concurrent values, by writer
+    pub fn values(&self) -> Vec<&V> {
+        let mut values: Vec<_> = self.values.iter().collect();
+        values.sort_by_key(|(_, c)| c.pid());
+        values.into_iter().map(|(v, _)| v).collect()
+    }
+    // Replaces every value at replica i
+    pub fn set(&mut self, i: usize, v: V) -> (V, VectorClock) {
+        // Exceed every write of replica i seen, so that merging keeps the clock's invariant
+        let mut clock = VectorClock::new(i, self.n);
+        while self.values.iter().any(|(_, c)| c[i] >= clock[i]) {
+            clock = clock.extend();
+        }
+        let clock = (self.values.iter()).fold(clock, |clock, (_, c)| clock.merge(c));
+        let op = (v, clock);
+        self.apply(&op);
+        op
+    }
+}
+
+impl<V: Clone + PartialEq> PartialEq for MvRegister<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.values.len() == other.values.len()
+            && self.values.iter().all(|e| other.values.contains(e))
+    }
+}
+
+impl<V: Clone + PartialEq> CvRdt for MvRegister<V> {
+    fn merge(&mut self, other: &Self) {
+        other.values.iter().for_each(|op| self.apply(op));
+    }
+}
+
+impl<V: Clone + PartialEq> CmRdt for MvRegister<V> {
+    type Op = (V, VectorClock);
+
+    fn apply(&mut self, (v, clock): &(V, VectorClock)) {
+        if self.values.iter().any(|(_, c)| c >= clock) {
+            return;
+        }
+        // Replaces the values it happens after
+        self.values
+            .retain(|(_, c)| c.partial_cmp(clock) != Some(Less));
+        self.values.push((v.clone(), clock.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crdt::register::{Hlc, LwwRegister, MvRegister, Timestamp};
+    use crate::crdt::tests::check_convergence;
+    use crate::crdt::{CmRdt, CvRdt};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn hlc_follows_causality_near_physical_time() {
+        let mut clocks: Vec<_> = (0..3).map(Hlc::new).collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sent: Vec<Timestamp> = Vec::new();
+        for now in 0..1000u64 {
+            let i = rng.gen_range(0..3);
+            // Physical clocks skewed by up to 10
+            let physical = now + 10 * i as u64;
+            let t = match sent.last() {
+                Some(&s) if rng.gen_bool(0.5) => {
+                    clocks[i].recv(s, physical);
+                    let t = clocks[i].now(physical);
+                    assert!(t > s);
+                    t
+                }
+                _ => clocks[i].now(physical),
+            };
+            assert!(physical <= t.wall && t.wall <= now + 20);
+            sent.push(t);
+        }
+    }
+
+    #[test]
+    fn lww_register_converges() {
+        let mut clocks: Vec<_> = (0..3).map(Hlc::new).collect();
+        check_convergence(3, LwwRegister::default(), |r, i, rng| {
+            if let Some(&t) = r.timestamp() {
+                clocks[i].recv(t, 0);
+            }
+            let t = clocks[i].now(rng.gen_range(0..3));
+            r.set(rng.gen_range(0..10), t)
+        });
+    }
+
+    #[test]
+    fn mv_register_converges() {
+        check_convergence(3, MvRegister::new(3), |r, i, rng| {
+            let op = r.set(i, rng.gen_range(0..10));
+            // A write replaces every value seen
+            assert_eq!(r.values(), vec![&op.0]);
+            op
+        });
+    }
+
+    #[test]
+    fn mv_register_keeps_concurrent_values() {
+        let mut rs: Vec<_> = (0..3).map(|_| MvRegister::new(3)).collect();
+        let ops: Vec<_> = (0..3).map(|i| rs[i].set(i, i)).collect();
+        rs[0].apply(&ops[2]);
+        rs[0].apply(&ops[1]);
+        let merged = rs.iter().fold(MvRegister::new(3), |mut s, r| {
+            s.merge(r);
+            s
+        });
+        assert_eq!(rs[0], merged);
+        assert_eq!(merged.values(), vec![&0, &1, &2]);
+        rs[1].merge(&merged);
+        rs[1].set(1, 3);
+        let r = rs[1].clone();
+        rs[0].merge(&r);
+        assert_eq!(rs[0].values(), vec![&3]);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::crdt::{CmRdt, CvRdt};
+use std::collections::{BTreeMap, HashMap};
+
+// Lamport timestamp, then replica to break ties
+pub type Id = (u64, usize);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<V> {
+    // Inserts the value after the element, or at the head if none
+    Insert(Id, Option<Id>, V),
+    Delete(Id),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Element<V> {
+    after: Option<Id>,
+    value: V,
+    deleted: bool,
+}
+
+/// Replicated growable array by Roh et al., a sequence that replicas edit concurrently, e.g. the text of a shared
+/// document.
+///
+/// Each element is inserted after another, with a unique id greater than any seen, so the elements form a tree rooted
+/// at the head. The sequence lists the tree depth first, with the children of an element in descending order of id, so
+/// an element follows the one it was inserted after, while concurrent insertions at the same place are ordered
+/// consistently and not interleaved. A deleted element stays as a tombstone, so that insertions after it have a place.
+///
+/// # Examples
+/// ```
+/// use rads::crdt::rga::Rga;
+/// use rads::crdt::CvRdt;
+///
+/// let (mut a, mut b) = (Rga::default(), Rga::default());
+/// a.insert(0, 0, 'a');
+/// a.insert(0, 1, 'c');
+/// b.merge(&a);
+/// a.insert(0, 1, 'b');
+/// b.delete(0);
+/// b.insert(1, 1, 'd');
+/// a.merge(&b);
+/// assert_eq!(a.to_vec(), vec!['b', 'c', 'd']);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rga<V> {
+    elements: BTreeMap<Id, Element<V>>,
+    // Greatest timestamp seen
+    clock: u64,
+}
+
+impl<V> Default for Rga<V> {
+    fn default() -> Self {
+        Self {
+            elements: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<V: Clone> Rga<V> {
+    pub fn to_vec(&self) -> Vec<V> {
+        let elements = self.ids().into_iter().map(|id| &self.elements[&id]);
+        elements.map(|e| e.value.clone()).collect()
+    }
+    pub fn len(&self) -> usize {
+        self.elements.values().filter(|e| !e.deleted).count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // Inserts v at the index at replica i
+    pub fn insert(&mut self, i: usize, index: usize, v: V) -> Op<V> {
+        let after = index.checked_sub(1).map(|k| self.ids()[k]);
+        let op = Op::Insert((self.clock + 1, i), after, v);
+        self.apply(&op);
+        op
+    }
+    pub fn delete(&mut self, index: usize) -> Op<V> {
+        let op = Op::Delete(self.ids()[index]);
+        self.apply(&op);
+        op
+    }
+
+    // Ids of elements not deleted, in order
+    fn ids(&self) -> Vec<Id> {
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        // Ascending, so that popping from the stack visits the greatest child first
+        for (&id, e) in &self.elements {
+            children.entry(e.after).or_default().push(id);
+        }
+        let mut stack = children.remove(&None).unwrap_or_default();
+        let mut ids = Vec::new();
+        while let Some(id) = stack.pop() {
+            if !self.elements[&id].deleted {
+                ids.push(id);
+            }
+            stack.extend(children.remove(&Some(id)).unwrap_or_default());
+        }
+        ids
+    }
+}
+
+impl<V: Clone> CvRdt for Rga<V> {
+    fn merge(&mut self, other: &Self) {
+        for (id, e) in &other.elements {
+            let f = self.elements.entry(*id).or_insert_with(|| e.clone());
+            f.deleted |= e.deleted;
+        }
+        self.clock = self.clock.max(other.clock);
+    }
+}
+
+impl<V: Clone> CmRdt for Rga<V> {
+    type Op = Op<V>;
+
+    fn apply(&mut self, op: &Op<V>) {
+        match op {
+            Op::Insert(id, after, v) => {
+                self.clock = self.clock.max(id.0);
+                let e = Element {
+                    after: *after,
+                    value: v.clone(),
+                    deleted: false,
+                };
+                self.elements.entry(*id).or_insert(e);
+            }
+            Op::Delete(id) => {
+                if let Some(e) = self.elements.get_mut(id) {
+                    e.deleted = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crdt::rga::Rga;
+    use crate::crdt::tests::check_convergence;
+    use crate::crdt::CvRdt;
+    use rand::Rng;
+
+    #[test]
+    fn converges() {
+        check_convergence(3, Rga::default(), |s, i, rng| {
+            let len = s.len();
+            match len == 0 || rng.gen_bool(0.7) {
+                true => s.insert(i, rng.gen_range(0..=len), rng.gen_range(0..10)),
+                false => s.delete(rng.gen_range(0..len)),
+            }
+        });
+    }
+
+    #[test]
+    fn does_not_interleave_concurrent_inserts() {
+        let (mut a, mut b) = (Rga::default(), Rga::default());
+        a.insert(0, 0, '.');
+        b.merge(&a);
+        for (k, c) in "ab".chars().enumerate() {
+            a.insert(0, k, c);
+        }
+        for (k, c) in "xyz".chars().enumerate() {
+            b.insert(1, k + 1, c);
+        }
+        a.insert(0, 2, 'c');
+        a.merge(&b);
+        b.merge(&a);
+        assert_eq!(a.to_vec(), b.to_vec());
+        assert_eq!(a.to_vec().into_iter().collect::<String>(), "abc.xyz");
+    }
+}
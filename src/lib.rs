@@ -1,2 +1,5 @@
+pub mod consensus;
+pub mod crdt;
 pub mod order;
+pub mod stabilize;
 pub mod sync;
@@ -0,0 +1,231 @@
+use crate::order::checkpoint::Checkpoint;
+use crate::order::history::History;
+use crate::order::vector_clock::VectorClock;
+use crate::order::{HasEvents, LogicalClock, OrdProcess};
+
+// Vector clock of an event, piggybacking the checkpoint index of its process
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct BcsClock {
+    pub clock: VectorClock,
+    pub index: usize,
+}
+
+impl LogicalClock for BcsClock {
+    fn new(i: usize, n_procs: usize) -> Self {
+        Self {
+            clock: VectorClock::new(i, n_procs),
+            index: 0,
+        }
+    }
+    fn extend(&self) -> Self {
+        Self {
+            clock: self.clock.extend(),
+            index: self.index,
+        }
+    }
+    fn merge(&self, other: &Self) -> Self {
+        Self {
+            clock: self.clock.merge(&other.clock),
+            index: self.index.max(other.index),
+        }
+    }
+}
+
+/// Communication-induced checkpointing by Briatico, Ciuffoletti & Simoncini (BCS).
+///
+/// Processes take basic checkpoints independently, which increments their checkpoint index. Before delivering a
+/// message with a greater index, the receiver takes a forced checkpoint with that index. Hence no message sent after a
+/// checkpoint of index `k` is received before a checkpoint of index `k`, so the first checkpoint of each process with
+/// index `>= k` forms a consistent global checkpoint. No checkpoint is useless, which avoids the domino effect.
+///
+/// # Examples
+/// ```
+/// use rads::order::bcs::BcsProcess;
+/// use rads::order::OrdProcess;
+///
+/// let mut ps = [BcsProcess::new(0, 2), BcsProcess::new(1, 2)];
+/// ps[0].checkpoint();
+/// let mut e = None;
+/// ps[0].send(|ev| e = Some(ev));
+/// ps[1].recv(|| e.unwrap()); // forced to checkpoint before receiving
+/// assert_eq!(ps[1].checkpoints().len(), 2);
+/// assert_eq!(ps[1].index(), 1);
+/// ```
+pub struct BcsProcess {
+    i: usize,
+    n_procs: usize,
+    events: History<BcsClock>,
+    checkpoints: Vec<Checkpoint>,
+    n_forced: usize,
+}
+
+impl BcsProcess {
+    pub fn new(i: usize, n_procs: usize) -> Self {
+        Self {
+            i,
+            n_procs,
+            events: History::new(),
+            checkpoints: vec![Checkpoint::initial(i, n_procs)],
+            n_forced: 0,
+        }
+    }
+    // Index of the latest checkpoint, which may skip indices due to forced checkpoints
+    pub fn index(&self) -> usize {
+        self.checkpoints.last().unwrap().index
+    }
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+    pub fn n_forced(&self) -> usize {
+        self.n_forced
+    }
+
+    // Takes a basic checkpoint
+    pub fn checkpoint(&mut self) -> &Checkpoint {
+        self.take(self.index() + 1)
+    }
+    fn take(&mut self, index: usize) -> &Checkpoint {
+        let c = Checkpoint {
+            index,
+            ..self.volatile()
+        };
+        self.checkpoints.push(c);
+        self.checkpoints.last().unwrap()
+    }
+    fn next_event(&self) -> BcsClock {
+        let mut e = (self.last_event().cloned())
+            .unwrap_or_else(|| BcsClock::new(self.i, self.n_procs))
+            .extend();
+        e.index = self.index();
+        e
+    }
+    // Current state, as if it were the next checkpoint
+    pub fn volatile(&self) -> Checkpoint {
+        Checkpoint {
+            index: self.index() + 1,
+            event: self.events.next_index(),
+            clock: self.last_event().map_or_else(
+                || VectorClock::new(self.i, self.n_procs),
+                |e| e.clock.clone(),
+            ),
+        }
+    }
+}
+
+// Global checkpoint of index k, i.e. the first checkpoint of each process with index >= k, else its volatile state
+pub fn global_checkpoint(procs: &[BcsProcess], k: usize) -> Vec<Checkpoint> {
+    procs
+        .iter()
+        .map(|p| {
+            let c = p.checkpoints.iter().find(|c| c.index >= k);
+            c.cloned().unwrap_or_else(|| p.volatile())
+        })
+        .collect()
+}
+
+impl HasEvents<BcsClock> for BcsProcess {
+    fn pid(&self) -> usize {
+        self.i
+    }
+    fn n_procs(&self) -> usize {
+        self.n_procs
+    }
+    fn history(&self) -> &History<BcsClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<BcsClock> {
+        &mut self.events
+    }
+}
+
+impl OrdProcess<BcsClock> for BcsProcess {
+    fn exec<F: FnOnce()>(&mut self, f: F) {
+        let e = self.next_event();
+        self.push_event(e);
+        f();
+    }
+    // Piggybacks the latest checkpoint index
+    fn send<F: FnOnce(BcsClock)>(&mut self, send_fn: F) {
+        let e = self.next_event();
+        self.push_event(e.clone());
+        send_fn(e);
+    }
+    fn recv<F: FnOnce() -> BcsClock>(&mut self, recv_fn: F) {
+        let e_recv = recv_fn();
+        if e_recv.index > self.index() {
+            self.take(e_recv.index);
+            self.n_forced += 1;
+        }
+        let mut e = (self.last_event().cloned())
+            .unwrap_or_else(|| BcsClock::new(self.pid(), self.n_procs()))
+            .merge(&e_recv);
+        e.index = self.index();
+        self.push_event(e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::bcs::{global_checkpoint, BcsProcess};
+    use crate::order::checkpoint::{is_consistent, useless};
+    use crate::order::OrdProcess;
+    use rand::Rng;
+
+    #[test]
+    fn forced_checkpoints_prevent_domino_effect() {
+        let mut ps = [BcsProcess::new(0, 2), BcsProcess::new(1, 2)];
+        let mut e = None;
+        // Same pattern that causes the domino effect for uncoordinated checkpoints
+        for _ in 0..10 {
+            ps[0].send(|ev| e = Some(ev));
+            ps[1].recv(|| e.take().unwrap());
+            ps[1].checkpoint();
+            ps[1].send(|ev| e = Some(ev));
+            ps[0].recv(|| e.take().unwrap());
+            ps[0].checkpoint();
+        }
+        assert_eq!(ps[0].n_forced(), 10);
+        assert_eq!(ps[1].n_forced(), 9);
+        assert!(useless(&nodes(&ps)).is_empty());
+    }
+
+    #[test]
+    fn index_forms_consistent_global_checkpoint() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n_procs = rng.gen_range(2..=5);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| BcsProcess::new(i, n_procs)).collect();
+            let mut in_flight = vec![Vec::new(); n_procs];
+            for _ in 0..50 {
+                let i = rng.gen_range(0..n_procs);
+                match rng.gen_range(0..4) {
+                    0 => {
+                        ps[i].checkpoint();
+                    }
+                    1 if !in_flight[i].is_empty() => {
+                        let k = rng.gen_range(0..in_flight[i].len());
+                        let e = in_flight[i].swap_remove(k);
+                        ps[i].recv(|| e);
+                    }
+                    _ => {
+                        let j = (i + rng.gen_range(1..n_procs)) % n_procs;
+                        ps[i].send(|e| in_flight[j].push(e));
+                    }
+                }
+            }
+            let max_index = ps.iter().map(BcsProcess::index).max().unwrap();
+            for k in 0..=max_index {
+                let cut = global_checkpoint(&ps, k);
+                assert!(is_consistent(&cut.iter().collect::<Vec<_>>()));
+            }
+            assert!(useless(&nodes(&ps)).is_empty());
+        }
+    }
+
+    fn nodes(ps: &[BcsProcess]) -> Vec<Vec<crate::order::checkpoint::Checkpoint>> {
+        ps.iter()
+            .map(|p| [p.checkpoints(), &[p.volatile()]].concat())
+            .collect()
+    }
+}
@@ -1,3 +1,4 @@
+use crate::order::history::History;
 use crate::order::{HasEvents, LogicalClock, OrdProcess};
 use std::collections::HashMap;
 
@@ -30,24 +31,32 @@ impl LogicalClock for ChandyLamportClock {
 pub struct ChandyLamportProc {
     i: usize,
     n: usize,
-    events: Vec<ChandyLamportClock>,
+    events: History<ChandyLamportClock>,
+    // Index of the first event after each snapshot
     snapshots: HashMap<ChandyLamportClock, usize>,
 }
 impl ChandyLamportProc {
-    pub fn snapshots(&self) -> Vec<(ChandyLamportClock, &[ChandyLamportClock])> {
+    pub fn snapshots(&self) -> Vec<(ChandyLamportClock, Vec<&ChandyLamportClock>)> {
         self.snapshots
             .iter()
-            .map(|(k, v)| (k.clone(), &self.events[..*v]))
+            .map(|(k, v)| (k.clone(), self.events.range(..*v).collect()))
             .collect()
     }
 }
 
 impl ChandyLamportProc {
     fn new(i: usize, n: usize) -> Self {
+        Self::with_history(i, n, History::new())
+    }
+    // Keeps at most the latest cap events, so snapshots only list those retained
+    pub fn with_cap(i: usize, n: usize, cap: usize) -> Self {
+        Self::with_history(i, n, History::with_cap(cap))
+    }
+    fn with_history(i: usize, n: usize, events: History<ChandyLamportClock>) -> Self {
         Self {
             i,
             n,
-            events: Vec::new(),
+            events,
             snapshots: HashMap::new(),
         }
     }
@@ -62,20 +71,17 @@ impl ChandyLamportProc {
 }
 
 impl HasEvents<ChandyLamportClock> for ChandyLamportProc {
-    fn last_event(&self) -> Option<&ChandyLamportClock> {
-        self.events.last()
-    }
-    fn push_event(&mut self, e: ChandyLamportClock) {
-        self.events.push(e)
-    }
     fn pid(&self) -> usize {
         self.i
     }
     fn n_procs(&self) -> usize {
         self.n
     }
-    fn events(&self) -> &[ChandyLamportClock] {
-        self.events.as_slice()
+    fn history(&self) -> &History<ChandyLamportClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<ChandyLamportClock> {
+        &mut self.events
     }
 }
 
@@ -89,7 +95,7 @@ impl OrdProcess<ChandyLamportClock> for ChandyLamportProc {
                 .merge(&e_recv);
             self.push_event(e);
         } else if let std::collections::hash_map::Entry::Vacant(e) = self.snapshots.entry(e_recv) {
-            e.insert(self.events.len());
+            e.insert(self.events.next_index());
             // TODO broadcast to all
         }
     }
@@ -141,6 +147,15 @@ mod tests {
         p2.join().unwrap();
     }
 
+    #[test]
+    fn capped_history_keeps_latest_events() {
+        let mut p = ChandyLamportProc::with_cap(0, 2, 2);
+        (0..5).for_each(|_| p.exec(|| {}));
+        assert_eq!(p.events().count(), 2);
+        assert_eq!(p.history().first_index(), 3);
+        assert_eq!(p.last_event().unwrap().clk, 5);
+    }
+
     #[test]
     fn snapshot_after_send_before_recv() {
         // 1 snapshot
@@ -0,0 +1,372 @@
+use crate::order::vector_clock::VectorClock;
+use crate::order::{HasEvents, LogicalClock};
+use std::collections::VecDeque;
+
+// Local checkpoint of a process, tagged with the clock of its last event
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Checkpoint {
+    // 0 for the initial state
+    pub index: usize,
+    // Index of the first event after the checkpoint
+    pub event: usize,
+    pub clock: VectorClock,
+}
+
+impl Checkpoint {
+    pub fn initial(i: usize, n_procs: usize) -> Self {
+        Self {
+            index: 0,
+            event: 0,
+            clock: VectorClock::new(i, n_procs),
+        }
+    }
+    pub fn pid(&self) -> usize {
+        self.clock.pid()
+    }
+}
+
+/// Process that independently saves its state, starting with the initial checkpoint.
+pub trait Checkpointing: HasEvents<VectorClock> {
+    fn checkpoints(&self) -> &[Checkpoint];
+    fn push_checkpoint(&mut self, c: Checkpoint);
+
+    // Saves the state after the last event
+    fn checkpoint(&mut self) -> &Checkpoint {
+        let c = Checkpoint {
+            index: self.checkpoints().len(),
+            event: self.history().next_index(),
+            clock: (self.last_event().cloned())
+                .unwrap_or_else(|| VectorClock::new(self.pid(), self.n_procs())),
+        };
+        self.push_checkpoint(c);
+        self.checkpoints().last().unwrap()
+    }
+    // Current state that is lost on failure, as if it were the next checkpoint
+    fn volatile(&self) -> Checkpoint {
+        Checkpoint {
+            index: self.checkpoints().len(),
+            event: self.history().next_index(),
+            clock: (self.last_event().cloned())
+                .unwrap_or_else(|| VectorClock::new(self.pid(), self.n_procs())),
+        }
+    }
+}
+
+// No process has received a message that another has yet to send, i.e. no orphan message
+pub fn is_consistent(cut: &[&Checkpoint]) -> bool {
+    cut.iter()
+        .all(|c| cut.iter().all(|d| d.clock[c.pid()] <= c.clock[c.pid()]))
+}
+
+// Positions (process, checkpoint) of checkpoints that belong to no consistent global checkpoint, given the checkpoints
+// then volatile state of each process
+pub fn useless(nodes: &[Vec<Checkpoint>]) -> Vec<(usize, usize)> {
+    (nodes.iter().enumerate())
+        .flat_map(|(i, cs)| (0..cs.len()).map(move |x| (i, x)))
+        .filter(|&(i, x)| min_global_checkpoint(nodes, i, x).is_none())
+        .collect()
+}
+
+// Earliest consistent global checkpoint with checkpoint x of process i, by advancing others past orphan messages
+fn min_global_checkpoint(
+    nodes: &[Vec<Checkpoint>],
+    i: usize,
+    x: usize,
+) -> Option<Vec<&Checkpoint>> {
+    let mut cut: Vec<_> = (0..nodes.len())
+        .map(|j| if j == i { x } else { 0 })
+        .collect();
+    loop {
+        // Each process must have sent everything the others have received from it
+        let seen: Vec<_> = (0..nodes.len())
+            .map(|j| {
+                (0..nodes.len())
+                    .map(|k| nodes[k][cut[k]].clock[j])
+                    .max()
+                    .unwrap()
+            })
+            .collect();
+        if seen[i] > nodes[i][x].clock[i] {
+            return None;
+        }
+        let next: Vec<_> = (0..nodes.len())
+            .map(|j| match j == i {
+                true => x,
+                false => nodes[j].partition_point(|c| c.clock[j] < seen[j]),
+            })
+            .collect();
+        if next == cut {
+            return Some((0..nodes.len()).map(|j| &nodes[j][cut[j]]).collect());
+        }
+        cut = next;
+    }
+}
+
+/// Rollback-dependency graph (R-graph) of independent checkpoints, to find the latest consistent recovery line.
+///
+/// Rolling back to a checkpoint undoes the sends after it, so checkpoints that received them become orphans and must be
+/// rolled back too. Edge `(i, x) -> (j, y)` means that undoing checkpoint `x` of process `i` undoes checkpoint `y` of
+/// `j`, where `y` is the first checkpoint whose clock has seen more of `i` than checkpoint `x - 1`.
+///
+/// Without coordination, this may cascade to the initial state of all processes, i.e. the domino effect.
+///
+/// # Examples
+/// ```
+/// use rads::order::checkpoint::{Checkpointing, RollbackGraph};
+/// use rads::order::vector_clock::VecProcess;
+/// use rads::order::OrdProcess;
+///
+/// let mut ps = [VecProcess::new(0, 2), VecProcess::new(1, 2)];
+/// ps[0].checkpoint();
+/// let mut e = None;
+/// ps[0].send(|ev| e = Some(ev));
+/// ps[1].recv(|| e.unwrap());
+/// ps[1].checkpoint();
+///
+/// // Undoes the send, hence p1's checkpoint of the receive
+/// let line = RollbackGraph::new(&ps).recovery_line(&[0]);
+/// assert_eq!(line.checkpoints().iter().map(|c| c.index).collect::<Vec<_>>(), vec![1, 0]);
+/// ```
+pub struct RollbackGraph {
+    // Checkpoints of each process, then its volatile state
+    nodes: Vec<Vec<Checkpoint>>,
+    edges: Vec<Vec<Vec<(usize, usize)>>>,
+}
+
+// Latest consistent checkpoint of each process, where volatile states are kept
+pub struct RecoveryLine(Vec<Checkpoint>);
+
+impl RollbackGraph {
+    pub fn new<P: Checkpointing>(procs: &[P]) -> Self {
+        let nodes: Vec<Vec<_>> = procs
+            .iter()
+            .map(|p| {
+                let mut cs = p.checkpoints().to_vec();
+                cs.push(p.volatile());
+                cs
+            })
+            .collect();
+        let edges = (0..nodes.len())
+            .map(|i| {
+                (0..nodes[i].len())
+                    .map(|x| Self::undone_by(&nodes, i, x))
+                    .collect()
+            })
+            .collect();
+        Self { nodes, edges }
+    }
+
+    fn undone_by(nodes: &[Vec<Checkpoint>], i: usize, x: usize) -> Vec<(usize, usize)> {
+        if x == 0 {
+            // Initial state cannot be undone
+            return Vec::new();
+        }
+        let mut es: Vec<_> = (x + 1 < nodes[i].len())
+            .then_some((i, x + 1))
+            .into_iter()
+            .collect();
+        let seen = nodes[i][x - 1].clock[i];
+        for (j, cs) in nodes.iter().enumerate().filter(|&(j, _)| j != i) {
+            let y = cs.partition_point(|c| c.clock[i] <= seen);
+            if y < cs.len() {
+                es.push((j, y));
+            }
+        }
+        es
+    }
+
+    // Checkpoints or volatile states undone by undoing checkpoint x of process i
+    pub fn edges(&self, i: usize, x: usize) -> &[(usize, usize)] {
+        &self.edges[i][x]
+    }
+
+    // Undoes the volatile state of failed processes and everything that depends on it
+    pub fn recovery_line(&self, failed: &[usize]) -> RecoveryLine {
+        let mut undone: Vec<_> = self.nodes.iter().map(|cs| vec![false; cs.len()]).collect();
+        let mut q: VecDeque<_> = failed
+            .iter()
+            .map(|&i| (i, self.nodes[i].len() - 1))
+            .collect();
+        while let Some((i, x)) = q.pop_front() {
+            if !std::mem::replace(&mut undone[i][x], true) {
+                q.extend(self.edges(i, x));
+            }
+        }
+        RecoveryLine(
+            (self.nodes.iter().zip(&undone))
+                .map(|(cs, undone)| {
+                    // Undone checkpoints form a suffix
+                    let x = undone.iter().position(|u| *u).unwrap_or(cs.len());
+                    cs[x - 1].clone()
+                })
+                .collect(),
+        )
+    }
+}
+
+impl RecoveryLine {
+    // Volatile state of process i is kept if its index is the number of checkpoints it has taken
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.0
+    }
+    // All processes restart from their initial state
+    pub fn domino_effect(&self) -> bool {
+        self.0.iter().all(|c| c.index == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::checkpoint::{
+        is_consistent, useless, Checkpoint, Checkpointing, RollbackGraph,
+    };
+    use crate::order::vector_clock::{VecProcess, VectorClock};
+    use crate::order::{LogicalClock, OrdProcess};
+    use rand::Rng;
+
+    #[test]
+    fn domino_effect() {
+        let mut ps = [VecProcess::new(0, 2), VecProcess::new(1, 2)];
+        let mut e = None;
+        // Each process checkpoints between receiving and replying, so each checkpoint depends on an undone send
+        for _ in 0..10 {
+            ps[0].send(|ev| e = Some(ev));
+            ps[1].recv(|| e.take().unwrap());
+            ps[1].checkpoint();
+            ps[1].send(|ev| e = Some(ev));
+            ps[0].recv(|| e.take().unwrap());
+            ps[0].checkpoint();
+        }
+        ps[0].send(|ev| e = Some(ev));
+        ps[1].recv(|| e.take().unwrap());
+        ps[1].checkpoint();
+
+        let graph = RollbackGraph::new(&ps);
+        assert!(graph.recovery_line(&[0]).domino_effect());
+        // Since only the initial checkpoints and p1's last one are part of some consistent global checkpoint
+        let nodes: Vec<_> = (ps.iter())
+            .map(|p| [p.checkpoints(), &[p.volatile()]].concat())
+            .collect();
+        let expected: Vec<_> = (0..2).flat_map(|i| (1..=10).map(move |x| (i, x))).collect();
+        assert_eq!(useless(&nodes), expected);
+        // p1 recovers alone, since it has done nothing since its checkpoint
+        let line = graph.recovery_line(&[1]);
+        assert!(!line.domino_effect());
+        let indices: Vec<_> = line.checkpoints().iter().map(|c| c.index).collect();
+        assert_eq!(indices, vec![11, 11]);
+    }
+
+    #[test]
+    fn checkpoint_after_send_rolls_back_alone() {
+        let mut rng = rand::thread_rng();
+        let n_procs = 4;
+        let mut ps: Vec<_> = (0..n_procs).map(|i| VecProcess::new(i, n_procs)).collect();
+        for (i, j) in rand_messages(&mut rng, n_procs, 100) {
+            let mut e = None;
+            ps[i].send(|ev| e = Some(ev));
+            ps[i].checkpoint();
+            ps[j].recv(|| e.unwrap());
+        }
+        let graph = RollbackGraph::new(&ps);
+        for i in 0..n_procs {
+            let line = graph.recovery_line(&[i]);
+            for (j, c) in line.checkpoints().iter().enumerate() {
+                let expected = ps[j].checkpoints().len() - usize::from(i == j);
+                assert_eq!(c.index, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn latest_consistent_line() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n_procs = rng.gen_range(2..=3);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| VecProcess::new(i, n_procs)).collect();
+            for (i, j) in rand_messages(&mut rng, n_procs, 8) {
+                let mut e = None;
+                ps[i].send(|ev| e = Some(ev));
+                ps[j].recv(|| e.unwrap());
+                for p in ps.iter_mut().filter(|_| rng.gen_bool(0.3)) {
+                    p.checkpoint();
+                }
+            }
+            let failed: Vec<_> = (0..n_procs).filter(|_| rng.gen_bool(0.5)).collect();
+            let line = RollbackGraph::new(&ps).recovery_line(&failed);
+            let line: Vec<_> = line.checkpoints().iter().collect();
+            assert!(is_consistent(&line));
+
+            // Componentwise max of all consistent lines
+            let candidates: Vec<Vec<_>> = (ps.iter().enumerate())
+                .map(|(i, p)| {
+                    let mut cs = p.checkpoints().to_vec();
+                    if !failed.contains(&i) {
+                        cs.push(p.volatile());
+                    }
+                    cs
+                })
+                .collect();
+            for cut in cuts(&candidates) {
+                if is_consistent(&cut) {
+                    assert!(cut.iter().zip(&line).all(|(c, l)| c.index <= l.index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn useless_iff_in_no_consistent_cut() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n_procs = rng.gen_range(2..=3);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| VecProcess::new(i, n_procs)).collect();
+            for (i, j) in rand_messages(&mut rng, n_procs, 8) {
+                let mut e = None;
+                ps[i].send(|ev| e = Some(ev));
+                ps[j].recv(|| e.unwrap());
+                for p in ps.iter_mut().filter(|_| rng.gen_bool(0.3)) {
+                    p.checkpoint();
+                }
+            }
+            let nodes: Vec<_> = (ps.iter())
+                .map(|p| [p.checkpoints(), &[p.volatile()]].concat())
+                .collect();
+            let consistent: Vec<_> = (cuts(&nodes).into_iter())
+                .filter(|cut| is_consistent(cut))
+                .collect();
+            let expected: Vec<_> = (nodes.iter().enumerate())
+                .flat_map(|(i, cs)| cs.iter().map(move |c| (i, c.index)))
+                .filter(|&(i, x)| consistent.iter().all(|cut| cut[i].index != x))
+                .collect();
+            assert_eq!(useless(&nodes), expected);
+        }
+    }
+
+    #[test]
+    fn initial_checkpoint() {
+        let mut p = VecProcess::new(1, 3);
+        assert!(p.checkpoints() == [Checkpoint::initial(1, 3)]);
+        p.exec(|| {});
+        let c = p.checkpoint().clone();
+        assert_eq!((c.index, c.event), (1, 1));
+        assert!(VectorClock::new(1, 3) < c.clock);
+    }
+
+    fn rand_messages(rng: &mut impl Rng, n_procs: usize, n: usize) -> Vec<(usize, usize)> {
+        (0..n)
+            .map(|_| {
+                let i = rng.gen_range(0..n_procs);
+                (i, (i + rng.gen_range(1..n_procs)) % n_procs)
+            })
+            .collect()
+    }
+
+    fn cuts(candidates: &[Vec<Checkpoint>]) -> Vec<Vec<&Checkpoint>> {
+        candidates.iter().fold(vec![Vec::new()], |cuts, cs| {
+            cuts.iter()
+                .flat_map(|cut| cs.iter().map(|c| [cut.as_slice(), &[c]].concat()))
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,591 @@
+use crate::order::history::History;
+use crate::order::vector_clock::VectorClock;
+use crate::order::{HasEvents, OrdProcess};
+use std::collections::{BTreeSet, HashMap};
+
+// Probe of detection seq started by initiator, through the path of blocked processes starting with it
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Probe {
+    pub initiator: usize,
+    pub seq: usize,
+    pub path: Vec<usize>,
+    pub clock: VectorClock,
+}
+
+/// Deadlock detection by Chandy, Misra & Haas' edge chasing in the AND model, where a process is blocked until every
+/// process it waits for grants its request.
+///
+/// A blocked process starts a detection by sending a probe along each wait-for edge. A blocked process forwards the
+/// first probe of each detection along its own edges, while an active process discards it. A probe is also discarded
+/// if its sender no longer waits for the receiver, i.e. the receiver has granted it, which requires requests and probes
+/// to share a FIFO channel. Hence the initiator is deadlocked if its probe returns, and the path of the probe is the
+/// cycle.
+///
+/// # Examples
+/// ```
+/// use rads::order::deadlock::AndProcess;
+///
+/// let mut ps = [AndProcess::new(0, 2), AndProcess::new(1, 2)];
+/// ps[0].wait_for(&[1]);
+/// ps[1].requested(0);
+/// ps[1].wait_for(&[0]);
+/// ps[0].requested(1);
+/// let mut probes = Vec::new();
+/// ps[0].initiate(|j, p| probes.push((j, p)));
+/// while let Some((j, p)) = probes.pop() {
+///     ps[j].recv(p, |k, p| probes.push((k, p)));
+/// }
+/// assert_eq!(ps[0].deadlock(), Some(&[0, 1][..]));
+/// ```
+pub struct AndProcess {
+    i: usize,
+    n_procs: usize,
+    events: History<VectorClock>,
+    waits_for: Vec<usize>,
+    // Processes waiting for this process
+    requesters: Vec<usize>,
+    seq: usize,
+    // Latest detection of each initiator that was forwarded
+    forwarded: Vec<usize>,
+    deadlock: Option<Vec<usize>>,
+}
+
+impl AndProcess {
+    pub fn new(i: usize, n_procs: usize) -> Self {
+        Self {
+            i,
+            n_procs,
+            events: History::new(),
+            waits_for: Vec::new(),
+            requesters: Vec::new(),
+            seq: 0,
+            forwarded: vec![0; n_procs],
+            deadlock: None,
+        }
+    }
+    pub fn waits_for(&self) -> &[usize] {
+        &self.waits_for
+    }
+    pub fn is_blocked(&self) -> bool {
+        !self.waits_for.is_empty()
+    }
+    // Cycle of processes waiting for each other, starting with this process, found by its latest detection
+    pub fn deadlock(&self) -> Option<&[usize]> {
+        self.deadlock.as_deref()
+    }
+
+    // Blocks until every process in deps grants
+    pub fn wait_for(&mut self, deps: &[usize]) {
+        deps.iter().for_each(|&j| {
+            if !self.waits_for.contains(&j) {
+                self.waits_for.push(j);
+            }
+        });
+    }
+    pub fn granted(&mut self, j: usize) {
+        self.waits_for.retain(|&k| k != j);
+    }
+    pub fn requesters(&self) -> &[usize] {
+        &self.requesters
+    }
+    // Receives the request of process k
+    pub fn requested(&mut self, k: usize) {
+        if !self.requesters.contains(&k) {
+            self.requesters.push(k);
+        }
+    }
+    pub fn grant(&mut self, k: usize) {
+        self.requesters.retain(|&j| j != k);
+    }
+
+    pub fn initiate<F: FnMut(usize, Probe)>(&mut self, send_fn: F) {
+        if !self.is_blocked() {
+            return;
+        }
+        self.seq += 1;
+        self.forwarded[self.i] = self.seq;
+        self.deadlock = None;
+        self.forward(self.i, self.seq, vec![self.i], send_fn);
+    }
+    pub fn recv<F: FnMut(usize, Probe)>(&mut self, p: Probe, send_fn: F) {
+        let from = p.clock.pid();
+        OrdProcess::recv(self, || p.clock);
+        if !self.is_blocked() || !self.requesters.contains(&from) {
+            return;
+        }
+        if p.seq <= self.forwarded[p.initiator] {
+            if p.initiator == self.i && p.seq == self.seq {
+                self.deadlock = Some(p.path);
+            }
+            return;
+        }
+        self.forwarded[p.initiator] = p.seq;
+        let mut path = p.path;
+        path.push(self.i);
+        self.forward(p.initiator, p.seq, path, send_fn);
+    }
+    fn forward<F: FnMut(usize, Probe)>(
+        &mut self,
+        initiator: usize,
+        seq: usize,
+        path: Vec<usize>,
+        mut send_fn: F,
+    ) {
+        for j in self.waits_for.clone() {
+            OrdProcess::send(self, |clock| {
+                let path = path.clone();
+                send_fn(
+                    j,
+                    Probe {
+                        initiator,
+                        seq,
+                        path,
+                        clock,
+                    },
+                )
+            });
+        }
+    }
+}
+
+impl HasEvents<VectorClock> for AndProcess {
+    fn pid(&self) -> usize {
+        self.i
+    }
+    fn n_procs(&self) -> usize {
+        self.n_procs
+    }
+    fn history(&self) -> &History<VectorClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        &mut self.events
+    }
+}
+
+impl OrdProcess<VectorClock> for AndProcess {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrKind {
+    Query,
+    // Every process the sender transitively waits for is blocked
+    Reply,
+}
+
+// Query or reply of detection seq started by initiator, where a reply carries the blocked processes it reached
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct OrMsg {
+    pub kind: OrKind,
+    pub initiator: usize,
+    pub seq: usize,
+    pub reached: Vec<usize>,
+    pub clock: VectorClock,
+}
+
+// Detection that a blocked process takes part in, since it was engaged by the first query
+struct Engagement {
+    seq: usize,
+    // None for the initiator
+    engager: Option<usize>,
+    pending: usize,
+    reached: BTreeSet<usize>,
+}
+
+/// Deadlock detection by Chandy, Misra & Haas' diffusing computation in the OR model, where a process is blocked until
+/// any process it waits for grants its request.
+///
+/// A blocked process starts a detection by querying every process it waits for. The first query of a detection engages
+/// a blocked process, which queries its own dependents and replies to its engager once all of them have replied, while
+/// later queries are replied to immediately. An active process discards queries, and unblocking forgets every
+/// engagement. Hence the initiator is deadlocked once all of its dependents reply, as every process it transitively
+/// waits for is blocked.
+///
+/// # Examples
+/// ```
+/// use rads::order::deadlock::OrProcess;
+///
+/// let mut ps = [OrProcess::new(0, 3), OrProcess::new(1, 3), OrProcess::new(2, 3)];
+/// ps[0].wait_for(&[1, 2]);
+/// ps[1].wait_for(&[0]);
+/// let mut msgs = Vec::new();
+/// ps[0].initiate(|j, m| msgs.push((j, m)));
+/// while let Some((j, m)) = msgs.pop() {
+///     ps[j].recv(m, |k, m| msgs.push((k, m)));
+/// }
+/// assert_eq!(ps[0].deadlock(), None); // 2 is active, so it may grant 0
+///
+/// ps[2].wait_for(&[1]);
+/// ps[0].initiate(|j, m| msgs.push((j, m)));
+/// while let Some((j, m)) = msgs.pop() {
+///     ps[j].recv(m, |k, m| msgs.push((k, m)));
+/// }
+/// assert_eq!(ps[0].deadlock(), Some(&[0, 1, 2][..]));
+/// ```
+pub struct OrProcess {
+    i: usize,
+    n_procs: usize,
+    events: History<VectorClock>,
+    waits_for: Vec<usize>,
+    seq: usize,
+    // By initiator
+    engagements: HashMap<usize, Engagement>,
+    deadlock: Option<Vec<usize>>,
+}
+
+impl OrProcess {
+    pub fn new(i: usize, n_procs: usize) -> Self {
+        Self {
+            i,
+            n_procs,
+            events: History::new(),
+            waits_for: Vec::new(),
+            seq: 0,
+            engagements: HashMap::new(),
+            deadlock: None,
+        }
+    }
+    pub fn waits_for(&self) -> &[usize] {
+        &self.waits_for
+    }
+    pub fn is_blocked(&self) -> bool {
+        !self.waits_for.is_empty()
+    }
+    // Processes this process transitively waits for, which are all blocked, found by its latest detection
+    pub fn deadlock(&self) -> Option<&[usize]> {
+        self.deadlock.as_deref()
+    }
+
+    // Blocks until any process in deps grants
+    pub fn wait_for(&mut self, deps: &[usize]) {
+        deps.iter().for_each(|&j| {
+            if !self.waits_for.contains(&j) {
+                self.waits_for.push(j);
+            }
+        });
+    }
+    // Unblocks, as j granted the request
+    pub fn grant(&mut self, j: usize) {
+        debug_assert!(
+            self.waits_for.contains(&j),
+            "Process {} granted without a request",
+            j
+        );
+        self.waits_for.clear();
+        self.engagements.clear();
+    }
+
+    pub fn initiate<F: FnMut(usize, OrMsg)>(&mut self, send_fn: F) {
+        if !self.is_blocked() {
+            return;
+        }
+        self.seq += 1;
+        self.deadlock = None;
+        self.engage(self.i, self.seq, None, send_fn);
+    }
+    pub fn recv<F: FnMut(usize, OrMsg)>(&mut self, m: OrMsg, mut send_fn: F) {
+        let from = m.clock.pid();
+        OrdProcess::recv(self, || m.clock);
+        if !self.is_blocked() {
+            return;
+        }
+        let engagement = (self.engagements.get_mut(&m.initiator)).filter(|en| en.seq >= m.seq);
+        match (m.kind, engagement) {
+            (OrKind::Query, None) => self.engage(m.initiator, m.seq, Some(from), send_fn),
+            (OrKind::Query, Some(en)) if en.seq == m.seq => {
+                self.reply(from, m.initiator, m.seq, Vec::new(), &mut send_fn)
+            }
+            (OrKind::Reply, Some(en)) if en.seq == m.seq && en.pending > 0 => {
+                en.pending -= 1;
+                en.reached.extend(m.reached);
+                if en.pending == 0 {
+                    let reached: Vec<_> = en.reached.iter().copied().collect();
+                    match en.engager {
+                        None => self.deadlock = Some(reached),
+                        Some(engager) => {
+                            self.reply(engager, m.initiator, m.seq, reached, &mut send_fn)
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    fn engage<F: FnMut(usize, OrMsg)>(
+        &mut self,
+        initiator: usize,
+        seq: usize,
+        engager: Option<usize>,
+        mut send_fn: F,
+    ) {
+        let en = Engagement {
+            seq,
+            engager,
+            pending: self.waits_for.len(),
+            reached: BTreeSet::from([self.i]),
+        };
+        self.engagements.insert(initiator, en);
+        for j in self.waits_for.clone() {
+            self.send_msg(j, OrKind::Query, initiator, seq, Vec::new(), &mut send_fn);
+        }
+    }
+    fn reply<F: FnMut(usize, OrMsg)>(
+        &mut self,
+        to: usize,
+        initiator: usize,
+        seq: usize,
+        reached: Vec<usize>,
+        send_fn: &mut F,
+    ) {
+        self.send_msg(to, OrKind::Reply, initiator, seq, reached, send_fn);
+    }
+    fn send_msg<F: FnMut(usize, OrMsg)>(
+        &mut self,
+        to: usize,
+        kind: OrKind,
+        initiator: usize,
+        seq: usize,
+        reached: Vec<usize>,
+        send_fn: &mut F,
+    ) {
+        OrdProcess::send(self, |clock| {
+            send_fn(
+                to,
+                OrMsg {
+                    kind,
+                    initiator,
+                    seq,
+                    reached,
+                    clock,
+                },
+            )
+        });
+    }
+}
+
+impl HasEvents<VectorClock> for OrProcess {
+    fn pid(&self) -> usize {
+        self.i
+    }
+    fn n_procs(&self) -> usize {
+        self.n_procs
+    }
+    fn history(&self) -> &History<VectorClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        &mut self.events
+    }
+}
+
+impl OrdProcess<VectorClock> for OrProcess {}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::deadlock::{AndProcess, OrProcess, Probe};
+    use rand::Rng;
+    use std::collections::{BTreeSet, VecDeque};
+
+    #[test]
+    fn and_cycles_of_any_length() {
+        for k in 1..=8 {
+            // Cycle 0 -> 1 -> ... -> k-1 -> 0, with a tail k -> k+1 -> 0 that waits for the cycle
+            let n_procs = k + 2;
+            let mut ps: Vec<_> = (0..n_procs).map(|i| AndProcess::new(i, n_procs)).collect();
+            (0..k).for_each(|i| request(&mut ps, i, &[(i + 1) % k]));
+            request(&mut ps, k, &[k + 1]);
+            request(&mut ps, k + 1, &[0]);
+            run_and(&mut ps, &mut rand::thread_rng());
+            for (i, p) in ps.iter().enumerate() {
+                let expected: Option<Vec<_>> =
+                    (i < k).then(|| (0..k).map(|j| (i + j) % k).collect());
+                assert_eq!(p.deadlock().map(<[usize]>::to_vec), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn and_deadlocked_iff_on_cycle() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n_procs = rng.gen_range(1..=7);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| AndProcess::new(i, n_procs)).collect();
+            for i in 0..n_procs {
+                let deps: Vec<_> = (0..n_procs).filter(|_| rng.gen_bool(0.25)).collect();
+                request(&mut ps, i, &deps);
+            }
+            run_and(&mut ps, &mut rng);
+            let deps: Vec<_> = ps.iter().map(|p| p.waits_for().to_vec()).collect();
+            for (i, p) in ps.iter().enumerate() {
+                assert_eq!(p.deadlock().is_some(), reachable(&deps, i).contains(&i));
+                if let Some(cycle) = p.deadlock() {
+                    assert_eq!(cycle[0], i);
+                    let next = cycle[1..].iter().chain([&i]);
+                    assert!(cycle.iter().zip(next).all(|(&j, k)| deps[j].contains(k)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn and_no_false_deadlock() {
+        enum Msg {
+            Request,
+            Probe(Probe),
+        }
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n_procs = rng.gen_range(2..=6);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| AndProcess::new(i, n_procs)).collect();
+            // FIFO channel of each (sender, receiver)
+            let mut chans: Vec<Vec<VecDeque<Msg>>> = (0..n_procs)
+                .map(|_| (0..n_procs).map(|_| VecDeque::new()).collect())
+                .collect();
+            for _ in 0..200 {
+                let i = rng.gen_range(0..n_procs);
+                let j = rng.gen_range(0..n_procs);
+                match rng.gen_range(0..4) {
+                    // Only an active process requests or grants
+                    0 if !ps[i].is_blocked() => {
+                        let deps: Vec<_> = (0..n_procs).filter(|_| rng.gen_bool(0.3)).collect();
+                        ps[i].wait_for(&deps);
+                        deps.iter()
+                            .for_each(|&j| chans[i][j].push_back(Msg::Request));
+                    }
+                    1 if !ps[i].is_blocked() => {
+                        for k in ps[i].requesters().to_vec() {
+                            ps[i].grant(k);
+                            ps[k].granted(i);
+                        }
+                    }
+                    2 => ps[i].initiate(|j, p| chans[i][j].push_back(Msg::Probe(p))),
+                    _ => match chans[i][j].pop_front() {
+                        Some(Msg::Request) => ps[j].requested(i),
+                        Some(Msg::Probe(p)) => {
+                            ps[j].recv(p, |k, p| chans[j][k].push_back(Msg::Probe(p)))
+                        }
+                        None => {}
+                    },
+                }
+            }
+            // Deadlock is stable, so a detected cycle still exists
+            for p in &ps {
+                if let Some(cycle) = p.deadlock() {
+                    let next = cycle[1..].iter().chain(&cycle[..1]);
+                    assert!(cycle
+                        .iter()
+                        .zip(next)
+                        .all(|(&j, k)| ps[j].waits_for().contains(k)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn or_deadlocked_iff_no_path_to_active() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n_procs = rng.gen_range(1..=7);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| OrProcess::new(i, n_procs)).collect();
+            for p in &mut ps {
+                let deps: Vec<_> = (0..n_procs).filter(|_| rng.gen_bool(0.3)).collect();
+                p.wait_for(&deps);
+            }
+            run_or(&mut ps, &mut rng);
+            let deps: Vec<_> = ps.iter().map(|p| p.waits_for().to_vec()).collect();
+            for (i, p) in ps.iter().enumerate() {
+                let mut reached = reachable(&deps, i);
+                reached.insert(i);
+                let deadlocked =
+                    !deps[i].is_empty() && reached.iter().all(|&j| !deps[j].is_empty());
+                assert_eq!(p.deadlock().is_some(), deadlocked);
+                if deadlocked {
+                    assert_eq!(
+                        p.deadlock(),
+                        Some(&reached.into_iter().collect::<Vec<_>>()[..])
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn or_no_false_deadlock() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n_procs = rng.gen_range(2..=6);
+            let mut ps: Vec<_> = (0..n_procs).map(|i| OrProcess::new(i, n_procs)).collect();
+            let mut msgs = Vec::new();
+            for _ in 0..100 {
+                let i = rng.gen_range(0..n_procs);
+                match rng.gen_range(0..4) {
+                    0 if !ps[i].is_blocked() => {
+                        let deps: Vec<_> = (0..n_procs).filter(|_| rng.gen_bool(0.3)).collect();
+                        ps[i].wait_for(&deps);
+                    }
+                    1 if !ps[i].is_blocked() => {
+                        for p in &mut ps {
+                            if p.waits_for().contains(&i) {
+                                p.grant(i);
+                            }
+                        }
+                    }
+                    2 => ps[i].initiate(|j, m| msgs.push((j, m))),
+                    _ if !msgs.is_empty() => {
+                        let (j, m) = msgs.swap_remove(rng.gen_range(0..msgs.len()));
+                        ps[j].recv(m, |k, m| msgs.push((k, m)));
+                    }
+                    _ => {}
+                }
+            }
+            // Deadlock is stable, so every reached process still waits only for reached processes
+            for p in &ps {
+                if let Some(reached) = p.deadlock() {
+                    assert!(reached.iter().all(|&j| {
+                        let deps = ps[j].waits_for();
+                        !deps.is_empty() && deps.iter().all(|k| reached.contains(k))
+                    }));
+                }
+            }
+        }
+    }
+
+    fn run_and(ps: &mut [AndProcess], rng: &mut impl Rng) {
+        let mut probes = Vec::new();
+        ps.iter_mut()
+            .for_each(|p| p.initiate(|j, p| probes.push((j, p))));
+        while !probes.is_empty() {
+            let (j, p) = probes.swap_remove(rng.gen_range(0..probes.len()));
+            ps[j].recv(p, |k, p| probes.push((k, p)));
+        }
+    }
+
+    fn run_or(ps: &mut [OrProcess], rng: &mut impl Rng) {
+        let mut msgs = Vec::new();
+        ps.iter_mut()
+            .for_each(|p| p.initiate(|j, m| msgs.push((j, m))));
+        while !msgs.is_empty() {
+            let (j, m) = msgs.swap_remove(rng.gen_range(0..msgs.len()));
+            ps[j].recv(m, |k, m| msgs.push((k, m)));
+        }
+    }
+
+    // Process i waits for deps, whose requests have been received
+    fn request(ps: &mut [AndProcess], i: usize, deps: &[usize]) {
+        ps[i].wait_for(deps);
+        deps.iter().for_each(|&j| ps[j].requested(i));
+    }
+
+    // Processes reachable from i by at least one wait-for edge
+    fn reachable(deps: &[Vec<usize>], i: usize) -> BTreeSet<usize> {
+        let mut reached = BTreeSet::new();
+        let mut stack = deps[i].clone();
+        while let Some(j) = stack.pop() {
+            if reached.insert(j) {
+                stack.extend(&deps[j]);
+            }
+        }
+        reached
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+use std::ops::{Bound, RangeBounds};
+
+pub type Iter<'a, Event> = std::collections::vec_deque::Iter<'a, Event>;
+
+/// Events of a process in program order, where each event keeps its index after older events are discarded.
+///
+/// Events are discarded from the front, either by GC or by evicting the oldest event once the history exceeds its
+/// capacity. Hence indices of retained events lie in `first_index()..next_index()`.
+///
+/// # Examples
+/// ```
+/// use rads::order::history::History;
+///
+/// let mut h = History::with_cap(2);
+/// assert_eq!(h.push('a'), 0);
+/// assert_eq!(h.push('b'), 1);
+/// assert_eq!(h.push('c'), 2); // evicts 'a'
+/// assert_eq!(h.get(0), None);
+/// assert_eq!(h.get(2), Some(&'c'));
+/// assert_eq!(h.iter().collect::<String>(), "bc");
+/// ```
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct History<Event> {
+    events: VecDeque<Event>,
+    // Index of the front event
+    offset: usize,
+    cap: Option<usize>,
+}
+
+impl<Event> History<Event> {
+    pub fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+            offset: 0,
+            cap: None,
+        }
+    }
+    // Keeps at most the latest cap events
+    pub fn with_cap(cap: usize) -> Self {
+        assert!(cap > 0, "Expect to keep at least the last event");
+        Self {
+            events: VecDeque::with_capacity(cap),
+            offset: 0,
+            cap: Some(cap),
+        }
+    }
+
+    // Returns the index of the event
+    pub fn push(&mut self, e: Event) -> usize {
+        if self.cap == Some(self.events.len()) {
+            self.events.pop_front();
+            self.offset += 1;
+        }
+        self.events.push_back(e);
+        self.next_index() - 1
+    }
+    pub fn last(&self) -> Option<&Event> {
+        self.events.back()
+    }
+    pub fn get(&self, index: usize) -> Option<&Event> {
+        self.events.get(index.checked_sub(self.offset)?)
+    }
+
+    // Number of retained events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+    // Index of the oldest retained event
+    pub fn first_index(&self) -> usize {
+        self.offset
+    }
+    // Index of the next event to push
+    pub fn next_index(&self) -> usize {
+        self.offset + self.events.len()
+    }
+
+    pub fn iter(&self) -> Iter<'_, Event> {
+        self.events.iter()
+    }
+    // Retained events within the range of indices
+    pub fn range<R: RangeBounds<usize>>(&self, indices: R) -> Iter<'_, Event> {
+        let start = match indices.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match indices.end_bound() {
+            Bound::Included(&i) => i.saturating_add(1),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => usize::MAX,
+        };
+        let clamp = |i: usize| i.clamp(self.offset, self.next_index()) - self.offset;
+        self.events
+            .range(clamp(start)..clamp(end).max(clamp(start)))
+    }
+
+    // Index of the first event where pred is false, given that pred is true for a prefix of events
+    pub fn partition_point<P: FnMut(&Event) -> bool>(&self, pred: P) -> usize {
+        self.offset + self.events.partition_point(pred)
+    }
+    // Discards events before index
+    pub fn drain_to(&mut self, index: usize) -> Vec<Event> {
+        let n = index.saturating_sub(self.offset).min(self.events.len());
+        self.offset += n;
+        self.events.drain(..n).collect()
+    }
+}
+
+impl<Event> Default for History<Event> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::history::History;
+
+    #[test]
+    fn indices_survive_wrap_around() {
+        let mut h = History::new();
+        for round in 0..10 {
+            for i in 0..7 {
+                assert_eq!(h.push(round * 7 + i), round * 7 + i);
+            }
+            // Keep last 3 events, so the ring buffer wraps on later rounds
+            let gc = h.drain_to(h.next_index() - 3);
+            assert_eq!(gc.len(), if round == 0 { 4 } else { 7 });
+            assert_eq!(h.len(), 3);
+            assert_eq!(h.first_index(), round * 7 + 4);
+            let events: Vec<_> = h.iter().copied().collect();
+            assert_eq!(events, (round * 7 + 4..round * 7 + 7).collect::<Vec<_>>());
+            assert!((h.first_index()..h.next_index()).all(|i| h.get(i) == Some(&i)));
+        }
+    }
+
+    #[test]
+    fn range_clamps_to_retained() {
+        let mut h = History::with_cap(5);
+        (0..8).for_each(|i| {
+            h.push(i);
+        });
+        let range = |r: std::ops::Range<usize>| h.range(r).copied().collect::<Vec<_>>();
+        assert_eq!(range(0..4), vec![3]);
+        assert_eq!(range(4..6), vec![4, 5]);
+        assert_eq!(range(6..100), vec![6, 7]);
+        assert_eq!(range(100..200), vec![]);
+        assert_eq!(h.range(..=4).copied().collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(h.partition_point(|&e| e < 5), 5);
+        assert_eq!(h.drain_to(5), vec![3, 4]);
+        assert_eq!(h.drain_to(5), vec![]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::order::history::History;
 use crate::order::{pairwise_max, CausalOrd, GCClock, HasEvents, LogicalClock, OrdProcess};
 use std::cmp::Ordering;
 use std::collections::VecDeque;
@@ -9,6 +10,23 @@ pub struct MatrixClock {
     clk: Vec<Vec<usize>>,
 }
 
+impl MatrixClock {
+    // Process that owns this clock
+    pub fn pid(&self) -> usize {
+        self.i
+    }
+    // Sequence number of own latest event
+    pub fn seq(&self) -> usize {
+        self.clk[self.i][self.i]
+    }
+    // For each origin process, the latest sequence number that all processes have seen, i.e. column-wise min
+    pub fn stable_frontier(&self) -> Vec<usize> {
+        (0..self.clk.len())
+            .map(|j| self.clk.iter().map(|vi| vi[j]).min().unwrap_or(0))
+            .collect()
+    }
+}
+
 impl GCClock for MatrixClock {
     fn gc(&self, latest: &Self) -> bool {
         let seq = self.clk[self.i][self.i];
@@ -92,51 +110,113 @@ impl PartialEq<Self> for MatrixClock {
 pub struct GCProcess {
     i: usize,
     n_procs: usize,
-    events: VecDeque<MatrixClock>,
+    events: History<MatrixClock>,
 }
 
 impl GCProcess {
     pub fn new(i: usize, n_procs: usize) -> Self {
-        Self {
-            i,
-            n_procs,
-            events: VecDeque::new(),
-        }
+        Self::with_history(i, n_procs, History::new())
+    }
+    // Keeps at most the latest cap events, even if they cannot be GCed yet
+    pub fn with_cap(i: usize, n_procs: usize, cap: usize) -> Self {
+        Self::with_history(i, n_procs, History::with_cap(cap))
+    }
+    fn with_history(i: usize, n_procs: usize, events: History<MatrixClock>) -> Self {
+        Self { i, n_procs, events }
     }
     pub fn gc(&mut self) -> Vec<MatrixClock> {
-        let Some(latest) = self.events.back() else {
+        let Some(latest) = self.events.last() else {
             return Vec::new()
         };
         let i = self.events.partition_point(|c| c.gc(latest));
-        self.events.drain(..i).collect()
+        self.events.drain_to(i)
     }
 }
 
 impl OrdProcess<MatrixClock> for GCProcess {}
 
 impl HasEvents<MatrixClock> for GCProcess {
-    fn last_event(&self) -> Option<&MatrixClock> {
-        self.events.back()
-    }
-    fn push_event(&mut self, e: MatrixClock) {
-        self.events.push_back(e);
-    }
     fn pid(&self) -> usize {
         self.i
     }
     fn n_procs(&self) -> usize {
         self.n_procs
     }
-    fn events(&self) -> &[MatrixClock] {
-        let (front, _back) = self.events.as_slices();
-        front
+    fn history(&self) -> &History<MatrixClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<MatrixClock> {
+        &mut self.events
+    }
+}
+
+/// Buffer of messages from any origin, which are discarded once stable, i.e. seen by all processes.
+///
+/// Causal broadcast must buffer messages in case another process has yet to receive them. A message sent at sequence
+/// number `s` by process `j` is stable once the column-wise min of the latest matrix clock is at least `s` for `j`.
+///
+/// # Examples
+/// ```
+/// use rads::order::LogicalClock;
+/// use rads::order::matrix_clock::{MatrixClock, StableBuffer};
+///
+/// let mut buf = StableBuffer::new(2);
+/// let e = MatrixClock::new(0, 2).extend(); // p0 sends "hi" to p1
+/// let f = MatrixClock::new(1, 2).merge(&e);
+/// buf.push(&e, "hi");
+/// assert!(buf.gc(&e).is_empty()); // p0 does not know if p1 has seen "hi"
+/// let e = e.merge(&f.extend()); // p1 replies
+/// assert_eq!(buf.gc(&e), vec!["hi"]);
+/// ```
+pub struct StableBuffer<T> {
+    // Messages of each origin in FIFO order, with their sequence number
+    msgs: Vec<VecDeque<(usize, T)>>,
+}
+
+impl<T> StableBuffer<T> {
+    pub fn new(n_procs: usize) -> Self {
+        Self {
+            msgs: (0..n_procs).map(|_| VecDeque::new()).collect(),
+        }
+    }
+    // Buffers a message sent with clock `sent`, which must be in FIFO order per origin
+    pub fn push(&mut self, sent: &MatrixClock, msg: T) {
+        let q = &mut self.msgs[sent.pid()];
+        debug_assert!(
+            q.back().is_none_or(|(s, _)| *s < sent.seq()),
+            "Expect messages of process {} in FIFO order",
+            sent.pid()
+        );
+        q.push_back((sent.seq(), msg));
+    }
+    pub fn len(&self) -> usize {
+        self.msgs.iter().map(VecDeque::len).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.msgs.iter().all(VecDeque::is_empty)
+    }
+    // Unstable messages by origin then FIFO order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.msgs.iter().flatten().map(|(_, m)| m)
+    }
+    // Discards stable messages of all origins, given the latest clock of the buffering process
+    pub fn gc(&mut self, latest: &MatrixClock) -> Vec<T> {
+        let frontier = latest.stable_frontier();
+        self.msgs
+            .iter_mut()
+            .zip(frontier)
+            .flat_map(|(q, seq)| {
+                let i = q.partition_point(|(s, _)| *s <= seq);
+                q.drain(..i).map(|(_, m)| m).collect::<Vec<_>>()
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::order::matrix_clock::{GCProcess, MatrixClock};
-    use crate::order::{HasEvents, LogicalClock, OrdProcess};
+    use crate::order::matrix_clock::{GCProcess, MatrixClock, StableBuffer};
+    use crate::order::{GCClock, HasEvents, LogicalClock, OrdProcess};
     use rand::Rng;
 
     #[test]
@@ -175,6 +255,113 @@ mod tests {
         assert_eq!(ps[0].events().len(), 1); // recv event only
     }
 
+    #[test]
+    fn events_after_wrap_around() {
+        let mut ps = [GCProcess::new(0, 2), GCProcess::new(1, 2)];
+        let mut n_gc = 0;
+        for round in 1..=20 {
+            for _ in 0..round % 7 {
+                ps[0].exec(|| {});
+            }
+            // Round trip so that 0 can GC all but the last event
+            for (i, j) in [(0, 1), (1, 0)] {
+                let mut e = None;
+                ps[i].send(|ev| e = Some(ev));
+                ps[j].recv(|| e.unwrap());
+            }
+            let n_events = ps[0].history().len();
+            let gc = ps[0].gc();
+            assert_eq!(gc.len(), n_events - 1); // all but recv
+            n_gc += gc.len();
+            ps[0].exec(|| {});
+            assert_eq!(ps[0].events().count(), 2);
+            assert_eq!(ps[0].history().first_index(), n_gc);
+            let last = ps[0].history().get(ps[0].history().next_index() - 1);
+            assert_eq!(ps[0].events().last(), last);
+        }
+    }
+
+    #[test]
+    fn capped_before_gc() {
+        let mut ps = [GCProcess::with_cap(0, 2, 4), GCProcess::new(1, 2)];
+        (0..10).for_each(|_| ps[0].exec(|| {}));
+        assert_eq!(ps[0].events().count(), 4);
+        assert_eq!(ps[0].history().first_index(), 6);
+
+        // Round trip so that 0 can GC all but the last event that it still keeps
+        for (i, j) in [(0, 1), (1, 0)] {
+            let mut e = None;
+            ps[i].send(|ev| e = Some(ev));
+            ps[j].recv(|| e.unwrap());
+        }
+        assert_eq!(ps[0].history().first_index(), 8);
+        assert_eq!(ps[0].gc().len(), 3);
+        assert_eq!(ps[0].events().count(), 1);
+        assert_eq!(ps[0].history().first_index(), 11);
+    }
+
+    #[test]
+    fn frontier_agrees_with_gc() {
+        let mut rng = rand::thread_rng();
+        let n_procs = rng.gen_range(2..=10);
+        let mut ps: Vec<_> = (0..n_procs).map(|i| GCProcess::new(i, n_procs)).collect();
+        for _ in 0..1000 {
+            let (i, j) = (rng.gen_range(0..n_procs), rng.gen_range(0..n_procs));
+            if i == j {
+                ps[i].exec(|| {});
+                continue;
+            }
+            let mut e = None;
+            ps[i].send(|ev| e = Some(ev));
+            ps[j].recv(|| e.unwrap());
+            let latest = ps[j].last_event().unwrap();
+            let frontier = latest.stable_frontier();
+            for e in ps.iter().flat_map(|p| p.last_event()) {
+                assert_eq!(e.gc(latest), frontier[e.pid()] >= e.seq());
+            }
+        }
+    }
+
+    #[test]
+    fn discards_stable_of_any_origin() {
+        let n_procs = 4;
+        let mut ps: Vec<_> = (0..n_procs).map(|i| GCProcess::new(i, n_procs)).collect();
+        let mut bufs: Vec<_> = (0..n_procs).map(|_| StableBuffer::new(n_procs)).collect();
+        let mut seen = vec![vec![false; n_procs]; n_procs]; // [msg][proc]
+        let mut broadcast = |i: usize, msg: Option<usize>| {
+            let mut e = None;
+            ps[i].send(|ev| e = Some(ev));
+            let e = e.unwrap();
+            if let Some(m) = msg {
+                bufs[i].push(&e, m);
+                seen[m][i] = true;
+            }
+            for j in (0..n_procs).filter(|&j| j != i) {
+                ps[j].recv(|| e.clone());
+                if let Some(m) = msg {
+                    bufs[j].push(&e, m);
+                    seen[m][j] = true;
+                }
+                for m in bufs[j].gc(ps[j].last_event().unwrap()) {
+                    assert!(
+                        seen[m].iter().all(|s| *s),
+                        "Discarded {m} before all have seen it"
+                    );
+                }
+            }
+        };
+
+        // Each process i broadcasts message i, which is buffered
+        for i in 0..n_procs {
+            broadcast(i, Some(i));
+        }
+        // Everyone learns that everyone has seen all messages by acknowledging
+        for i in 0..n_procs {
+            broadcast(i, None);
+        }
+        assert!(bufs.iter().all(StableBuffer::is_empty));
+    }
+
     #[test]
     fn partial_ord() {
         let e1 = MatrixClock::new(0, 2);
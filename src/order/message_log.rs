@@ -0,0 +1,445 @@
+use crate::order::history::History;
+use crate::order::vector_clock::VectorClock;
+use crate::order::wal::{FsyncPolicy, Wal};
+use crate::order::{HasEvents, OrdProcess};
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+
+/// Pessimistic receiver-based message logging for piecewise deterministic processes.
+///
+/// A process only changes state nondeterministically by receiving, so it logs each message to stable storage before
+/// delivering it. After a crash, re-executing the process replays the logged messages in their original order instead
+/// of receiving, reconstructing the exact state. Hence no other process can become an orphan, at the cost of an fsync
+/// per receive.
+///
+/// Sends while replaying were already sent before the crash, so they are not sent again. Sends after the last logged
+/// receive may have been sent too, so they are sent again, and a receiver discards any message it has delivered before,
+/// identified by its sender and the sender's own clock.
+///
+/// # Panics
+///
+/// Receiving panics if the message cannot be logged, as delivering it unlogged would lose it on recovery.
+///
+/// # Examples
+/// ```
+/// use rads::order::message_log::ReceiverLogged;
+/// use rads::order::vector_clock::VecProcess;
+/// use rads::order::{HasEvents, OrdProcess};
+///
+/// let path = std::env::temp_dir().join("rads-receiver-log-doctest");
+/// # std::fs::remove_file(&path).ok();
+/// let mut q = VecProcess::new(1, 2);
+/// let mut p = ReceiverLogged::open(VecProcess::new(0, 2), &path).unwrap();
+/// q.send(|e| p.recv(|| e));
+/// let before = p.last_event().unwrap().clone();
+/// drop(p); // crash
+///
+/// let mut p = ReceiverLogged::open(VecProcess::new(0, 2), &path).unwrap();
+/// assert!(p.is_replaying());
+/// p.recv(|| unreachable!()); // re-execute
+/// assert!(p.last_event() == Some(&before));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct ReceiverLogged<P> {
+    proc: P,
+    log: Wal<VectorClock>,
+    replay: VecDeque<VectorClock>,
+    // Messages delivered, by sender and the sender's own clock
+    delivered: HashSet<(usize, usize)>,
+}
+
+impl<P: OrdProcess<VectorClock>> ReceiverLogged<P> {
+    // Opens the log of a new process, which replays logged messages if it is recovering
+    pub fn open<Q: AsRef<Path>>(proc: P, path: Q) -> anyhow::Result<Self> {
+        let (log, logged) = Wal::open(path, FsyncPolicy::Always)?;
+        Ok(Self {
+            proc,
+            log,
+            replay: logged.into(),
+            delivered: HashSet::new(),
+        })
+    }
+    pub fn is_replaying(&self) -> bool {
+        !self.replay.is_empty()
+    }
+    pub fn into_inner(self) -> P {
+        self.proc
+    }
+}
+
+impl<P: OrdProcess<VectorClock>> HasEvents<VectorClock> for ReceiverLogged<P> {
+    fn pid(&self) -> usize {
+        self.proc.pid()
+    }
+    fn n_procs(&self) -> usize {
+        self.proc.n_procs()
+    }
+    fn history(&self) -> &History<VectorClock> {
+        self.proc.history()
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        self.proc.history_mut()
+    }
+}
+
+impl<P: OrdProcess<VectorClock>> OrdProcess<VectorClock> for ReceiverLogged<P> {
+    fn exec<F: FnOnce()>(&mut self, f: F) {
+        self.proc.exec(f)
+    }
+    fn send<F: FnOnce(VectorClock)>(&mut self, send_fn: F) {
+        let replaying = self.is_replaying();
+        self.proc.send(|e| {
+            if !replaying {
+                send_fn(e)
+            }
+        });
+    }
+    fn recv<F: FnOnce() -> VectorClock>(&mut self, recv_fn: F) {
+        let e = match self.replay.pop_front() {
+            Some(e) => e,
+            None => {
+                let e = recv_fn();
+                // Resent by a recovering sender
+                if self.delivered.contains(&(e.pid(), e[e.pid()])) {
+                    return;
+                }
+                // Cannot deliver without logging, as the message would be lost on recovery
+                self.log.append(&e).expect("Failed to log received message");
+                e
+            }
+        };
+        self.delivered.insert((e.pid(), e[e.pid()]));
+        self.proc.recv(|| e);
+    }
+}
+
+// Message in its sender's volatile log, with its receive sequence number (RSN) once acknowledged
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Logged {
+    pub to: usize,
+    pub msg: VectorClock,
+    pub rsn: Option<usize>,
+}
+
+// Receiver assigned rsn to the message sent by process from, identified by the sender's own clock `seq = msg[from]`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ack {
+    pub from: usize,
+    pub seq: usize,
+    pub rsn: usize,
+}
+
+/// Optimistic sender-based message logging for piecewise deterministic processes.
+///
+/// Each sender keeps the messages it sends in volatile memory, and each receiver acknowledges the order in which it
+/// received them without waiting for the sender to log it. After a crash, the receiver collects its messages from the
+/// senders' logs and replays them by receive sequence number (RSN), up to the first message whose RSN is unknown.
+///
+/// Lost receives cannot be replayed, so processes that have seen the lost states of the recovered process are orphans.
+pub struct SenderLogged<P> {
+    proc: P,
+    sent: Vec<Logged>,
+    n_recv: usize,
+    replay: VecDeque<VectorClock>,
+}
+
+impl<P: OrdProcess<VectorClock>> SenderLogged<P> {
+    pub fn new(proc: P) -> Self {
+        Self {
+            proc,
+            sent: Vec::new(),
+            n_recv: 0,
+            replay: VecDeque::new(),
+        }
+    }
+    // New process that replays the messages logged by senders for it, in their original order
+    pub fn recover(proc: P, senders: &[&SenderLogged<P>]) -> Self {
+        let i = proc.pid();
+        let mut logged: Vec<_> = (senders.iter())
+            .flat_map(|s| s.sent.iter())
+            .filter_map(|l| l.rsn.filter(|_| l.to == i).map(|rsn| (rsn, &l.msg)))
+            .collect();
+        logged.sort_by_key(|(rsn, _)| *rsn);
+        let replay = (logged.into_iter().enumerate())
+            .take_while(|(n, (rsn, _))| n == rsn)
+            .map(|(_, (_, msg))| msg.clone())
+            .collect();
+        Self {
+            replay,
+            ..Self::new(proc)
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        !self.replay.is_empty()
+    }
+    pub fn log(&self) -> &[Logged] {
+        &self.sent
+    }
+    pub fn into_inner(self) -> P {
+        self.proc
+    }
+
+    pub fn exec<F: FnOnce()>(&mut self, f: F) {
+        self.proc.exec(f)
+    }
+    pub fn send_to<F: FnOnce(VectorClock)>(&mut self, to: usize, send_fn: F) {
+        let mut msg = None;
+        let replaying = self.is_replaying();
+        self.proc.send(|e| {
+            msg = Some(e.clone());
+            if !replaying {
+                send_fn(e)
+            }
+        });
+        let msg = msg.unwrap();
+        self.sent.push(Logged { to, msg, rsn: None });
+    }
+    // Returns the acknowledgement for the sender to log
+    pub fn recv<F: FnOnce() -> VectorClock>(&mut self, recv_fn: F) -> Ack {
+        let e = self.replay.pop_front().unwrap_or_else(recv_fn);
+        let from = e.pid();
+        let ack = Ack {
+            from,
+            seq: e[from],
+            rsn: self.n_recv,
+        };
+        self.n_recv += 1;
+        self.proc.recv(|| e);
+        ack
+    }
+    pub fn ack(&mut self, ack: Ack) {
+        debug_assert_eq!(ack.from, self.pid(), "Acknowledged by the wrong sender");
+        if let Some(l) = self.sent.iter_mut().find(|l| l.msg[ack.from] == ack.seq) {
+            l.rsn = Some(ack.rsn);
+        }
+    }
+}
+
+impl<P: OrdProcess<VectorClock>> HasEvents<VectorClock> for SenderLogged<P> {
+    fn pid(&self) -> usize {
+        self.proc.pid()
+    }
+    fn n_procs(&self) -> usize {
+        self.proc.n_procs()
+    }
+    fn history(&self) -> &History<VectorClock> {
+        self.proc.history()
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        self.proc.history_mut()
+    }
+}
+
+// Processes whose latest clock has seen events of the recovered process beyond its recovered clock
+pub fn orphans(recovered: &VectorClock, latest: &[&VectorClock]) -> Vec<usize> {
+    let i = recovered.pid();
+    (latest.iter())
+        .filter(|e| e.pid() != i && e[i] > recovered[i])
+        .map(|e| e.pid())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::message_log::{orphans, ReceiverLogged, SenderLogged};
+    use crate::order::vector_clock::{VecProcess, VectorClock};
+    use crate::order::{HasEvents, LogicalClock, OrdProcess};
+    use rand::Rng;
+
+    #[derive(Clone, Copy)]
+    enum Op {
+        Exec,
+        Send(usize),
+        Recv,
+    }
+
+    // Deterministic program of each process, where receives may block
+    fn rand_programs(rng: &mut impl Rng, n_procs: usize, n_ops: usize) -> Vec<Vec<Op>> {
+        let mut programs = vec![Vec::new(); n_procs];
+        let mut n_sent = vec![0; n_procs];
+        for _ in 0..n_ops {
+            let i = rng.gen_range(0..n_procs);
+            let op = match rng.gen_range(0..3) {
+                0 => Op::Exec,
+                1 => {
+                    let j = (i + rng.gen_range(1..n_procs)) % n_procs;
+                    n_sent[j] += 1;
+                    Op::Send(j)
+                }
+                _ => Op::Recv,
+            };
+            programs[i].push(op);
+        }
+        // Only receive what is sent
+        for (program, n) in programs.iter_mut().zip(n_sent) {
+            let mut n_recv = 0;
+            program.retain(|op| match op {
+                Op::Recv if n_recv == n => false,
+                Op::Recv => {
+                    n_recv += 1;
+                    true
+                }
+                _ => true,
+            });
+        }
+        programs
+    }
+
+    #[test]
+    fn receiver_based_replays_exact_state() {
+        let mut rng = rand::thread_rng();
+        let n_procs = 3;
+        let paths: Vec<_> = (0..n_procs)
+            .map(|i| {
+                let name = format!("rads-receiver-log-{i}-{}", std::process::id());
+                std::env::temp_dir().join(name)
+            })
+            .collect();
+        let open = |i| ReceiverLogged::open(VecProcess::new(i, n_procs), &paths[i]).unwrap();
+        for _ in 0..20 {
+            for path in &paths {
+                std::fs::remove_file(path).ok();
+            }
+            let programs = rand_programs(&mut rng, n_procs, 60);
+            let mut ps: Vec<_> = (0..n_procs).map(open).collect();
+            let mut in_flight = vec![Vec::<VectorClock>::new(); n_procs];
+            let mut pcs = vec![0; n_procs];
+
+            // Randomly schedule runnable processes, then crash p0 anywhere
+            let crash_at = rng.gen_range(0..=programs[0].len());
+            loop {
+                let runnable: Vec<_> = (0..n_procs)
+                    .filter(|&i| i != 0 || pcs[0] < crash_at)
+                    .filter(|&i| match programs[i].get(pcs[i]) {
+                        None => false,
+                        Some(Op::Recv) => !in_flight[i].is_empty(),
+                        Some(_) => true,
+                    })
+                    .collect();
+                if runnable.is_empty() {
+                    break;
+                }
+                let i = runnable[rng.gen_range(0..runnable.len())];
+                match programs[i][pcs[i]] {
+                    Op::Exec => ps[i].exec(|| {}),
+                    Op::Send(j) => ps[i].send(|e| in_flight[j].push(e)),
+                    Op::Recv => {
+                        let k = rng.gen_range(0..in_flight[i].len());
+                        let e = in_flight[i].remove(k);
+                        ps[i].recv(|| e);
+                    }
+                }
+                pcs[i] += 1;
+            }
+            let before: Vec<_> = ps[0].events().cloned().collect();
+
+            // Re-execute p0's program up to the crash
+            ps[0] = open(0);
+            let mut resent = Vec::new();
+            for op in &programs[0][..pcs[0]] {
+                match op {
+                    Op::Exec => ps[0].exec(|| {}),
+                    Op::Send(j) => ps[0].send(|e| resent.push((*j, e))),
+                    Op::Recv => ps[0].recv(|| panic!("Received while replaying")),
+                }
+            }
+            assert!(!ps[0].is_replaying());
+            assert!(ps[0].events().cloned().collect::<Vec<_>>() == before);
+            let latest: Vec<_> = ps[1..].iter().flat_map(|p| p.last_event()).collect();
+            assert!(orphans(
+                ps[0].last_event().unwrap_or(&VectorClock::new(0, n_procs)),
+                &latest
+            )
+            .is_empty());
+
+            // Only sends after the last receive are resent, and receivers drop those they delivered
+            for (j, e) in resent {
+                assert!(before.contains(&e));
+                let delivered = !in_flight[j].contains(&e);
+                let n_events = ps[j].events().count();
+                ps[j].recv(|| e);
+                assert_eq!(ps[j].events().count(), n_events + usize::from(!delivered));
+            }
+        }
+        for path in &paths {
+            std::fs::remove_file(path).ok();
+        }
+    }
+
+    #[test]
+    fn sender_based_detects_orphans() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n_procs = 3;
+            let programs = rand_programs(&mut rng, n_procs, 60);
+            let mut ps: Vec<_> = (0..n_procs)
+                .map(|i| SenderLogged::new(VecProcess::new(i, n_procs)))
+                .collect();
+            let mut in_flight = vec![Vec::<VectorClock>::new(); n_procs];
+            let mut pcs = vec![0; n_procs];
+            let p_ack = rng.gen_range(0.0..=1.0);
+            loop {
+                let runnable: Vec<_> = (0..n_procs)
+                    .filter(|&i| match programs[i].get(pcs[i]) {
+                        None => false,
+                        Some(Op::Recv) => !in_flight[i].is_empty(),
+                        Some(_) => true,
+                    })
+                    .collect();
+                if runnable.is_empty() {
+                    break;
+                }
+                let i = runnable[rng.gen_range(0..runnable.len())];
+                match programs[i][pcs[i]] {
+                    Op::Exec => ps[i].exec(|| {}),
+                    Op::Send(j) => ps[i].send_to(j, |e| in_flight[j].push(e)),
+                    Op::Recv => {
+                        let k = rng.gen_range(0..in_flight[i].len());
+                        let e = in_flight[i].remove(k);
+                        let ack = ps[i].recv(|| e);
+                        // Optimistic, so the acknowledgement may be lost in the crash
+                        if rng.gen_bool(p_ack) {
+                            ps[ack.from].ack(ack);
+                        }
+                    }
+                }
+                pcs[i] += 1;
+            }
+            let before: Vec<_> = ps[0].events().cloned().collect();
+
+            // Crash p0, and re-execute its program until it blocks on a receive that cannot be replayed
+            let senders: Vec<_> = ps[1..].iter().collect();
+            let mut p0 = SenderLogged::recover(VecProcess::new(0, n_procs), &senders);
+            let mut resent = Vec::new();
+            for op in &programs[0][..pcs[0]] {
+                match op {
+                    Op::Exec => p0.exec(|| {}),
+                    Op::Send(j) => p0.send_to(*j, |e| resent.push(e)),
+                    Op::Recv if p0.is_replaying() => {
+                        p0.recv(|| unreachable!());
+                    }
+                    Op::Recv => break,
+                }
+            }
+            let after: Vec<_> = p0.events().cloned().collect();
+            assert!(after[..] == before[..after.len()], "Replay diverged");
+            assert!(resent.iter().all(|e| after.contains(e)));
+
+            let recovered = p0
+                .last_event()
+                .cloned()
+                .unwrap_or(VectorClock::new(0, n_procs));
+            let latest: Vec<_> = ps[1..].iter().flat_map(|p| p.last_event()).collect();
+            let expected: Vec<_> = (1..n_procs)
+                .filter(|&j| {
+                    before[after.len()..]
+                        .iter()
+                        .any(|s| ps[j].events().any(|t| s < t))
+                })
+                .collect();
+            assert_eq!(orphans(&recovered, &latest), expected);
+        }
+    }
+}
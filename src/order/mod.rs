@@ -1,6 +1,16 @@
+pub mod bcs;
 pub mod chandy_lamport;
+pub mod checkpoint;
+pub mod deadlock;
+pub mod history;
 pub mod matrix_clock;
+pub mod message_log;
+pub mod predicate;
+pub mod termination;
 pub mod vector_clock;
+pub mod wal;
+
+use history::History;
 
 // PartialOrd because not all clocks are comparable
 pub trait CausalOrd: PartialOrd {}
@@ -16,11 +26,21 @@ pub trait GCClock: LogicalClock {
 }
 
 pub trait HasEvents<Event: LogicalClock> {
-    fn last_event(&self) -> Option<&Event>;
-    fn push_event(&mut self, e: Event);
     fn pid(&self) -> usize;
     fn n_procs(&self) -> usize;
-    fn events(&self) -> &[Event];
+    fn history(&self) -> &History<Event>;
+    fn history_mut(&mut self) -> &mut History<Event>;
+
+    fn last_event(&self) -> Option<&Event> {
+        self.history().last()
+    }
+    fn push_event(&mut self, e: Event) {
+        self.history_mut().push(e);
+    }
+    // Retained events in program order
+    fn events(&self) -> history::Iter<'_, Event> {
+        self.history().iter()
+    }
 }
 
 pub trait OrdProcess<Event>: HasEvents<Event>
@@ -0,0 +1,222 @@
+use crate::order::vector_clock::VectorClock;
+use std::collections::VecDeque;
+
+/// Checker process for Weak Conjunctive Predicates (WCP) by Garg & Waldecker.
+///
+/// Each process has a local predicate and reports the clock of every state where it holds, in program order. The
+/// checker finds the first consistent cut where all local predicates hold without enumerating the lattice of cuts, in
+/// `O(n^2 m)` time for `n` processes reporting up to `m` states each.
+///
+/// A candidate that happens before another process' candidate can never be part of such a cut, since no later state of
+/// that process is concurrent with it either. Hence it is dropped until all queue heads are pairwise concurrent.
+///
+/// # Examples
+/// ```
+/// use rads::order::LogicalClock;
+/// use rads::order::predicate::WcpChecker;
+/// use rads::order::vector_clock::VectorClock;
+///
+/// let mut checker = WcpChecker::new(2);
+/// let p1 = VectorClock::new(0, 2).extend(); // p0 sends p1
+/// let q1 = VectorClock::new(1, 2).merge(&p1);
+/// let p2 = p1.extend();
+/// assert!(checker.push(p1).is_none());
+/// assert!(checker.push(q1.clone()).is_none()); // p1 -> q1
+/// assert!(checker.push(p2.clone()) == Some(&[p2, q1][..]));
+/// ```
+pub struct WcpChecker {
+    queues: Vec<VecDeque<VectorClock>>,
+    // Heads not yet compared against all other heads
+    changed: Vec<bool>,
+    cut: Option<Vec<VectorClock>>,
+}
+
+impl WcpChecker {
+    pub fn new(n_procs: usize) -> Self {
+        Self {
+            queues: (0..n_procs).map(|_| VecDeque::new()).collect(),
+            changed: vec![false; n_procs],
+            cut: None,
+        }
+    }
+
+    // Receives the next state of process `e.pid()` where its local predicate holds.
+    // Returns the first consistent cut satisfying the predicate, once found.
+    pub fn push(&mut self, e: VectorClock) -> Option<&[VectorClock]> {
+        if self.cut.is_none() {
+            let i = e.pid();
+            debug_assert!(
+                self.queues[i].back().is_none_or(|s| s < &e),
+                "Expect states of process {i} in program order"
+            );
+            self.changed[i] |= self.queues[i].is_empty();
+            self.queues[i].push_back(e);
+            self.eliminate();
+        }
+        self.cut.as_deref()
+    }
+
+    pub fn cut(&self) -> Option<&[VectorClock]> {
+        self.cut.as_deref()
+    }
+
+    fn eliminate(&mut self) {
+        let n = self.queues.len();
+        while self.queues.iter().all(|q| !q.is_empty()) {
+            let changed: Vec<_> = (0..n).filter(|&i| self.changed[i]).collect();
+            if changed.is_empty() {
+                self.cut = Some(self.queues.iter().map(|q| q[0].clone()).collect());
+                return;
+            }
+            let mut stale = vec![false; n];
+            for &i in &changed {
+                self.changed[i] = false;
+                for j in (0..n).filter(|&j| j != i) {
+                    let (s, t) = (&self.queues[i][0], &self.queues[j][0]);
+                    if happened_before(s, t) {
+                        stale[i] = true;
+                    } else if happened_before(t, s) {
+                        stale[j] = true;
+                    }
+                }
+            }
+            for j in (0..n).filter(|&j| stale[j]) {
+                self.queues[j].pop_front();
+                self.changed[j] = true;
+            }
+        }
+    }
+}
+
+// Finds the first consistent cut where all local predicates hold, given each process' states where its predicate holds
+pub fn detect(histories: &[Vec<VectorClock>]) -> Option<Vec<VectorClock>> {
+    let mut checker = WcpChecker::new(histories.len());
+    histories
+        .iter()
+        .flatten()
+        .find_map(|e| checker.push(e.clone()).map(<[_]>::to_vec))
+}
+
+// O(1) comparison for states s, t of different processes, since t must have seen s for s -> t
+fn happened_before(s: &VectorClock, t: &VectorClock) -> bool {
+    s[s.pid()] <= t[s.pid()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::predicate::{detect, WcpChecker};
+    use crate::order::vector_clock::{VecProcess, VectorClock};
+    use crate::order::{HasEvents, OrdProcess};
+    use rand::Rng;
+
+    #[test]
+    fn streams_from_processes() {
+        let (tx0, rx) = std::sync::mpsc::channel();
+        let tx1 = tx0.clone();
+        let (tx_m, rx_m) = std::sync::mpsc::channel();
+
+        let th0 = std::thread::spawn(move || {
+            let mut p = VecProcess::new(0, 2);
+            p.exec(|| {});
+            tx0.send(p.last_event().unwrap().clone()).unwrap(); // holds before send
+            p.send(|e| tx_m.send(e).unwrap());
+            p.exec(|| {});
+            tx0.send(p.last_event().unwrap().clone()).unwrap(); // holds after send
+            p
+        });
+        let th1 = std::thread::spawn(move || {
+            let mut p = VecProcess::new(1, 2);
+            p.recv(|| rx_m.recv().unwrap());
+            p.exec(|| {});
+            tx1.send(p.last_event().unwrap().clone()).unwrap(); // holds after recv
+            p
+        });
+
+        let mut checker = WcpChecker::new(2);
+        let cut = rx.iter().find_map(|e| checker.push(e).map(<[_]>::to_vec));
+        let p0 = th0.join().unwrap();
+        let p1 = th1.join().unwrap();
+        let (p0, p1) = (p0.history(), p1.history());
+        assert!(cut == Some(vec![p0.get(2).unwrap().clone(), p1.get(1).unwrap().clone()]));
+    }
+
+    #[test]
+    fn undetected_if_always_ordered() {
+        // p0 and p1 pass a token back and forth, so no two states are concurrent
+        let mut p0 = VecProcess::new(0, 2);
+        let mut p1 = VecProcess::new(1, 2);
+        for _ in 0..10 {
+            let mut e = None;
+            p0.send(|ev| e = Some(ev));
+            p1.recv(|| e.take().unwrap());
+            p1.send(|ev| e = Some(ev));
+            p0.recv(|| e.take().unwrap());
+        }
+        let history = |p: &VecProcess| p.events().cloned().collect();
+        assert!(detect(&[history(&p0), history(&p1)]).is_none());
+
+        // Until p1 works independently of p0's last receive
+        p1.exec(|| {});
+        let cut = detect(&[history(&p0), history(&p1)]);
+        let (p0, p1) = (p0.history(), p1.history());
+        assert!(cut == Some(vec![p0.last().unwrap().clone(), p1.last().unwrap().clone()]));
+    }
+
+    #[test]
+    fn least_cut_of_lattice() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n_procs = rng.gen_range(2..=4);
+            let histories = rand_histories(&mut rng, n_procs, 5);
+            let cuts = lattice(&histories);
+            match detect(&histories) {
+                None => assert!(cuts.is_empty()),
+                Some(cut) => {
+                    assert!(cuts.iter().any(|c| c == &cut));
+                    assert!(cuts.iter().all(|c| cut.iter().zip(c).all(|(s, t)| s <= t)));
+                }
+            }
+        }
+    }
+
+    // Random sends and receives, where each state satisfies its local predicate by chance
+    fn rand_histories(
+        rng: &mut impl Rng,
+        n_procs: usize,
+        n_events: usize,
+    ) -> Vec<Vec<VectorClock>> {
+        let mut ps: Vec<_> = (0..n_procs).map(|i| VecProcess::new(i, n_procs)).collect();
+        let mut in_flight: Vec<Vec<VectorClock>> = vec![Vec::new(); n_procs];
+        let mut holds = vec![Vec::new(); n_procs];
+        for _ in 0..n_procs * n_events {
+            let i = rng.gen_range(0..n_procs);
+            if !in_flight[i].is_empty() && rng.gen_bool(0.5) {
+                let e = in_flight[i].remove(0);
+                ps[i].recv(|| e);
+            } else if rng.gen_bool(0.5) {
+                let j = (i + rng.gen_range(1..n_procs)) % n_procs;
+                ps[i].send(|e| in_flight[j].push(e));
+            } else {
+                ps[i].exec(|| {});
+            }
+            if rng.gen_bool(0.3) {
+                holds[i].push(ps[i].last_event().unwrap().clone());
+            }
+        }
+        holds
+    }
+
+    // Enumerates all consistent cuts where all local predicates hold
+    fn lattice(histories: &[Vec<VectorClock>]) -> Vec<Vec<VectorClock>> {
+        histories.iter().fold(vec![Vec::new()], |cuts, states| {
+            cuts.iter()
+                .flat_map(|cut| {
+                    states
+                        .iter()
+                        .filter(|t| cut.iter().all(|s: &VectorClock| s.partial_cmp(t).is_none()))
+                        .map(|t| [cut.as_slice(), std::slice::from_ref(t)].concat())
+                })
+                .collect()
+        })
+    }
+}
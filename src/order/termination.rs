@@ -0,0 +1,430 @@
+use crate::order::history::History;
+use crate::order::vector_clock::VectorClock;
+use crate::order::{HasEvents, OrdProcess};
+
+// Acknowledges one message that process to sent to process from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signal {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Termination detection of a diffusing computation by Dijkstra & Scholten.
+///
+/// Only the root is initially active, and others become active by receiving. A process that receives while idle joins
+/// the spanning tree as a child of the sender, while any other message is signalled back immediately. Each process
+/// counts its deficit of unsignalled messages, and leaves the tree by signalling its parent once it is passive with no
+/// deficit. Hence the computation has terminated when the root is passive with no deficit.
+///
+/// # Examples
+/// ```
+/// use rads::order::termination::DsProcess;
+///
+/// let mut ps = [DsProcess::root(0, 2), DsProcess::new(1, 2)];
+/// let mut e = None;
+/// ps[0].send(|ev| e = Some(ev));
+/// assert_eq!(ps[1].recv(|| e.unwrap()), None); // joins the tree
+/// assert_eq!(ps[0].passivate(), None);
+/// assert!(!ps[0].terminated());
+/// let signal = ps[1].passivate().unwrap(); // leaves the tree
+/// ps[0].signal(signal);
+/// assert!(ps[0].terminated());
+/// ```
+pub struct DsProcess {
+    i: usize,
+    n_procs: usize,
+    events: History<VectorClock>,
+    is_root: bool,
+    active: bool,
+    parent: Option<usize>,
+    deficit: usize,
+}
+
+impl DsProcess {
+    // Idle process, until it receives a message
+    pub fn new(i: usize, n_procs: usize) -> Self {
+        Self {
+            i,
+            n_procs,
+            events: History::new(),
+            is_root: false,
+            active: false,
+            parent: None,
+            deficit: 0,
+        }
+    }
+    // Initiator of the diffusing computation
+    pub fn root(i: usize, n_procs: usize) -> Self {
+        Self {
+            is_root: true,
+            active: true,
+            ..Self::new(i, n_procs)
+        }
+    }
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    // Parent in the spanning tree, if the process is in it
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+    pub fn deficit(&self) -> usize {
+        self.deficit
+    }
+    pub fn terminated(&self) -> bool {
+        self.is_root && !self.active && self.deficit == 0
+    }
+
+    pub fn send<F: FnOnce(VectorClock)>(&mut self, send_fn: F) {
+        assert!(self.active, "Passive process {} cannot send", self.i);
+        self.deficit += 1;
+        OrdProcess::send(self, send_fn);
+    }
+    // Returns the signal to send back, unless the sender becomes the parent
+    pub fn recv<F: FnOnce() -> VectorClock>(&mut self, recv_fn: F) -> Option<Signal> {
+        let e = recv_fn();
+        let signal = Signal {
+            from: self.i,
+            to: e.pid(),
+        };
+        OrdProcess::recv(self, || e);
+        self.active = true;
+        if self.is_root || self.parent.is_some() {
+            return Some(signal);
+        }
+        self.parent = Some(signal.to);
+        None
+    }
+    pub fn signal(&mut self, signal: Signal) -> Option<Signal> {
+        debug_assert_eq!(signal.to, self.i, "Signal for process {}", signal.to);
+        self.deficit = (self.deficit.checked_sub(1)).expect("Signalled more than sent");
+        self.detach()
+    }
+    pub fn passivate(&mut self) -> Option<Signal> {
+        self.active = false;
+        self.detach()
+    }
+    // Leaves the tree by signalling the parent, once no message of the subtree is outstanding
+    fn detach(&mut self) -> Option<Signal> {
+        if self.active || self.deficit > 0 {
+            return None;
+        }
+        self.parent.take().map(|to| Signal { from: self.i, to })
+    }
+}
+
+impl HasEvents<VectorClock> for DsProcess {
+    fn pid(&self) -> usize {
+        self.i
+    }
+    fn n_procs(&self) -> usize {
+        self.n_procs
+    }
+    fn history(&self) -> &History<VectorClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        &mut self.events
+    }
+}
+
+impl OrdProcess<VectorClock> for DsProcess {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    White,
+    // Received a message since the token last passed, which the token may have missed
+    Black,
+}
+
+// Sum of message counters and whether any process was black, since process 0 started the wave
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub count: isize,
+    pub color: Color,
+}
+
+/// Termination detection by Safra, with a token passed on the ring `0 -> n - 1 -> ... -> 1 -> 0`.
+///
+/// Each process counts messages sent minus received, and turns black on receiving. A passive process passes the token
+/// on, adding its counter and blackening the token if it is black, then turns white. When the token returns to process
+/// 0, the computation has terminated if the token and process 0 are white and the counters sum to 0, i.e. no message is
+/// in flight. Otherwise process 0 starts a new wave.
+///
+/// # Examples
+/// ```
+/// use rads::order::termination::SafraProcess;
+///
+/// let mut ps = [SafraProcess::new(0, 2), SafraProcess::new(1, 2)];
+/// let mut e = None;
+/// ps[0].send(|ev| e = Some(ev));
+/// ps[0].passivate();
+/// let t = ps[0].pass_token().unwrap(); // starts a wave
+/// ps[1].recv_token(t);
+/// assert_eq!(ps[1].pass_token(), None); // still active
+///
+/// ps[1].recv(|| e.unwrap());
+/// ps[1].passivate();
+/// let t = ps[1].pass_token().unwrap();
+/// ps[0].recv_token(t);
+/// let t = ps[0].pass_token().unwrap(); // 1 turned black, so starts another wave
+/// ps[1].recv_token(t);
+/// let t = ps[1].pass_token().unwrap();
+/// ps[0].recv_token(t);
+/// assert_eq!(ps[0].pass_token(), None);
+/// assert!(ps[0].terminated());
+/// ```
+pub struct SafraProcess {
+    i: usize,
+    n_procs: usize,
+    events: History<VectorClock>,
+    active: bool,
+    counter: isize,
+    color: Color,
+    token: Option<Token>,
+    terminated: bool,
+}
+
+impl SafraProcess {
+    // Every process is initially active, and process 0 initially holds a black token so the 1st wave never terminates
+    pub fn new(i: usize, n_procs: usize) -> Self {
+        Self {
+            i,
+            n_procs,
+            events: History::new(),
+            active: true,
+            counter: 0,
+            color: Color::White,
+            token: (i == 0).then_some(Token {
+                count: 0,
+                color: Color::Black,
+            }),
+            terminated: false,
+        }
+    }
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+    // Only process 0 detects termination
+    pub fn terminated(&self) -> bool {
+        self.terminated
+    }
+    // Next process on the ring to pass the token to
+    pub fn next(&self) -> usize {
+        (self.i + self.n_procs - 1) % self.n_procs
+    }
+
+    pub fn send<F: FnOnce(VectorClock)>(&mut self, send_fn: F) {
+        assert!(self.active, "Passive process {} cannot send", self.i);
+        self.counter += 1;
+        OrdProcess::send(self, send_fn);
+    }
+    pub fn recv<F: FnOnce() -> VectorClock>(&mut self, recv_fn: F) {
+        self.counter -= 1;
+        self.color = Color::Black;
+        self.active = true;
+        OrdProcess::recv(self, recv_fn);
+    }
+    pub fn passivate(&mut self) {
+        self.active = false;
+    }
+    pub fn recv_token(&mut self, t: Token) {
+        debug_assert!(self.token.is_none(), "Only one token on the ring");
+        self.token = Some(t);
+    }
+    // Returns the token to pass to the next process, once passive
+    pub fn pass_token(&mut self) -> Option<Token> {
+        if self.active || self.terminated {
+            return None;
+        }
+        let t = self.token.take()?;
+        let t = if self.i != 0 {
+            Token {
+                count: t.count + self.counter,
+                color: if self.color == Color::Black {
+                    Color::Black
+                } else {
+                    t.color
+                },
+            }
+        } else if t.color == Color::White
+            && self.color == Color::White
+            && t.count + self.counter == 0
+        {
+            self.terminated = true;
+            self.token = Some(t);
+            return None;
+        } else {
+            Token {
+                count: 0,
+                color: Color::White,
+            }
+        };
+        self.color = Color::White;
+        Some(t)
+    }
+}
+
+impl HasEvents<VectorClock> for SafraProcess {
+    fn pid(&self) -> usize {
+        self.i
+    }
+    fn n_procs(&self) -> usize {
+        self.n_procs
+    }
+    fn history(&self) -> &History<VectorClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        &mut self.events
+    }
+}
+
+impl OrdProcess<VectorClock> for SafraProcess {}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::termination::{DsProcess, SafraProcess, Signal, Token};
+    use crate::order::vector_clock::VectorClock;
+    use rand::Rng;
+
+    #[test]
+    fn dijkstra_scholten_detects_termination() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n_procs = rng.gen_range(1..=6);
+            let mut ps: Vec<_> = (0..n_procs)
+                .map(|i| {
+                    if i == 0 {
+                        DsProcess::root(i, n_procs)
+                    } else {
+                        DsProcess::new(i, n_procs)
+                    }
+                })
+                .collect();
+            let mut in_flight: Vec<(usize, VectorClock)> = Vec::new();
+            let mut signals: Vec<Signal> = Vec::new();
+            let mut budget = rng.gen_range(0..100);
+
+            loop {
+                let terminated = ps.iter().all(|p| !p.is_active()) && in_flight.is_empty();
+                assert!(
+                    !ps[0].terminated() || terminated,
+                    "Detected before termination"
+                );
+                if terminated && signals.is_empty() {
+                    break;
+                }
+                // Tree edges point to processes in the tree
+                for p in &ps {
+                    assert!(p
+                        .parent()
+                        .is_none_or(|j| ps[j].parent().is_some() || j == 0));
+                }
+                let i = rng.gen_range(0..n_procs);
+                match rng.gen_range(0..4) {
+                    0 if ps[i].is_active() && budget > 0 => {
+                        budget -= 1;
+                        let j = rng.gen_range(0..n_procs);
+                        ps[i].send(|e| in_flight.push((j, e)));
+                    }
+                    1 if !in_flight.is_empty() => {
+                        let (j, e) = in_flight.swap_remove(rng.gen_range(0..in_flight.len()));
+                        signals.extend(ps[j].recv(|| e));
+                    }
+                    2 if !signals.is_empty() => {
+                        let s = signals.swap_remove(rng.gen_range(0..signals.len()));
+                        signals.extend(ps[s.to].signal(s));
+                    }
+                    3 if ps[i].is_active() => signals.extend(ps[i].passivate()),
+                    _ => {}
+                }
+            }
+            assert!(ps[0].terminated(), "Undetected termination");
+            assert!(ps.iter().all(|p| p.deficit() == 0 && p.parent().is_none()));
+        }
+    }
+
+    #[test]
+    fn safra_detects_termination() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let n_procs = rng.gen_range(1..=6);
+            let mut ps: Vec<_> = (0..n_procs)
+                .map(|i| SafraProcess::new(i, n_procs))
+                .collect();
+            let mut in_flight: Vec<(usize, VectorClock)> = Vec::new();
+            let mut token: Option<(usize, Token)> = None;
+            let mut budget = rng.gen_range(0..100);
+
+            while !ps[0].terminated() {
+                let terminated = ps.iter().all(|p| !p.is_active()) && in_flight.is_empty();
+                let i = rng.gen_range(0..n_procs);
+                match rng.gen_range(0..4) {
+                    0 if ps[i].is_active() && budget > 0 => {
+                        budget -= 1;
+                        let j = rng.gen_range(0..n_procs);
+                        ps[i].send(|e| in_flight.push((j, e)));
+                    }
+                    1 if !in_flight.is_empty() => {
+                        let (j, e) = in_flight.swap_remove(rng.gen_range(0..in_flight.len()));
+                        ps[j].recv(|| e);
+                    }
+                    2 => {
+                        if let Some((j, t)) = token.take() {
+                            ps[j].recv_token(t);
+                        }
+                        let p = ps.iter_mut().find(|p| p.has_token()).unwrap();
+                        token = p.pass_token().map(|t| (p.next(), t));
+                    }
+                    3 => ps[i].passivate(),
+                    _ => {}
+                }
+                assert!(
+                    !ps[0].terminated() || terminated,
+                    "Detected before termination"
+                );
+            }
+            assert!(ps.iter().all(|p| !p.is_active()) && in_flight.is_empty());
+        }
+    }
+
+    #[test]
+    fn safra_detects_message_behind_token() {
+        let mut ps: Vec<_> = (0..3).map(|i| SafraProcess::new(i, 3)).collect();
+        // Passes the token of a new wave around the ring, returning the token of the next wave if any
+        let around = |ps: &mut Vec<SafraProcess>, mut t: Token| {
+            for i in [2, 1, 0] {
+                ps[i].recv_token(t);
+                if i != 0 {
+                    t = ps[i].pass_token().unwrap();
+                }
+            }
+            ps[0].pass_token()
+        };
+        ps[0].passivate();
+        ps[2].passivate();
+        let t = ps[0].pass_token().unwrap();
+        ps[2].recv_token(t);
+        let t = ps[2].pass_token().unwrap();
+        ps[1].recv_token(t);
+
+        // 1 sends to 2, which the token has passed
+        let mut e = None;
+        ps[1].send(|ev| e = Some(ev));
+        ps[1].passivate();
+        let t = ps[1].pass_token().unwrap();
+        assert_eq!(t.count, 1);
+        ps[0].recv_token(t);
+        let t = ps[0].pass_token().unwrap();
+
+        ps[2].recv(|| e.unwrap());
+        ps[2].passivate();
+        let t = around(&mut ps, t).expect("Counters sum to 0, but 2 is black");
+        assert_eq!(around(&mut ps, t), None);
+        assert!(ps[0].terminated());
+    }
+}
@@ -1,4 +1,7 @@
 use super::LogicalClock;
+use crate::order::checkpoint::{Checkpoint, Checkpointing};
+use crate::order::history::History;
+use crate::order::wal::Durable;
 use crate::order::{pairwise_max, CausalOrd, HasEvents, OrdProcess};
 
 /// Vector Clock is used to compare if one event happens before (<) / after (>) another or if they are concurrent (None).
@@ -23,11 +26,30 @@ use crate::order::{pairwise_max, CausalOrd, HasEvents, OrdProcess};
 /// assert!(f1 < f2);
 /// ```
 #[derive(Clone)]
+#[cfg_attr(test, derive(Debug))]
 pub struct VectorClock {
     i: usize,
     clk: Vec<usize>,
 }
 
+impl VectorClock {
+    // Process that owns this clock
+    pub fn pid(&self) -> usize {
+        self.i
+    }
+    pub fn n_procs(&self) -> usize {
+        self.clk.len()
+    }
+}
+
+// Number of events of process j that this clock has seen
+impl std::ops::Index<usize> for VectorClock {
+    type Output = usize;
+    fn index(&self, j: usize) -> &usize {
+        &self.clk[j]
+    }
+}
+
 impl LogicalClock for VectorClock {
     fn new(i: usize, n_procs: usize) -> Self {
         assert!(
@@ -64,6 +86,32 @@ impl LogicalClock for VectorClock {
     }
 }
 
+// Little endian u64s of i, n_procs then each clock
+impl Durable for VectorClock {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        [self.i, self.clk.len()]
+            .iter()
+            .chain(&self.clk)
+            .for_each(|v| buf.extend((*v as u64).to_le_bytes()));
+    }
+    fn decode(buf: &[u8]) -> anyhow::Result<Self> {
+        let mut vs = buf
+            .chunks(8)
+            .map(|v| Ok(u64::from_le_bytes(v.try_into()?) as usize));
+        let mut next = || vs.next().unwrap_or_else(|| anyhow::bail!("Missing clock"));
+        let (i, n_procs) = (next()?, next()?);
+        anyhow::ensure!(i < n_procs, "Expect process {i} < n_procs={n_procs}");
+        let clk = (0..n_procs)
+            .map(|_| next())
+            .collect::<anyhow::Result<_>>()?;
+        anyhow::ensure!(vs.next().is_none(), "Expect {n_procs} clocks only");
+        Ok(Self { i, clk })
+    }
+    fn owner(&self) -> (usize, usize) {
+        (self.i, self.clk.len())
+    }
+}
+
 impl PartialOrd for VectorClock {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         if self.clk.len() != other.clk.len() {
@@ -93,39 +141,54 @@ impl PartialEq for VectorClock {
 pub struct VecProcess {
     i: usize,
     n_procs: usize,
-    events: Vec<VectorClock>,
+    events: History<VectorClock>,
+    checkpoints: Vec<Checkpoint>,
 }
 
 impl VecProcess {
     pub fn new(i: usize, n_procs: usize) -> Self {
+        Self::with_history(i, n_procs, History::new())
+    }
+    // Keeps at most the latest cap events
+    pub fn with_cap(i: usize, n_procs: usize, cap: usize) -> Self {
+        Self::with_history(i, n_procs, History::with_cap(cap))
+    }
+    fn with_history(i: usize, n_procs: usize, events: History<VectorClock>) -> Self {
         Self {
             i,
             n_procs,
-            events: Vec::new(),
+            events,
+            checkpoints: vec![Checkpoint::initial(i, n_procs)],
         }
     }
 }
 
 impl HasEvents<VectorClock> for VecProcess {
-    fn last_event(&self) -> Option<&VectorClock> {
-        self.events.last()
-    }
-    fn push_event(&mut self, e: VectorClock) {
-        self.events.push(e)
-    }
     fn pid(&self) -> usize {
         self.i
     }
     fn n_procs(&self) -> usize {
         self.n_procs
     }
-    fn events(&self) -> &[VectorClock] {
-        self.events.as_slice()
+    fn history(&self) -> &History<VectorClock> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<VectorClock> {
+        &mut self.events
     }
 }
 
 impl OrdProcess<VectorClock> for VecProcess {}
 
+impl Checkpointing for VecProcess {
+    fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+    fn push_checkpoint(&mut self, c: Checkpoint) {
+        self.checkpoints.push(c)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::order::vector_clock::VecProcess;
@@ -190,9 +253,9 @@ mod tests {
         let p1 = th1.join().unwrap();
         let p2 = th2.join().unwrap();
         let p3 = th3.join().unwrap();
-        let p1 = p1.events();
-        let p2 = p2.events();
-        let p3 = p3.events();
+        let p1: Vec<_> = p1.events().cloned().collect();
+        let p2: Vec<_> = p2.events().cloned().collect();
+        let p3: Vec<_> = p3.events().cloned().collect();
 
         // Number of events
         assert_eq!(p1.len(), 3);
@@ -212,6 +275,21 @@ mod tests {
         assert!(p1[..2].iter().all(|s| s < &p3[3]));
     }
 
+    #[test]
+    fn capped_history_keeps_latest_events() {
+        let (mut p, mut q) = (VecProcess::with_cap(0, 2, 3), VecProcess::new(1, 2));
+        (0..5).for_each(|_| p.exec(|| {}));
+        let mut e = None;
+        p.send(|ev| e = Some(ev));
+        q.recv(|| e.take().unwrap());
+
+        // Clocks continue from the last event after the oldest are evicted
+        assert_eq!(p.events().count(), 3);
+        assert_eq!(p.history().first_index(), 3);
+        assert_eq!(p.last_event().unwrap()[0], 7);
+        assert!(p.events().all(|s| s < q.last_event().unwrap()));
+    }
+
     fn rand_timeout() {
         let mut rng = rand::thread_rng();
         let t = rng.gen_range(0..=200);
@@ -0,0 +1,350 @@
+use crate::order::history::History;
+use crate::order::{HasEvents, LogicalClock, OrdProcess};
+use anyhow::{ensure, Context};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// Events that can be written to and read from durable storage.
+pub trait Durable: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+    fn decode(buf: &[u8]) -> anyhow::Result<Self>;
+    // Process i of n_procs whose event this is, to check that a log is recovered by its own process
+    fn owner(&self) -> (usize, usize);
+}
+
+// When appended records are flushed to disk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    // Before append returns, so no acknowledged record is lost
+    Always,
+    // After every n records, so a crash loses at most the last n - 1 records
+    EveryN(usize),
+    // Whenever the OS decides, or on `sync()`
+    Never,
+}
+
+/// Append-only log of events, where each record is checksummed so that a torn write from a crash is detected.
+///
+/// A record is `[len: u32][crc32: u32][payload: len bytes]` in little endian, where the checksum covers both `len` and
+/// the payload. On open, a last record that is incomplete or fails its checksum is truncated, since a crash can only
+/// tear the tail of the log. A record that fails its checksum before the end, or that intact records follow, is
+/// corruption rather than a crash, so opening fails instead of discarding the records after it.
+///
+/// # Examples
+/// ```
+/// use rads::order::LogicalClock;
+/// use rads::order::vector_clock::VectorClock;
+/// use rads::order::wal::{FsyncPolicy, Wal};
+///
+/// let path = std::env::temp_dir().join("rads-wal-doctest");
+/// # std::fs::remove_file(&path).ok();
+/// let (mut wal, recovered) = Wal::open(&path, FsyncPolicy::Always).unwrap();
+/// assert!(recovered.is_empty());
+/// let e = VectorClock::new(0, 2).extend();
+/// wal.append(&e).unwrap();
+/// drop(wal); // crash
+/// let (_, recovered) = Wal::<VectorClock>::open(&path, FsyncPolicy::Always).unwrap();
+/// assert!(recovered == vec![e]);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct Wal<E> {
+    file: File,
+    policy: FsyncPolicy,
+    unsynced: usize,
+    _events: PhantomData<E>,
+}
+
+impl<E: Durable> Wal<E> {
+    const HEADER: usize = 8;
+
+    // Opens or creates the log, returning its valid records
+    pub fn open<P: AsRef<Path>>(path: P, policy: FsyncPolicy) -> anyhow::Result<(Self, Vec<E>)> {
+        if let FsyncPolicy::EveryN(n) = policy {
+            ensure!(n > 0, "Expect to fsync every n > 0 records");
+        }
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log {}", path.display()))?;
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+
+        let mut events = Vec::new();
+        let mut valid = 0;
+        loop {
+            let Some((payload, intact)) = Self::record(&buf[valid..]) else {
+                // A torn write only leaves a prefix of the last record, so no intact record follows
+                let intact = |k: usize| Self::record(&buf[k..]).is_some_and(|(_, intact)| intact);
+                ensure!(
+                    !(valid + 1..buf.len()).any(intact),
+                    "Corrupt record at byte {valid} of {}, before intact records",
+                    path.display()
+                );
+                break;
+            };
+            let end = valid + Self::HEADER + payload.len();
+            if !intact {
+                ensure!(
+                    end == buf.len(),
+                    "Corrupt record at byte {valid} of {}, before the end",
+                    path.display()
+                );
+                break;
+            }
+            let e = E::decode(payload)
+                .with_context(|| format!("Corrupt record at byte {valid} of {}", path.display()))?;
+            events.push(e);
+            valid = end;
+        }
+        if valid < buf.len() {
+            // Torn tail from a crash
+            file.set_len(valid as u64)?;
+            file.sync_all()?;
+        }
+        let wal = Self {
+            file,
+            policy,
+            unsynced: 0,
+            _events: PhantomData,
+        };
+        Ok((wal, events))
+    }
+
+    // Payload of the first record and whether its checksum matches, if it is complete
+    fn record(buf: &[u8]) -> Option<(&[u8], bool)> {
+        let header = buf.get(..Self::HEADER)?;
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let payload = buf.get(Self::HEADER..Self::HEADER.checked_add(len)?)?;
+        Some((payload, crc32(header[..4].iter().chain(payload)) == crc))
+    }
+
+    pub fn append(&mut self, e: &E) -> anyhow::Result<()> {
+        let mut payload = Vec::new();
+        e.encode(&mut payload);
+        let len = u32::try_from(payload.len()).context("Record too large")?;
+        let mut record = Vec::with_capacity(Self::HEADER + payload.len());
+        record.extend(len.to_le_bytes());
+        record.extend(crc32(record.iter().chain(&payload)).to_le_bytes());
+        record.extend(payload);
+        // Single write, so a crash tears at most this record
+        self.file.write_all(&record)?;
+        self.unsynced += 1;
+        match self.policy {
+            FsyncPolicy::Always => self.sync(),
+            FsyncPolicy::EveryN(n) if self.unsynced >= n => self.sync(),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn sync(&mut self) -> anyhow::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+}
+
+// CRC-32 (IEEE 802.3)
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    !bytes.into_iter().fold(!0, |crc, &b| {
+        (0..8).fold(crc ^ u32::from(b), |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+/// Process that logs each event before it happens, so that it recovers its last clock and history after a crash.
+///
+/// Since an event is logged before its clock is sent, other processes never see a clock newer than the recovered one
+/// with `FsyncPolicy::Always`. Otherwise a crash may lose events that were sent, breaking monotonicity of its clock.
+///
+/// # Panics
+///
+/// Executing, sending and receiving panic if the event cannot be logged, as continuing unlogged would lose it on
+/// recovery.
+pub struct WalProcess<Event> {
+    i: usize,
+    n_procs: usize,
+    events: History<Event>,
+    wal: Wal<Event>,
+}
+
+impl<Event: LogicalClock + Durable> WalProcess<Event> {
+    // Recovers the process from its log, or starts a new process if there is none
+    pub fn open<P: AsRef<Path>>(
+        i: usize,
+        n_procs: usize,
+        path: P,
+        policy: FsyncPolicy,
+    ) -> anyhow::Result<Self> {
+        let (wal, recovered) = Wal::<Event>::open(path, policy)?;
+        for e in &recovered {
+            let (j, n) = e.owner();
+            ensure!(
+                (j, n) == (i, n_procs),
+                "Expect events of process {i} of {n_procs}, but recovered process {j} of {n}"
+            );
+        }
+        let mut events = History::new();
+        recovered.into_iter().for_each(|e| {
+            events.push(e);
+        });
+        Ok(Self {
+            i,
+            n_procs,
+            events,
+            wal,
+        })
+    }
+    pub fn sync(&mut self) -> anyhow::Result<()> {
+        self.wal.sync()
+    }
+}
+
+impl<Event: LogicalClock + Durable> HasEvents<Event> for WalProcess<Event> {
+    fn pid(&self) -> usize {
+        self.i
+    }
+    fn n_procs(&self) -> usize {
+        self.n_procs
+    }
+    fn history(&self) -> &History<Event> {
+        &self.events
+    }
+    fn history_mut(&mut self) -> &mut History<Event> {
+        &mut self.events
+    }
+    fn push_event(&mut self, e: Event) {
+        // Cannot continue without logging, as the event would be lost on recovery
+        self.wal
+            .append(&e)
+            .expect("Failed to append event to write-ahead log");
+        self.events.push(e);
+    }
+}
+
+impl<Event: LogicalClock + Durable> OrdProcess<Event> for WalProcess<Event> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::order::vector_clock::{VecProcess, VectorClock};
+    use crate::order::wal::{crc32, FsyncPolicy, Wal, WalProcess};
+    use crate::order::{HasEvents, LogicalClock, OrdProcess};
+    use std::path::PathBuf;
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn recovers_prefix_of_truncated_log() {
+        let path = temp_path("truncated");
+        let events: Vec<_> = (0..5)
+            .scan(VectorClock::new(0, 3), |e, _| {
+                *e = e.extend();
+                Some(e.clone())
+            })
+            .collect();
+        let (mut wal, _) = Wal::open(&path, FsyncPolicy::EveryN(2)).unwrap();
+        let mut ends = Vec::new();
+        for e in &events {
+            wal.append(e).unwrap();
+            ends.push(std::fs::metadata(&path).unwrap().len());
+        }
+        drop(wal);
+        let full = std::fs::read(&path).unwrap();
+
+        // Crash while writing any byte
+        for len in 0..=full.len() as u64 {
+            std::fs::write(&path, &full[..len as usize]).unwrap();
+            let (mut wal, recovered) = Wal::open(&path, FsyncPolicy::Always).unwrap();
+            let n_valid = ends.iter().filter(|&&end| end <= len).count();
+            assert!(recovered == events[..n_valid], "Truncated at byte {len}");
+
+            // Appends after the valid prefix
+            wal.append(&events[0]).unwrap();
+            drop(wal);
+            let (_, recovered) = Wal::<VectorClock>::open(&path, FsyncPolicy::Always).unwrap();
+            assert_eq!(recovered.len(), n_valid + 1);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn fails_on_corrupt_record_before_end() {
+        let path = temp_path("corrupt");
+        let (mut wal, _) = Wal::open(&path, FsyncPolicy::Never).unwrap();
+        let e = VectorClock::new(1, 2);
+        (0..3).for_each(|_| wal.append(&e).unwrap());
+        wal.sync().unwrap();
+        drop(wal);
+
+        // Flip a bit in the 2nd record's payload, which intact records follow
+        let full = std::fs::read(&path).unwrap();
+        let record_len = full.len() / 3;
+        let mut bytes = full.clone();
+        bytes[record_len + 10] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Wal::<VectorClock>::open(&path, FsyncPolicy::Never).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        // Or in the 2nd record's length, so that it seems to run past the end
+        let mut bytes = full.clone();
+        bytes[record_len + 3] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Wal::<VectorClock>::open(&path, FsyncPolicy::Never).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+        // In the last record, it may be torn by a crash
+        let mut bytes = full;
+        bytes[2 * record_len + 10] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+        let (_, recovered) = Wal::<VectorClock>::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(recovered.len(), 2);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len() as usize,
+            2 * record_len
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn restarts_with_monotonic_clock() {
+        let path = temp_path("restart");
+        let mut p = WalProcess::<VectorClock>::open(0, 2, &path, FsyncPolicy::Always).unwrap();
+        let mut q = VecProcess::new(1, 2);
+        p.exec(|| {});
+        let mut e = None;
+        p.send(|ev| e = Some(ev));
+        q.recv(|| e.take().unwrap());
+        let before: Vec<_> = p.events().cloned().collect();
+        drop(p); // crash
+
+        // Recovers the last clock, so merging the reply does not break p's own clock's invariant
+        let mut p = WalProcess::<VectorClock>::open(0, 2, &path, FsyncPolicy::Always).unwrap();
+        assert!(p.events().cloned().collect::<Vec<_>>() == before);
+        q.send(|ev| e = Some(ev));
+        p.recv(|| e.take().unwrap());
+        assert!(before.iter().all(|s| s < p.last_event().unwrap()));
+        assert!(q.last_event().unwrap() < p.last_event().unwrap());
+        drop(p);
+
+        // Refuses to recover another process from it
+        assert!(WalProcess::<VectorClock>::open(1, 2, &path, FsyncPolicy::Always).is_err());
+        assert!(WalProcess::<VectorClock>::open(0, 3, &path, FsyncPolicy::Always).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rads-wal-{name}-{}", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        path
+    }
+}
@@ -0,0 +1,161 @@
+use crate::stabilize::Protocol;
+
+// Distance from the root, and parent unless the root
+pub type State = (usize, Option<usize>);
+
+/// Self-stabilizing BFS spanning tree, after Dolev, Israeli & Moran, over a connected graph of `n` nodes.
+///
+/// The root sets its distance to 0, and any other node to one more than the least distance of its neighbours, capped
+/// at `n`, with the lowest such neighbour as its parent. Corrupted distances that are too small grow by at least one per
+/// round, while true distances spread from the root, so under a synchronous daemon every distance is correct after
+/// `D + 1` rounds, and every parent after `D + 2`, where `D` is the depth of the tree. It converges under any daemon,
+/// and is then silent, as no node is enabled.
+///
+/// # Examples
+/// ```
+/// use rads::stabilize::bfs_tree::BfsTree;
+/// use rads::stabilize::{Daemon, System};
+///
+/// // 0 - 1 - 2 - 3 - 0, with corrupted distances and parents
+/// let adj = vec![vec![1, 3], vec![0, 2], vec![1, 3], vec![2, 0]];
+/// let mut sys = System::new(BfsTree::new(adj, 0), vec![(2, Some(1)), (0, None), (1, Some(1)), (0, Some(0))]);
+/// sys.stabilize(Daemon::Synchronous, &mut rand::thread_rng(), 5).unwrap();
+/// assert_eq!(sys.states(), [(0, None), (1, Some(0)), (2, Some(1)), (1, Some(0))]);
+/// ```
+pub struct BfsTree {
+    // Node i has neighbours adj[i]
+    adj: Vec<Vec<usize>>,
+    root: usize,
+}
+
+impl BfsTree {
+    pub fn new(adj: Vec<Vec<usize>>, root: usize) -> Self {
+        Self { adj, root }
+    }
+    pub fn root(&self) -> usize {
+        self.root
+    }
+    fn target(&self, i: usize, states: &[State]) -> State {
+        if i == self.root {
+            return (0, None);
+        }
+        let n = self.adj.len();
+        match (self.adj[i].iter()).min_by_key(|&&j| (states[j].0, j)) {
+            Some(&j) => ((states[j].0 + 1).min(n), Some(j)),
+            None => (n, None),
+        }
+    }
+}
+
+impl Protocol for BfsTree {
+    type State = State;
+
+    fn step(&self, i: usize, states: &[State]) -> Option<State> {
+        let target = self.target(i, states);
+        (states[i] != target).then_some(target)
+    }
+    fn is_legitimate(&self, states: &[State]) -> bool {
+        (0..states.len()).all(|i| self.step(i, states).is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stabilize::bfs_tree::BfsTree;
+    use crate::stabilize::{Daemon, System};
+    use rand::Rng;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn converges_to_bfs_tree() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..300 {
+            let n = rng.gen_range(1..=15);
+            let p = rng.gen_range(0.0..0.5);
+            let adj = rand_graph(&mut rng, n, p);
+            let root = rng.gen_range(0..n);
+            let states = (0..n)
+                .map(|_| {
+                    (
+                        rng.gen_range(0..=n),
+                        Some(rng.gen_range(0..n)).filter(|_| rng.gen()),
+                    )
+                })
+                .collect();
+            let mut sys = System::new(BfsTree::new(adj.clone(), root), states);
+            let daemon = [
+                Daemon::Central,
+                Daemon::Distributed,
+                Daemon::Synchronous,
+                Daemon::Adversarial,
+            ][rng.gen_range(0..4)];
+            let n_steps = sys.stabilize(daemon, &mut rng, 100_000).unwrap();
+            let dist = bfs(&adj, root);
+            let depth = dist.iter().max().unwrap();
+            if daemon == Daemon::Synchronous {
+                assert!(n_steps <= depth + 2, "{n_steps} rounds for depth {depth}");
+            }
+            for (i, &(d, parent)) in sys.states().iter().enumerate() {
+                assert_eq!(d, dist[i]);
+                match parent {
+                    None => assert_eq!(i, root),
+                    Some(j) => assert_eq!(dist[j] + 1, d),
+                }
+            }
+            assert!(!sys.step(daemon, &mut rng));
+        }
+    }
+
+    #[test]
+    fn worst_case_rounds() {
+        // A path rooted at one end, where every corrupted distance is 0, so the last node counts up to n - 1
+        let mut rng = rand::thread_rng();
+        for n in 2..=20 {
+            let adj = (0..n)
+                .map(|i: usize| {
+                    (i.saturating_sub(1)..(i + 2).min(n))
+                        .filter(|&j| j != i)
+                        .collect()
+                })
+                .collect();
+            let states = vec![(0, None); n];
+            let mut sys = System::new(BfsTree::new(adj, 0), states);
+            let n_steps = sys.stabilize(Daemon::Synchronous, &mut rng, n + 1).unwrap();
+            assert_eq!(n_steps, n - 1);
+        }
+    }
+
+    // Random connected graph, as a random tree with extra edges
+    fn rand_graph(rng: &mut impl Rng, n: usize, p: f64) -> Vec<Vec<usize>> {
+        let mut adj = vec![Vec::new(); n];
+        for i in 1..n {
+            let j = rng.gen_range(0..i);
+            adj[i].push(j);
+            adj[j].push(i);
+        }
+        for i in 0..n {
+            for j in 0..i {
+                if !adj[i].contains(&j) && rng.gen_bool(p) {
+                    adj[i].push(j);
+                    adj[j].push(i);
+                }
+            }
+        }
+        adj
+    }
+
+    fn bfs(adj: &[Vec<usize>], root: usize) -> Vec<usize> {
+        let mut dist = vec![usize::MAX; adj.len()];
+        dist[root] = 0;
+        let mut queue = VecDeque::from([root]);
+        while let Some(i) = queue.pop_front() {
+            for &j in &adj[i] {
+                if dist[j] == usize::MAX {
+                    dist[j] = dist[i] + 1;
+                    queue.push_back(j);
+                }
+            }
+        }
+        dist
+    }
+}
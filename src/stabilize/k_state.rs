@@ -0,0 +1,105 @@
+use crate::stabilize::Protocol;
+
+/// Dijkstra's K-state mutual exclusion on a unidirectional ring of `n` nodes, where node `i` reads node `i - 1`.
+///
+/// Node `i` holds the privilege, or token, if its guard holds, and passes it on by moving. Node 0 is privileged if it
+/// equals node `n - 1`, and moves to the next state mod `K`, while any other node is privileged if it differs from its
+/// predecessor, and copies it. So some node is always privileged, and with `K >= n` states, node 0 eventually takes a
+/// state no other node has. Then exactly one node is privileged, under any central daemon, within `O(n^2)` moves.
+///
+/// # Examples
+/// ```
+/// use rads::stabilize::k_state::KState;
+/// use rads::stabilize::{Daemon, System};
+///
+/// let mut sys = System::new(KState::new(3, 3), vec![0, 0, 0]);
+/// assert_eq!(sys.enabled(), vec![0]);
+/// sys.step(Daemon::Central, &mut rand::thread_rng());
+/// assert_eq!(sys.states(), [1, 0, 0]);
+/// assert_eq!(sys.enabled(), vec![1]);
+/// ```
+pub struct KState {
+    n: usize,
+    k: usize,
+}
+
+impl KState {
+    pub fn new(n: usize, k: usize) -> Self {
+        assert!(k >= n, "{k} states for {n} nodes");
+        Self { n, k }
+    }
+    pub fn is_privileged(&self, i: usize, states: &[usize]) -> bool {
+        match i {
+            0 => states[0] == states[self.n - 1],
+            _ => states[i] != states[i - 1],
+        }
+    }
+}
+
+impl Protocol for KState {
+    type State = usize;
+
+    fn step(&self, i: usize, states: &[usize]) -> Option<usize> {
+        match i {
+            _ if !self.is_privileged(i, states) => None,
+            0 => Some((states[0] + 1) % self.k),
+            _ => Some(states[i - 1]),
+        }
+    }
+    // Exactly one node is privileged
+    fn is_legitimate(&self, states: &[usize]) -> bool {
+        (0..self.n)
+            .filter(|&i| self.is_privileged(i, states))
+            .count()
+            == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stabilize::k_state::KState;
+    use crate::stabilize::{Daemon, System};
+    use rand::Rng;
+
+    #[test]
+    fn stabilizes_and_circulates_token() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let n = rng.gen_range(1..=12);
+            let k = rng.gen_range(n..=2 * n);
+            let states = (0..n).map(|_| rng.gen_range(0..k)).collect();
+            let mut sys = System::new(KState::new(n, k), states);
+            let daemon = [Daemon::Central, Daemon::Adversarial][rng.gen_range(0..2)];
+            sys.stabilize(daemon, &mut rng, 2 * n * n).unwrap();
+            assert!(
+                sys.n_moves() <= 2 * n * n,
+                "{} moves for n={n}",
+                sys.n_moves()
+            );
+
+            // Closure, as the token passes from each node to the next
+            let mut holder = sys.enabled()[0];
+            for _ in 0..3 * n {
+                sys.step(daemon, &mut rng);
+                assert!(sys.is_legitimate());
+                assert_eq!(sys.enabled(), vec![(holder + 1) % n]);
+                holder = (holder + 1) % n;
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_node_restabilizes() {
+        let mut rng = rand::thread_rng();
+        let n = 10;
+        let mut sys = System::new(KState::new(n, n), vec![0; n]);
+        for _ in 0..100 {
+            sys.set(rng.gen_range(0..n), rng.gen_range(0..n));
+            sys.stabilize(Daemon::Adversarial, &mut rng, 2 * n * n)
+                .unwrap();
+            (0..rng.gen_range(0..n)).for_each(|_| {
+                sys.step(Daemon::Central, &mut rng);
+            });
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub mod bfs_tree;
+pub mod k_state;
+
+/// Self-stabilizing algorithm in the state-reading model, where each node moves by reading its neighbours' states.
+///
+/// From any initial state, e.g. after transient faults corrupt memory, the system converges to a legitimate state under
+/// the daemon that schedules moves, and stays legitimate.
+pub trait Protocol {
+    type State: Clone;
+
+    // New state of node i if its guard holds, reading only the states of its neighbours
+    fn step(&self, i: usize, states: &[Self::State]) -> Option<Self::State>;
+    fn is_legitimate(&self, states: &[Self::State]) -> bool;
+}
+
+// Scheduler that chooses which enabled nodes move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Daemon {
+    // A random enabled node
+    Central,
+    // A random non-empty subset of enabled nodes, simultaneously
+    Distributed,
+    // Every enabled node, simultaneously
+    Synchronous,
+    // An enabled node whose move keeps the system illegitimate with the most enabled nodes, if possible
+    Adversarial,
+}
+
+/// System of nodes running a protocol, where the daemon chooses the moves.
+///
+/// # Examples
+/// ```
+/// use rads::stabilize::k_state::KState;
+/// use rads::stabilize::{Daemon, System};
+///
+/// let mut rng = rand::thread_rng();
+/// let mut sys = System::new(KState::new(5, 5), vec![3, 1, 4, 1, 0]);
+/// assert!(!sys.is_legitimate());
+/// sys.stabilize(Daemon::Central, &mut rng, 1000).unwrap();
+/// assert!(sys.is_legitimate());
+/// ```
+pub struct System<P: Protocol> {
+    protocol: P,
+    states: Vec<P::State>,
+    n_moves: usize,
+}
+
+impl<P: Protocol> System<P> {
+    pub fn new(protocol: P, states: Vec<P::State>) -> Self {
+        Self {
+            protocol,
+            states,
+            n_moves: 0,
+        }
+    }
+    pub fn protocol(&self) -> &P {
+        &self.protocol
+    }
+    pub fn states(&self) -> &[P::State] {
+        &self.states
+    }
+    // Corrupts the state of node i
+    pub fn set(&mut self, i: usize, s: P::State) {
+        self.states[i] = s;
+    }
+    pub fn n_moves(&self) -> usize {
+        self.n_moves
+    }
+    pub fn is_legitimate(&self) -> bool {
+        self.protocol.is_legitimate(&self.states)
+    }
+    pub fn enabled(&self) -> Vec<usize> {
+        (0..self.states.len())
+            .filter(|&i| self.protocol.step(i, &self.states).is_some())
+            .collect()
+    }
+
+    // Moves the nodes chosen by the daemon, returning false if no node is enabled
+    pub fn step<R: Rng>(&mut self, daemon: Daemon, rng: &mut R) -> bool {
+        let enabled = self.enabled();
+        if enabled.is_empty() {
+            return false;
+        }
+        let chosen = match daemon {
+            Daemon::Central => vec![*enabled.choose(rng).unwrap()],
+            Daemon::Distributed => {
+                let k = rng.gen_range(1..=enabled.len());
+                enabled.choose_multiple(rng, k).copied().collect()
+            }
+            Daemon::Synchronous => enabled,
+            Daemon::Adversarial => vec![self.adversary(&enabled)],
+        };
+        // Every chosen node reads the states before any moves
+        let moves: Vec<_> = (chosen.into_iter())
+            .map(|i| (i, self.protocol.step(i, &self.states).unwrap()))
+            .collect();
+        self.n_moves += moves.len();
+        moves.into_iter().for_each(|(i, s)| self.states[i] = s);
+        true
+    }
+    // Steps until legitimate, returning the number of steps, or None if not within max_steps
+    pub fn stabilize<R: Rng>(
+        &mut self,
+        daemon: Daemon,
+        rng: &mut R,
+        max_steps: usize,
+    ) -> Option<usize> {
+        for n_steps in 0..=max_steps {
+            if self.is_legitimate() {
+                return Some(n_steps);
+            }
+            if !self.step(daemon, rng) {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn adversary(&self, enabled: &[usize]) -> usize {
+        let mut states = self.states.clone();
+        (enabled.iter().copied())
+            .max_by_key(|&i| {
+                states[i] = self.protocol.step(i, &self.states).unwrap();
+                let key = (
+                    !self.protocol.is_legitimate(&states),
+                    self.count_enabled(&states),
+                );
+                states[i] = self.states[i].clone();
+                key
+            })
+            .unwrap()
+    }
+    fn count_enabled(&self, states: &[P::State]) -> usize {
+        (0..states.len())
+            .filter(|&i| self.protocol.step(i, states).is_some())
+            .count()
+    }
+}
@@ -1,3 +1,4 @@
+use super::race_detector::{SharedLock, Traced, Tracer};
 use super::NoStarveMutex;
 use crate::sync::WantGuard;
 use std::sync::atomic::Ordering;
@@ -61,6 +62,15 @@ impl BakeryN {
             bakery: bakery.clone(),
         }
     }
+    // Traced by the thread's tracer, so that critical sections are ordered by happens before
+    pub fn traced(n: usize, bakery: &std::sync::Arc<Bakery>, tracer: Tracer) -> Traced<Self> {
+        Traced::new(Self::new(n, bakery), tracer)
+    }
+}
+impl SharedLock for BakeryN {
+    fn lock_id(&self) -> usize {
+        std::sync::Arc::as_ptr(&self.bakery) as usize
+    }
 }
 impl<'a> NoStarveMutex<'a, BakeryGuard<'a>, BakeryWant<'a>> for BakeryN {
     fn want_lock(&'a mut self) -> BakeryWant<'a> {
@@ -1,5 +1,6 @@
 pub mod lamports_bakery;
 pub mod peterson;
+pub mod race_detector;
 
 /// Starvation Free Mutex allows for realtime / bounded wait for a critical section.
 ///
@@ -1,3 +1,4 @@
+use super::race_detector::{SharedLock, Traced, Tracer};
 use super::{NoStarveMutex, WantGuard};
 use std::sync::atomic::Ordering;
 
@@ -49,6 +50,22 @@ impl Peterson {
         let p = std::sync::Arc::new(Peterson::default());
         (PetersonA(p.clone()), PetersonB(p))
     }
+    // Traces each half by its own thread's tracer, so that critical sections are ordered by happens before
+    pub fn traced_binary_mutex(a: Tracer, b: Tracer) -> (Traced<PetersonA>, Traced<PetersonB>) {
+        let (mu_a, mu_b) = Self::binary_mutex();
+        (Traced::new(mu_a, a), Traced::new(mu_b, b))
+    }
+}
+
+impl SharedLock for PetersonA {
+    fn lock_id(&self) -> usize {
+        std::sync::Arc::as_ptr(&self.0) as usize
+    }
+}
+impl SharedLock for PetersonB {
+    fn lock_id(&self) -> usize {
+        std::sync::Arc::as_ptr(&self.0) as usize
+    }
 }
 
 impl<'a> NoStarveMutex<'a, PetersonAGuard<'a>, PetersonAWantGuard<'a>> for PetersonA {
@@ -0,0 +1,481 @@
+use super::{NoStarveMutex, WantGuard};
+use crate::order::vector_clock::VectorClock;
+use crate::order::LogicalClock;
+use std::collections::HashMap;
+
+/// FastTrack data race detector for instrumented threads.
+///
+/// Each thread and lock has a vector clock, where acquiring a lock merges the clock of its last release, like receiving
+/// a message. Two accesses to the same address race if they are not ordered by happens before and one is a write.
+///
+/// Most accesses are totally ordered, so variables only store the epoch `c@t` of their last write and read instead of
+/// a vector clock, in `O(1)` time and space. Reads fall back to `O(n)` once concurrent reads are shared by threads.
+///
+/// # Examples
+/// ```
+/// use rads::sync::race_detector::RaceDetector;
+///
+/// let mut d = RaceDetector::new(2);
+/// d.on_write(0, 0xbeef);
+/// d.fork(0, 1);
+/// d.on_read(1, 0xbeef); // ordered by fork
+/// d.on_write(0, 0xbeef); // but not by join
+/// assert_eq!(d.races().len(), 1);
+/// ```
+pub struct RaceDetector {
+    threads: Vec<VectorClock>,
+    locks: HashMap<usize, VectorClock>,
+    vars: HashMap<usize, VarState>,
+    races: Vec<Race>,
+}
+
+// The clk-th event of thread tid
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Epoch {
+    pub tid: usize,
+    pub clk: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+// Racing pair of accesses to addr, where prev was traced before next
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Race {
+    pub addr: usize,
+    pub prev: (Access, Epoch),
+    pub next: (Access, Epoch),
+}
+
+#[derive(Default)]
+struct VarState {
+    write: Epoch,
+    read: Reads,
+}
+
+enum Reads {
+    Exclusive(Epoch),
+    // Last read clock of each thread, 0 if none
+    Shared(Vec<usize>),
+}
+
+impl Epoch {
+    // 0@0 happens before everything, since clocks start from 1
+    const NONE: Epoch = Epoch { tid: 0, clk: 0 };
+    fn happens_before(&self, c: &VectorClock) -> bool {
+        self.clk <= c[self.tid]
+    }
+}
+
+impl Default for Epoch {
+    fn default() -> Self {
+        Epoch::NONE
+    }
+}
+
+impl Default for Reads {
+    fn default() -> Self {
+        Reads::Exclusive(Epoch::NONE)
+    }
+}
+
+impl RaceDetector {
+    pub fn new(n_threads: usize) -> Self {
+        Self {
+            threads: (0..n_threads)
+                .map(|t| VectorClock::new(t, n_threads))
+                .collect(),
+            locks: HashMap::new(),
+            vars: HashMap::new(),
+            races: Vec::new(),
+        }
+    }
+
+    pub fn races(&self) -> &[Race] {
+        &self.races
+    }
+
+    fn epoch(&self, t: usize) -> Epoch {
+        Epoch {
+            tid: t,
+            clk: self.threads[t][t],
+        }
+    }
+
+    pub fn on_read(&mut self, t: usize, addr: usize) {
+        let (c, e) = (&self.threads[t], self.epoch(t));
+        let v = self.vars.entry(addr).or_default();
+        match &mut v.read {
+            Reads::Exclusive(r) if *r == e => return,
+            Reads::Shared(rs) if rs[t] == e.clk => return,
+            _ => {}
+        }
+        if !v.write.happens_before(c) {
+            self.races.push(Race {
+                addr,
+                prev: (Access::Write, v.write),
+                next: (Access::Read, e),
+            });
+        }
+        match &mut v.read {
+            Reads::Shared(rs) => rs[t] = e.clk,
+            Reads::Exclusive(r) if r.happens_before(c) => *r = e,
+            Reads::Exclusive(r) => {
+                let mut rs = vec![0; self.threads.len()];
+                rs[r.tid] = r.clk;
+                rs[t] = e.clk;
+                v.read = Reads::Shared(rs);
+            }
+        }
+    }
+
+    pub fn on_write(&mut self, t: usize, addr: usize) {
+        let (c, e) = (&self.threads[t], self.epoch(t));
+        let v = self.vars.entry(addr).or_default();
+        if v.write == e {
+            return;
+        }
+        let next = (Access::Write, e);
+        if !v.write.happens_before(c) {
+            self.races.push(Race {
+                addr,
+                prev: (Access::Write, v.write),
+                next,
+            });
+        }
+        match &v.read {
+            Reads::Exclusive(r) if !r.happens_before(c) => self.races.push(Race {
+                addr,
+                prev: (Access::Read, *r),
+                next,
+            }),
+            Reads::Exclusive(_) => {}
+            Reads::Shared(rs) => {
+                let racing = (rs.iter().enumerate())
+                    .map(|(tid, &clk)| Epoch { tid, clk })
+                    .filter(|r| !r.happens_before(c))
+                    .map(|r| Race {
+                        addr,
+                        prev: (Access::Read, r),
+                        next,
+                    });
+                self.races.extend(racing);
+                // Later accesses must be ordered after this write anyway
+                v.read = Reads::default();
+            }
+        }
+        v.write = e;
+    }
+
+    pub fn on_acquire(&mut self, t: usize, lock: usize) {
+        if let Some(l) = self.locks.get(&lock) {
+            self.threads[t] = self.threads[t].merge(l);
+        }
+    }
+
+    pub fn on_release(&mut self, t: usize, lock: usize) {
+        self.locks.insert(lock, self.threads[t].clone());
+        self.threads[t] = self.threads[t].extend();
+    }
+
+    // Thread t spawns thread u
+    pub fn fork(&mut self, t: usize, u: usize) {
+        self.threads[u] = self.threads[u].merge(&self.threads[t]);
+        self.threads[t] = self.threads[t].extend();
+    }
+
+    // Thread t waits for thread u to finish
+    pub fn join(&mut self, t: usize, u: usize) {
+        self.threads[t] = self.threads[t].merge(&self.threads[u]);
+        self.threads[u] = self.threads[u].extend();
+    }
+}
+
+/// Handle for a thread to trace its own accesses to a shared `RaceDetector`.
+#[derive(Clone)]
+pub struct Tracer {
+    tid: usize,
+    detector: std::sync::Arc<std::sync::Mutex<RaceDetector>>,
+}
+
+impl Tracer {
+    // Traces the main thread 0, which forks the other threads
+    pub fn new(n_threads: usize) -> Self {
+        Self {
+            tid: 0,
+            detector: std::sync::Arc::new(std::sync::Mutex::new(RaceDetector::new(n_threads))),
+        }
+    }
+    pub fn tid(&self) -> usize {
+        self.tid
+    }
+    pub fn races(&self) -> Vec<Race> {
+        self.detector.lock().unwrap().races().to_vec()
+    }
+    pub fn on_read(&self, addr: usize) {
+        self.detector.lock().unwrap().on_read(self.tid, addr);
+    }
+    pub fn on_write(&self, addr: usize) {
+        self.detector.lock().unwrap().on_write(self.tid, addr);
+    }
+    pub fn on_acquire(&self, lock: usize) {
+        self.detector.lock().unwrap().on_acquire(self.tid, lock);
+    }
+    pub fn on_release(&self, lock: usize) {
+        self.detector.lock().unwrap().on_release(self.tid, lock);
+    }
+    // Call before spawning thread u, which traces with the returned handle
+    pub fn fork(&self, u: usize) -> Tracer {
+        self.detector.lock().unwrap().fork(self.tid, u);
+        Self {
+            tid: u,
+            detector: self.detector.clone(),
+        }
+    }
+    // Call after joining the thread traced by `child`
+    pub fn join(&self, child: Tracer) {
+        self.detector.lock().unwrap().join(self.tid, child.tid);
+    }
+}
+
+/// Mutex handle that shares its lock with the other handles, which identifies the lock when tracing.
+pub trait SharedLock {
+    // Same for every handle of the lock, e.g. the address of their shared state
+    fn lock_id(&self) -> usize;
+}
+
+/// Mutex that traces acquire and release, so that critical sections are ordered by happens before.
+///
+/// # Examples
+/// ```
+/// use rads::sync::peterson::Peterson;
+/// use rads::sync::race_detector::Tracer;
+/// use rads::sync::NoStarveMutex;
+///
+/// let main = Tracer::new(2);
+/// let (mut mu_a, mut mu_b) = Peterson::traced_binary_mutex(main.fork(1), main.clone());
+/// let th = std::thread::spawn(move || {
+///     mu_a.lock().tracer().on_write(0xbeef);
+///     mu_a.into_tracer()
+/// });
+/// mu_b.lock().tracer().on_write(0xbeef);
+/// main.join(th.join().unwrap());
+/// assert!(main.races().is_empty());
+/// ```
+pub struct Traced<M> {
+    mu: M,
+    tracer: Tracer,
+    lock: usize,
+}
+pub struct TracedWant<'a, W> {
+    want: Option<W>,
+    tracer: &'a Tracer,
+    lock: usize,
+}
+pub struct TracedGuard<'a, G> {
+    _guard: G,
+    tracer: &'a Tracer,
+    lock: usize,
+}
+
+impl<M: SharedLock> Traced<M> {
+    pub fn new(mu: M, tracer: Tracer) -> Self {
+        let lock = mu.lock_id();
+        Self { mu, tracer, lock }
+    }
+}
+
+impl<M> Traced<M> {
+    pub fn tracer(&self) -> &Tracer {
+        &self.tracer
+    }
+    pub fn into_tracer(self) -> Tracer {
+        self.tracer
+    }
+}
+
+// Same bounds as the traced mutex
+#[allow(drop_bounds)]
+impl<'a, M, G, W> NoStarveMutex<'a, TracedGuard<'a, G>, TracedWant<'a, W>> for Traced<M>
+where
+    M: NoStarveMutex<'a, G, W>,
+    G: Drop + 'a,
+    W: WantGuard<'a, G> + 'a,
+{
+    fn want_lock(&'a mut self) -> TracedWant<'a, W> {
+        let Self { mu, tracer, lock } = self;
+        TracedWant {
+            want: Some(mu.want_lock()),
+            tracer,
+            lock: *lock,
+        }
+    }
+}
+
+#[allow(drop_bounds)]
+impl<'a, G, W> WantGuard<'a, TracedGuard<'a, G>> for TracedWant<'a, W>
+where
+    G: Drop + 'a,
+    W: WantGuard<'a, G>,
+{
+    fn wait(mut self) -> TracedGuard<'a, G> {
+        let guard = self.want.take().unwrap().wait();
+        self.tracer.on_acquire(self.lock);
+        TracedGuard {
+            _guard: guard,
+            tracer: self.tracer,
+            lock: self.lock,
+        }
+    }
+}
+impl<G> TracedGuard<'_, G> {
+    // Traces accesses within the critical section
+    pub fn tracer(&self) -> &Tracer {
+        self.tracer
+    }
+}
+impl<W> Drop for TracedWant<'_, W> {
+    fn drop(&mut self) {
+        // Untraced, since the inner want releases without entering the critical section
+    }
+}
+impl<G> Drop for TracedGuard<'_, G> {
+    fn drop(&mut self) {
+        // Traced before the inner guard releases the lock
+        self.tracer.on_release(self.lock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync::lamports_bakery::{Bakery, BakeryN};
+    use crate::sync::peterson::Peterson;
+    use crate::sync::race_detector::{Access, Epoch, Race, RaceDetector, Tracer};
+    use crate::sync::NoStarveMutex;
+    const ADDR: usize = 0xbeef;
+
+    #[test]
+    fn unsynchronized_writes() {
+        let main = Tracer::new(3);
+        let ths: Vec<_> = (1..3)
+            .map(|u| {
+                let t = main.fork(u);
+                std::thread::spawn(move || {
+                    t.on_write(ADDR);
+                    t
+                })
+            })
+            .collect();
+        ths.into_iter().for_each(|th| main.join(th.join().unwrap()));
+        let races = main.races();
+        assert_eq!(races.len(), 1, "Got {races:?}");
+        assert_eq!(
+            (races[0].prev.0, races[0].next.0),
+            (Access::Write, Access::Write)
+        );
+
+        // Joined threads happen before
+        main.on_write(ADDR);
+        assert_eq!(main.races().len(), 1);
+    }
+
+    #[test]
+    fn shared_reads_race_with_write() {
+        let mut d = RaceDetector::new(3);
+        d.fork(0, 1);
+        d.fork(0, 2);
+        d.on_read(1, ADDR);
+        d.on_read(2, ADDR);
+        assert!(d.races().is_empty()); // reads never race
+        d.on_write(0, ADDR);
+        let read = |tid| (Access::Read, Epoch { tid, clk: 2 });
+        let write = (Access::Write, Epoch { tid: 0, clk: 3 });
+        assert_eq!(
+            d.races(),
+            &[
+                Race {
+                    addr: ADDR,
+                    prev: read(1),
+                    next: write,
+                },
+                Race {
+                    addr: ADDR,
+                    prev: read(2),
+                    next: write,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn peterson_orders_critical_sections() {
+        let main = Tracer::new(3);
+        let (mut mu_a, mut mu_b) = Peterson::traced_binary_mutex(main.fork(1), main.fork(2));
+        let th_a = std::thread::spawn(move || {
+            for _ in 0..1000 {
+                let guard = mu_a.lock();
+                guard.tracer().on_read(ADDR);
+                guard.tracer().on_write(ADDR);
+            }
+            mu_a.into_tracer()
+        });
+        let th_b = std::thread::spawn(move || {
+            for _ in 0..1000 {
+                let guard = mu_b.lock();
+                guard.tracer().on_read(ADDR);
+                guard.tracer().on_write(ADDR);
+            }
+            mu_b.into_tracer()
+        });
+        main.join(th_a.join().unwrap());
+        main.join(th_b.join().unwrap());
+        assert!(main.races().is_empty(), "Got {:?}", main.races());
+    }
+
+    #[test]
+    fn bakery_orders_critical_sections() {
+        let n_threads = 4;
+        let mu = std::sync::Arc::new(Bakery::new(n_threads));
+        let main = Tracer::new(n_threads + 1);
+        let ths: Vec<_> = (0..n_threads)
+            .map(|n| {
+                let mut mu = BakeryN::traced(n, &mu, main.fork(n + 1));
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        let guard = mu.lock();
+                        guard.tracer().on_write(ADDR);
+                    }
+                    // Unguarded
+                    mu.tracer().on_read(ADDR + 1);
+                    mu.tracer().on_write(ADDR + 1);
+                    mu.into_tracer()
+                })
+            })
+            .collect();
+        ths.into_iter().for_each(|th| main.join(th.join().unwrap()));
+        let races = main.races();
+        assert!(!races.is_empty());
+        assert!(races.iter().all(|r| r.addr == ADDR + 1), "Got {races:?}");
+    }
+
+    #[test]
+    fn distinct_mutexes_do_not_order_each_other() {
+        let main = Tracer::new(3);
+        let (mut mu_a, _) = Peterson::traced_binary_mutex(main.fork(1), main.clone());
+        let (_, mut mu_b) = Peterson::traced_binary_mutex(main.clone(), main.fork(2));
+        let th_a = std::thread::spawn(move || {
+            mu_a.lock().tracer().on_write(ADDR);
+            mu_a.into_tracer()
+        });
+        let th_b = std::thread::spawn(move || {
+            mu_b.lock().tracer().on_write(ADDR);
+            mu_b.into_tracer()
+        });
+        main.join(th_a.join().unwrap());
+        main.join(th_b.join().unwrap());
+        assert_eq!(main.races().len(), 1);
+    }
+}